@@ -1,13 +1,13 @@
 import React, { useEffect, useState } from 'react';
 import { Table, Button, Modal, Form, Input, InputNumber, message } from 'antd';
 import api from '../utils/api';
+import { Money, formatMoney } from '../utils/money';
 
 interface Account {
   id: string;
   name: string;
   account_type: string;
-  balance: number;
-  currency: string;
+  balance: Money;
   remark?: string;
 }
 
@@ -58,8 +58,8 @@ export default function AccountPage() {
         columns={[
           { title: '名称', dataIndex: 'name' },
           { title: '类型', dataIndex: 'account_type' },
-          { title: '余额', dataIndex: 'balance' },
-          { title: '币种', dataIndex: 'currency' },
+          { title: '余额', dataIndex: 'balance', render: (v: Money) => formatMoney(v) },
+          { title: '币种', dataIndex: ['balance', 'currency'] },
           { title: '备注', dataIndex: 'remark' },
         ]}
       />
@@ -3,6 +3,7 @@ import numeral from 'numeral';
 import { Table, Button, Modal, Form, Input, InputNumber, message, Select, DatePicker, Popconfirm, Card, Typography, Space, Row, Col } from 'antd';
 import { Bar, Column } from '@ant-design/charts';
 import api from '../utils/api';
+import { formatAmount, toMajor } from '../utils/money';
 import dayjs from 'dayjs';
 import isBetween from 'dayjs/plugin/isBetween';
 dayjs.extend(isBetween);
@@ -28,7 +29,7 @@ interface Order {
   id: string;
   name: string;
   type: string;
-  amount: number;
+  amount: number; // 主单位金额，由后端的 { minor, currency } 换算
   currency: string;
   remark?: string;
   date: string;
@@ -75,13 +76,12 @@ export default function OrderPage({ onLogout, onSwitchUser }: { onLogout: () =>
       const res = await api.get<any>('/order_query/orders/query');
       // 后端返回对象结构
       if (res.data && Array.isArray(res.data.orders)) {
-        const currencyMap: Record<string, string> = { '人民币': 'CNY', '美元': 'USD', '欧元': 'EUR' };
         let mapped = res.data.orders.map((item: any) => ({
           id: item.id || item._id || '',
           name: item.name || '',
           type: item.type || item.order_type || '',
-          amount: item.amount,
-          currency: currencyMap[item.currency] || item.currency,
+          amount: toMajor(item.amount),
+          currency: item.amount.currency,
           remark: item.remark,
           date: item.date ? (typeof item.date === 'string' ? item.date : dayjs(item.date).format('YYYY-MM-DD')) : '',
         }));
@@ -226,7 +226,7 @@ export default function OrderPage({ onLogout, onSwitchUser }: { onLogout: () =>
           columns={[
             { title: '名称', dataIndex: 'name', align: 'center' },
             { title: '类型', dataIndex: 'type', align: 'center', render: v => <span style={{ color: '#52c41a', fontWeight: 500 }}>{v}</span> },
-            { title: '金额', dataIndex: 'amount', align: 'center', render: v => <span style={{ color: '#faad14', fontWeight: 700 }}>{v}</span> },
+            { title: '金额', dataIndex: 'amount', align: 'center', render: (v, record) => <span style={{ color: '#faad14', fontWeight: 700 }}>{formatAmount(v, record.currency)}</span> },
             { title: '币种', dataIndex: 'currency', align: 'center' },
             { title: '日期', dataIndex: 'date', align: 'center', render: v => v ? dayjs(v).format('YYYY-MM-DD') : '' },
             { title: '备注', dataIndex: 'remark', align: 'center' },
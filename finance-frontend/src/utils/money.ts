@@ -0,0 +1,27 @@
+// 后端金额格式：最小货币单位整数 + ISO 4217 币种，如 { minor: 1234, currency: 'CNY' } 表示 12.34 元
+export interface Money {
+  minor: number;
+  currency: string;
+}
+
+// 币种的小数位数，与后端 Currency::decimals 保持一致
+export function decimalsOf(currency: string) {
+  return currency === 'JPY' || currency === 'KRW' ? 0 : 2;
+}
+
+// 最小单位换算为主单位金额，用于统计和图表
+export function toMajor(money: Money) {
+  return money.minor / 10 ** decimalsOf(money.currency);
+}
+
+// 按币种小数位格式化主单位金额
+export function formatAmount(amount: number, currency: string) {
+  return amount.toLocaleString('zh-CN', {
+    minimumFractionDigits: decimalsOf(currency),
+    maximumFractionDigits: decimalsOf(currency),
+  });
+}
+
+export function formatMoney(money: Money) {
+  return formatAmount(toMajor(money), money.currency);
+}
@@ -1,55 +1,297 @@
-use axum::extract::FromRequestParts;
-use axum::http::{request::Parts, StatusCode};
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::request::Parts;
+use crate::config::{AuthConfig, Config};
+use crate::db::LedgerStore;
+use crate::error::AppError;
+use crate::models::session::Session;
+use crate::models::api_key::{ApiKey, ApiKeyScope};
+use crate::models::ledger::{Ledger, Role};
 // use headers::{Authorization, authorization::Bearer};
 // use async_trait::async_trait;
 use jsonwebtoken::{decode, DecodingKey, Validation, encode, EncodingKey, Header};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
-// use std::env;
-use mongodb::bson::oid::ObjectId;
-
-const SECRET: &[u8] = b"finance_secret_key";
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // user_id
+    pub sid: String, // session_id，会话吊销后令牌随之失效
     pub exp: usize,
 }
 
-pub fn create_jwt(user_id: &ObjectId) -> String {
+/// 访问令牌（短期 JWT）与刷新令牌
+#[derive(Debug, Serialize)]
+pub struct Tokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_in: i64, // 访问令牌有效秒数
+}
+
+pub fn create_jwt(auth: &AuthConfig, user_id: &ObjectId, session_id: &ObjectId) -> String {
     let claims = Claims {
         sub: user_id.to_hex(),
-        exp: chrono::Utc::now().timestamp() as usize + 60 * 60 * 24 * 7, // 7天
+        sid: session_id.to_hex(),
+        exp: (chrono::Utc::now().timestamp() + auth.access_token_ttl_secs) as usize,
+    };
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(auth.jwt_secret.as_bytes())).unwrap()
+}
+
+/// 校验签名和有效期，返回 (user_id, session_id)
+pub fn decode_jwt(auth: &AuthConfig, token: &str) -> Option<(ObjectId, ObjectId)> {
+    let key = DecodingKey::from_secret(auth.jwt_secret.as_bytes());
+    let data = decode::<Claims>(token, &key, &Validation::default()).ok()?;
+    let user_id = ObjectId::parse_str(&data.claims.sub).ok()?;
+    let session_id = ObjectId::parse_str(&data.claims.sid).ok()?;
+    Some((user_id, session_id))
+}
+
+// 两步验证挑战令牌：密码正确后签发，只能用于 `/user/2fa/verify`，不能访问其他接口
+#[derive(Debug, Serialize, Deserialize)]
+struct ChallengeClaims {
+    sub: String,
+    jti: String, // 验证成功后记为已使用，同一挑战令牌不能再换取令牌
+    purpose: String,
+    exp: usize,
+}
+
+const CHALLENGE_PURPOSE: &str = "2fa";
+
+/// 解码后的挑战令牌
+#[derive(Debug)]
+pub struct Challenge {
+    pub user_id: ObjectId,
+    pub jti: String,
+    pub expires_at: DateTime,
+}
+
+pub fn create_challenge(auth: &AuthConfig, user_id: &ObjectId) -> String {
+    let claims = ChallengeClaims {
+        sub: user_id.to_hex(),
+        jti: ObjectId::new().to_hex(),
+        purpose: CHALLENGE_PURPOSE.to_string(),
+        exp: (chrono::Utc::now().timestamp() + auth.challenge_ttl_secs) as usize,
     };
-    encode(&Header::default(), &claims, &EncodingKey::from_secret(SECRET)).unwrap()
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(auth.jwt_secret.as_bytes())).unwrap()
 }
 
-pub fn decode_jwt(token: &str) -> Option<ObjectId> {
-    let data = decode::<Claims>(token, &DecodingKey::from_secret(SECRET), &Validation::default()).ok()?;
-    ObjectId::parse_str(&data.claims.sub).ok()
+pub fn decode_challenge(auth: &AuthConfig, token: &str) -> Option<Challenge> {
+    let key = DecodingKey::from_secret(auth.jwt_secret.as_bytes());
+    let data = decode::<ChallengeClaims>(token, &key, &Validation::default()).ok()?;
+    if data.claims.purpose != CHALLENGE_PURPOSE {
+        return None;
+    }
+    Some(Challenge {
+        user_id: ObjectId::parse_str(&data.claims.sub).ok()?,
+        jti: data.claims.jti,
+        expires_at: DateTime::from_millis(data.claims.exp as i64 * 1000),
+    })
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+// 刷新令牌格式：`<session_id>.<32 字节随机数>`，数据库只保存其哈希
+fn new_refresh_token(session_id: &ObjectId) -> String {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    format!("{}.{}", session_id.to_hex(), hex::encode(secret))
+}
+
+fn expires_at(auth: &AuthConfig) -> DateTime {
+    DateTime::from_millis(DateTime::now().timestamp_millis() + auth.refresh_token_ttl_secs * 1000)
 }
 
+/// 登录成功后创建会话并签发令牌
+pub async fn start_session(db: &dyn LedgerStore, auth: &AuthConfig, user_id: ObjectId) -> Result<Tokens, AppError> {
+    let session_id = ObjectId::new();
+    let refresh_token = new_refresh_token(&session_id);
+    db.create_session(Session {
+        id: session_id,
+        user_id,
+        refresh_hash: hash_token(&refresh_token),
+        previous_hash: None,
+        created_at: DateTime::now(),
+        expires_at: expires_at(auth),
+        revoked: false,
+    }).await?;
+    Ok(Tokens {
+        access_token: create_jwt(auth, &user_id, &session_id),
+        refresh_token,
+        expires_in: auth.access_token_ttl_secs,
+    })
+}
+
+/// 用刷新令牌换取新的令牌对；旧刷新令牌立即作废，被重复使用时吊销整个会话
+pub async fn refresh_session(db: &dyn LedgerStore, auth: &AuthConfig, refresh_token: &str) -> Result<Tokens, AppError> {
+    let invalid = || AppError::unauthorized("刷新令牌无效或已过期");
+    let session_id = refresh_token.split_once('.')
+        .and_then(|(id, _)| ObjectId::parse_str(id).ok())
+        .ok_or_else(invalid)?;
+    let session = db.get_session(session_id).await?.ok_or_else(invalid)?;
+    if !session.is_active() {
+        return Err(invalid());
+    }
+    let hash = hash_token(refresh_token);
+    if session.previous_hash.as_deref() == Some(hash.as_str()) {
+        tracing::warn!("[refresh_session] 刷新令牌被重复使用，吊销会话: {:?}", session_id);
+        db.revoke_session(session.user_id, session_id).await?;
+        return Err(invalid());
+    }
+    let new_token = new_refresh_token(&session_id);
+    if !db.rotate_session(session_id, &hash, hash_token(&new_token), expires_at(auth)).await? {
+        return Err(invalid());
+    }
+    Ok(Tokens {
+        access_token: create_jwt(auth, &session.user_id, &session_id),
+        refresh_token: new_token,
+        expires_in: auth.access_token_ttl_secs,
+    })
+}
+
+/// API 密钥前缀，用于和 JWT 区分
+pub const API_KEY_PREFIX: &str = "ak_";
+/// 除 `Authorization: Bearer` 外，也可以通过该请求头传递 API 密钥
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+// API 密钥格式：`ak_<key_id>.<32 字节随机数>`，与刷新令牌一样只保存哈希
+pub fn new_api_key(key_id: &ObjectId) -> String {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    format!("{}{}.{}", API_KEY_PREFIX, key_id.to_hex(), hex::encode(secret))
+}
+
+/// 校验 API 密钥：存在、哈希一致且未过期
+pub async fn verify_api_key(db: &dyn LedgerStore, key: &str) -> Result<ApiKey, AppError> {
+    let invalid = || AppError::unauthorized("API密钥无效或已过期");
+    let key_id = key.strip_prefix(API_KEY_PREFIX)
+        .and_then(|rest| rest.split_once('.'))
+        .and_then(|(id, _)| ObjectId::parse_str(id).ok())
+        .ok_or_else(invalid)?;
+    let api_key = db.get_api_key(key_id).await?.ok_or_else(invalid)?;
+    if api_key.key_hash != hash_token(key) || api_key.is_expired() {
+        return Err(invalid());
+    }
+    Ok(api_key)
+}
+
+/// 已登录的会话：访问令牌有效且对应会话未被吊销
+pub struct AuthSession {
+    pub user_id: ObjectId,
+    pub session_id: ObjectId,
+}
+
+/// 当前用户，接受访问令牌或 API 密钥；只读密钥只能调用 GET 等安全方法
 pub struct AuthUser(pub ObjectId);
 
 use axum::http::header::AUTHORIZATION;
 
-impl<S> FromRequestParts<S> for AuthUser
+impl<S> FromRequestParts<S> for AuthSession
 where
     S: Send + Sync,
+    Arc<Config>: FromRef<S>,
+    Arc<dyn LedgerStore>: FromRef<S>,
 {
-    type Rejection = (StatusCode, String);
+    type Rejection = AppError;
     fn from_request_parts(
         parts: &mut Parts,
-        _state: &S,
+        state: &S,
     ) -> impl futures::Future<Output = Result<Self, <Self as FromRequestParts<S>>::Rejection>> + Send {
         let headers = parts.headers.clone();
+        let config = Arc::<Config>::from_ref(state);
+        let db = Arc::<dyn LedgerStore>::from_ref(state);
         async move {
             let auth_header = headers.get(AUTHORIZATION)
                 .and_then(|v| v.to_str().ok())
-                .ok_or((StatusCode::UNAUTHORIZED, "缺少或无效的Token".to_string()))?;
+                .ok_or(AppError::unauthorized("缺少或无效的Token"))?;
             let token = auth_header.strip_prefix("Bearer ")
-                .ok_or((StatusCode::UNAUTHORIZED, "Token无效".to_string()))?;
-            let user_id = decode_jwt(token).ok_or((StatusCode::UNAUTHORIZED, "Token无效".to_string()))?;
-            Ok(AuthUser(user_id))
+                .ok_or(AppError::unauthorized("Token无效"))?;
+            let (user_id, session_id) = decode_jwt(&config.auth, token).ok_or(AppError::unauthorized("Token无效"))?;
+            match db.get_session(session_id).await? {
+                Some(session) if session.user_id == user_id && session.is_active() => {}
+                _ => return Err(AppError::unauthorized("登录已失效，请重新登录")),
+            }
+            Ok(AuthSession { user_id, session_id })
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for AuthUser
+where
+    S: Send + Sync,
+    Arc<Config>: FromRef<S>,
+    Arc<dyn LedgerStore>: FromRef<S>,
+{
+    type Rejection = AppError;
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let api_key = parts.headers.get(API_KEY_HEADER)
+            .or_else(|| parts.headers.get(AUTHORIZATION))
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.strip_prefix("Bearer ").unwrap_or(v))
+            .filter(|v| v.starts_with(API_KEY_PREFIX))
+            .map(String::from);
+        if let Some(key) = api_key {
+            let db = Arc::<dyn LedgerStore>::from_ref(state);
+            let api_key = verify_api_key(db.as_ref(), &key).await?;
+            if api_key.scope == ApiKeyScope::ReadOnly && !parts.method.is_safe() {
+                return Err(AppError::forbidden("只读API密钥不能修改数据"));
+            }
+            return Ok(AuthUser(api_key.user_id));
+        }
+        let session = AuthSession::from_request_parts(parts, state).await?;
+        Ok(AuthUser(session.user_id))
+    }
+}
+
+/// 选择账本的请求头，不传时使用用户的默认账本
+pub const LEDGER_HEADER: &str = "x-ledger-id";
+
+/// 用户未指定账本时使用的账本：设置的默认账本；已被删除或已退出时退回到自己拥有的第一个账本
+pub async fn default_ledger(db: &dyn LedgerStore, user_id: ObjectId) -> Result<Option<Ledger>, AppError> {
+    if let Some(ledger_id) = db.get_user(user_id).await?.and_then(|u| u.default_ledger_id)
+        && let Some(ledger) = db.get_ledger(ledger_id).await?.filter(|l| l.role_of(user_id).is_some())
+    {
+        return Ok(Some(ledger));
+    }
+    let ledgers = db.get_ledgers_for_user(user_id).await?;
+    Ok(ledgers.into_iter().find(|l| l.role_of(user_id) == Some(Role::Owner)))
+}
+
+/// 当前用户及其所在的账本；非成员看不到账本，查看者只能调用 GET 等安全方法
+pub struct LedgerMember {
+    pub user_id: ObjectId,
+    pub ledger_id: ObjectId,
+    pub role: Role,
+}
+
+impl<S> FromRequestParts<S> for LedgerMember
+where
+    S: Send + Sync,
+    Arc<Config>: FromRef<S>,
+    Arc<dyn LedgerStore>: FromRef<S>,
+{
+    type Rejection = AppError;
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let AuthUser(user_id) = AuthUser::from_request_parts(parts, state).await?;
+        let db = Arc::<dyn LedgerStore>::from_ref(state);
+        let ledger = match parts.headers.get(LEDGER_HEADER) {
+            Some(v) => {
+                let ledger_id = v.to_str().ok()
+                    .and_then(|v| ObjectId::parse_str(v.trim()).ok())
+                    .ok_or(AppError::validation("账本ID格式错误"))?;
+                db.get_ledger(ledger_id).await?
+            }
+            None => default_ledger(db.as_ref(), user_id).await?,
+        };
+        let (ledger_id, role) = ledger
+            .and_then(|l| Some((l.id, l.role_of(user_id)?)))
+            .ok_or(AppError::not_found("账本不存在"))?;
+        if role < Role::Editor && !parts.method.is_safe() {
+            return Err(AppError::forbidden("查看者不能修改账本数据"));
         }
+        Ok(LedgerMember { user_id, ledger_id, role })
     }
 }
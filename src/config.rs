@@ -0,0 +1,347 @@
+use serde::Deserialize;
+use std::path::Path;
+
+/// 未配置时使用的 JWT 密钥，只允许在 dev 环境下使用
+pub const DEFAULT_JWT_SECRET: &str = "finance_secret_key";
+
+/// 服务配置：先读取 TOML 文件（默认 `config.toml`，可用 `APP_CONFIG` 指定），
+/// 再由 `APP_*` 环境变量覆盖。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub profile: String, // dev / prod
+    pub database: DatabaseConfig,
+    pub server: ServerConfig,
+    pub auth: AuthConfig,
+    pub cors: CorsConfig,
+    pub log: LogConfig,
+    pub notifications: NotificationConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub uri: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub bind: String,
+    pub trust_forwarded_for: bool, // 部署在反向代理后时，用 X-Forwarded-For 识别客户端 IP
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub jwt_secret: String,
+    pub access_token_ttl_secs: i64,  // 访问令牌有效期
+    pub refresh_token_ttl_secs: i64, // 刷新令牌有效期
+    pub password: PasswordPolicy,
+    pub login_limit: LoginLimit,
+    pub challenge_ttl_secs: i64,   // 两步验证挑战令牌有效期
+    pub two_factor_issuer: String, // 身份验证器中显示的应用名称
+}
+
+/// 登录失败限制：同一用户名或 IP 连续失败 `max_failures` 次后开始锁定，
+/// 锁定时长从 `base_lock_secs` 起每多失败一次翻倍，最长 `max_lock_secs`。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoginLimit {
+    pub max_failures: u32,
+    pub base_lock_secs: i64,
+    pub max_lock_secs: i64,
+    pub window_secs: i64, // 距上次失败超过该时长后重新计数
+}
+
+/// 注册和修改密码时的密码强度要求
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasswordPolicy {
+    pub min_length: usize, // bcrypt 只使用前 72 字节，最小长度不能超过 72
+    pub require_letter: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>, // "*" 表示允许任意来源
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    pub level: String, // trace/debug/info/warn/error
+}
+
+/// 预算提醒与通知投递；未配置 webhook 和 smtp 时通知只保存在站内
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub budget_thresholds: Vec<u32>, // 预算使用百分比达到这些值时提醒，为空表示不提醒
+    pub max_attempts: u32,           // 每条通知最多尝试投递的次数
+    pub retry_interval_secs: u64,    // 重新投递失败通知的间隔
+    pub webhook: Option<WebhookConfig>,
+    pub smtp: Option<SmtpConfig>,
+}
+
+/// 以 JSON POST 到 `url`；设置了 `secret` 时在 X-Signature 头中附带请求体的 HMAC-SHA256
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub secret: Option<String>,
+}
+
+/// 通过 SMTP 发送邮件给设置了邮箱的用户
+#[derive(Debug, Clone, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub from: String, // 发件人，如 "账本 <noreply@example.com>"
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(default)]
+    pub starttls: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            profile: "prod".to_string(),
+            database: DatabaseConfig::default(),
+            server: ServerConfig::default(),
+            auth: AuthConfig::default(),
+            cors: CorsConfig::default(),
+            log: LogConfig::default(),
+            notifications: NotificationConfig::default(),
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig { uri: "mongodb://localhost:27017".to_string(), name: "finance".to_string() }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig { bind: "0.0.0.0:3000".to_string(), trust_forwarded_for: false }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            jwt_secret: DEFAULT_JWT_SECRET.to_string(),
+            access_token_ttl_secs: 60 * 15,            // 15分钟，过期后用刷新令牌续期
+            refresh_token_ttl_secs: 60 * 60 * 24 * 30, // 30天
+            password: PasswordPolicy::default(),
+            login_limit: LoginLimit::default(),
+            challenge_ttl_secs: 60 * 5,
+            two_factor_issuer: "AccountBook".to_string(),
+        }
+    }
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy { min_length: 8, require_letter: true, require_digit: true, require_symbol: false }
+    }
+}
+
+impl Default for LoginLimit {
+    fn default() -> Self {
+        LoginLimit { max_failures: 5, base_lock_secs: 30, max_lock_secs: 60 * 60, window_secs: 60 * 60 * 24 }
+    }
+}
+
+impl LoginLimit {
+    /// 失败 `failures` 次后需要锁定的秒数，未达到阈值时为 0
+    pub fn lock_secs(&self, failures: u32) -> i64 {
+        if failures < self.max_failures {
+            return 0;
+        }
+        let doublings = (failures - self.max_failures).min(32);
+        self.base_lock_secs.saturating_mul(1i64 << doublings).min(self.max_lock_secs)
+    }
+}
+
+impl PasswordPolicy {
+    /// bcrypt 会截断超过 72 字节的密码
+    pub const MAX_BYTES: usize = 72;
+
+    /// 检查密码是否满足要求，不满足时返回提示信息
+    pub fn check(&self, password: &str) -> Result<(), String> {
+        if password.chars().count() < self.min_length {
+            return Err(format!("密码长度不能少于 {} 位", self.min_length));
+        }
+        if password.len() > Self::MAX_BYTES {
+            return Err(format!("密码不能超过 {} 字节", Self::MAX_BYTES));
+        }
+        if self.require_letter && !password.chars().any(|c| c.is_alphabetic()) {
+            return Err("密码必须包含字母".to_string());
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            return Err("密码必须包含数字".to_string());
+        }
+        if self.require_symbol && password.chars().all(|c| c.is_alphanumeric()) {
+            return Err("密码必须包含符号".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig { allowed_origins: vec!["*".to_string()] }
+    }
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        NotificationConfig {
+            budget_thresholds: vec![80, 100],
+            max_attempts: 5,
+            retry_interval_secs: 60,
+            webhook: None,
+            smtp: None,
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig { level: "info".to_string() }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("读取配置文件 {path} 失败: {source}")]
+    Read { path: String, source: std::io::Error },
+    #[error("解析配置文件 {path} 失败: {source}")]
+    Parse { path: String, source: toml::de::Error },
+    #[error("环境变量 {key} 的值不合法: {value}")]
+    Env { key: &'static str, value: String },
+    #[error("配置不合法: {0}")]
+    Invalid(String),
+}
+
+impl Config {
+    /// 按“配置文件 → 环境变量”的顺序加载并校验配置
+    pub fn load() -> Result<Config, ConfigError> {
+        let path = std::env::var("APP_CONFIG").unwrap_or_else(|_| "config.toml".to_string());
+        let mut config = if Path::new(&path).exists() {
+            Config::from_file(&path)?
+        } else {
+            Config::default()
+        };
+        config.apply_env(|key| std::env::var(key).ok())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &str) -> Result<Config, ConfigError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|source| ConfigError::Read { path: path.to_string(), source })?;
+        Config::from_toml(&text).map_err(|source| ConfigError::Parse { path: path.to_string(), source })
+    }
+
+    pub fn from_toml(text: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// 用环境变量覆盖配置项，`lookup` 便于测试时替换环境
+    pub fn apply_env(&mut self, lookup: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        fn parse<T: std::str::FromStr>(key: &'static str, value: String) -> Result<T, ConfigError> {
+            value.parse().map_err(|_| ConfigError::Env { key, value })
+        }
+        if let Some(v) = lookup("APP_PROFILE") { self.profile = v; }
+        if let Some(v) = lookup("APP_DATABASE_URI") { self.database.uri = v; }
+        if let Some(v) = lookup("APP_DATABASE_NAME") { self.database.name = v; }
+        if let Some(v) = lookup("APP_BIND") { self.server.bind = v; }
+        if let Some(v) = lookup("APP_TRUST_FORWARDED_FOR") {
+            self.server.trust_forwarded_for = parse("APP_TRUST_FORWARDED_FOR", v)?;
+        }
+        if let Some(v) = lookup("APP_JWT_SECRET") { self.auth.jwt_secret = v; }
+        if let Some(v) = lookup("APP_ACCESS_TOKEN_TTL_SECS") {
+            self.auth.access_token_ttl_secs = parse("APP_ACCESS_TOKEN_TTL_SECS", v)?;
+        }
+        if let Some(v) = lookup("APP_REFRESH_TOKEN_TTL_SECS") {
+            self.auth.refresh_token_ttl_secs = parse("APP_REFRESH_TOKEN_TTL_SECS", v)?;
+        }
+        if let Some(v) = lookup("APP_PASSWORD_MIN_LENGTH") {
+            self.auth.password.min_length = parse("APP_PASSWORD_MIN_LENGTH", v)?;
+        }
+        if let Some(v) = lookup("APP_LOGIN_MAX_FAILURES") {
+            self.auth.login_limit.max_failures = parse("APP_LOGIN_MAX_FAILURES", v)?;
+        }
+        if let Some(v) = lookup("APP_CORS_ORIGINS") {
+            self.cors.allowed_origins = v.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect();
+        }
+        if let Some(v) = lookup("APP_LOG_LEVEL") { self.log.level = v; }
+        if let Some(v) = lookup("APP_BUDGET_ALERT_THRESHOLDS") {
+            self.notifications.budget_thresholds = v.split(',').map(str::trim).filter(|s| !s.is_empty())
+                .map(|s| parse("APP_BUDGET_ALERT_THRESHOLDS", s.to_string()))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(v) = lookup("APP_WEBHOOK_URL") {
+            self.notifications.webhook = Some(WebhookConfig { url: v, secret: lookup("APP_WEBHOOK_SECRET") });
+        }
+        Ok(())
+    }
+
+    pub fn is_dev(&self) -> bool {
+        self.profile == "dev"
+    }
+
+    /// 启动前校验；非 dev 环境下拒绝使用默认或过短的 JWT 密钥
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !self.is_dev() && (self.auth.jwt_secret == DEFAULT_JWT_SECRET || self.auth.jwt_secret.len() < 32) {
+            return Err(ConfigError::Invalid(format!(
+                "{} 环境必须通过 auth.jwt_secret 或 APP_JWT_SECRET 设置至少 32 字节的 JWT 密钥", self.profile,
+            )));
+        }
+        if self.auth.access_token_ttl_secs <= 0 || self.auth.refresh_token_ttl_secs <= 0 || self.auth.challenge_ttl_secs <= 0 {
+            return Err(ConfigError::Invalid("令牌有效期必须大于 0".to_string()));
+        }
+        if !(1..=PasswordPolicy::MAX_BYTES).contains(&self.auth.password.min_length) {
+            return Err(ConfigError::Invalid(format!("密码最小长度必须在 1 到 {} 之间", PasswordPolicy::MAX_BYTES)));
+        }
+        let limit = &self.auth.login_limit;
+        if limit.max_failures == 0 || limit.base_lock_secs <= 0 || limit.max_lock_secs < limit.base_lock_secs || limit.window_secs <= 0 {
+            return Err(ConfigError::Invalid("登录失败限制配置不合法".to_string()));
+        }
+        if self.server.bind.parse::<std::net::SocketAddr>().is_err() {
+            return Err(ConfigError::Invalid(format!("监听地址不合法: {}", self.server.bind)));
+        }
+        if self.log.level.parse::<tracing::Level>().is_err() {
+            return Err(ConfigError::Invalid(format!("日志级别不合法: {}", self.log.level)));
+        }
+        let notifications = &self.notifications;
+        if notifications.budget_thresholds.iter().any(|t| !(1..=1000).contains(t)) {
+            return Err(ConfigError::Invalid("预算提醒阈值必须在 1 到 1000 之间".to_string()));
+        }
+        if notifications.max_attempts == 0 || notifications.retry_interval_secs == 0 {
+            return Err(ConfigError::Invalid("通知投递次数和重试间隔必须大于 0".to_string()));
+        }
+        if let Some(webhook) = &notifications.webhook
+            && !reqwest::Url::parse(&webhook.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+        {
+            return Err(ConfigError::Invalid(format!("webhook 地址不合法: {}", webhook.url)));
+        }
+        if let Some(smtp) = &notifications.smtp && smtp.from.parse::<lettre::message::Mailbox>().is_err() {
+            return Err(ConfigError::Invalid(format!("发件人地址不合法: {}", smtp.from)));
+        }
+        for origin in &self.cors.allowed_origins {
+            if origin != "*" && axum::http::HeaderValue::from_str(origin).is_err() {
+                return Err(ConfigError::Invalid(format!("CORS 来源不合法: {}", origin)));
+            }
+        }
+        Ok(())
+    }
+}
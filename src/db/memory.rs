@@ -0,0 +1,772 @@
+use crate::models::user::{TwoFactor, User};
+use crate::models::account::Account;
+use crate::models::category::{check_parent, check_reassign, Category};
+use crate::models::asset::Asset;
+use crate::models::transaction::Order;
+use crate::models::budget::Budget;
+use crate::models::currency::Currency;
+use crate::models::money::Money;
+use crate::models::exchange_rate::ExchangeRate;
+use crate::models::session::{Session, UsedChallenge};
+use crate::models::api_key::ApiKey;
+use crate::models::login_attempt::LoginAttempt;
+use crate::models::ledger::{Invitation, Ledger, Member, Role};
+use crate::models::notification::Notification;
+use crate::db::{add_balance, DBResult, LedgerStore, Owned, StoreError, OrderFilter, OrderPage, OrderSort, OrderTotal};
+use crate::util::day_start;
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, DateTime};
+use std::sync::RwLock;
+
+#[derive(Default)]
+struct Tables {
+    users: Vec<User>,
+    accounts: Vec<Account>,
+    categories: Vec<Category>,
+    assets: Vec<Asset>,
+    orders: Vec<Order>,
+    budgets: Vec<Budget>,
+    rates: Vec<ExchangeRate>,
+    sessions: Vec<Session>,
+    used_challenges: Vec<UsedChallenge>,
+    api_keys: Vec<ApiKey>,
+    login_attempts: Vec<LoginAttempt>,
+    ledgers: Vec<Ledger>,
+    invitations: Vec<Invitation>,
+    notifications: Vec<Notification>,
+}
+
+/// 纯内存实现，数据随进程结束而丢失，主要用于集成测试。
+#[derive(Default)]
+pub struct MemoryStore {
+    tables: RwLock<Tables>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+// 以下辅助函数只匹配归属于 `owner`（账本或用户）的文档，与 Mongo 实现的 `scoped` 过滤条件一致
+fn find_owned<T: Owned + Clone>(items: &[T], owner: ObjectId, id: ObjectId) -> Option<T> {
+    items.iter().find(|item| item.is_owned(owner, id)).cloned()
+}
+
+fn replace_owned<T: Owned>(items: &mut [T], value: T) -> bool {
+    let Some(current) = items.iter_mut().find(|item| item.is_owned(value.owner(), value.id())) else {
+        return false;
+    };
+    *current = value;
+    true
+}
+
+fn remove_owned<T: Owned>(items: &mut Vec<T>, owner: ObjectId, id: ObjectId) -> Option<T> {
+    let pos = items.iter().position(|item| item.is_owned(owner, id))?;
+    Some(items.remove(pos))
+}
+
+/// 依次应用各账户的余额变动；任一溢出则整体失败，不修改任何账户
+fn apply_balances(accounts: &mut [Account], changes: &[(ObjectId, i64)]) -> DBResult<()> {
+    let mut next: Vec<(usize, Money)> = Vec::new();
+    for &(account_id, delta) in changes {
+        let Some(i) = accounts.iter().position(|a| a.id == account_id) else {
+            continue;
+        };
+        let current = next.iter().rev().find(|(j, _)| *j == i).map_or(accounts[i].balance, |(_, b)| *b);
+        next.push((i, add_balance(current, delta)?));
+    }
+    for (i, balance) in next {
+        accounts[i].balance = balance;
+    }
+    Ok(())
+}
+
+// 删除账本及账本内的全部数据和邀请
+fn drop_ledgers(tables: &mut Tables, ids: &[ObjectId]) {
+    tables.ledgers.retain(|l| !ids.contains(&l.id));
+    tables.accounts.retain(|a| !ids.contains(&a.ledger_id));
+    tables.categories.retain(|c| !ids.contains(&c.ledger_id));
+    tables.assets.retain(|a| !ids.contains(&a.ledger_id));
+    tables.orders.retain(|o| !ids.contains(&o.ledger_id));
+    tables.budgets.retain(|b| !ids.contains(&b.ledger_id));
+    tables.invitations.retain(|i| !ids.contains(&i.ledger_id));
+    tables.notifications.retain(|n| !ids.contains(&n.ledger_id));
+}
+
+#[async_trait]
+impl LedgerStore for MemoryStore {
+    // 用户相关
+    async fn create_user(&self, user: User) -> DBResult<User> {
+        let mut tables = self.tables.write().unwrap();
+        if tables.users.iter().any(|u| u.username == user.username) {
+            return Err(StoreError::Conflict("用户名已存在".to_string()));
+        }
+        tables.users.push(user.clone());
+        Ok(user)
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> DBResult<Option<User>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.users.iter().find(|u| u.username == username).cloned())
+    }
+
+    async fn get_user(&self, user_id: ObjectId) -> DBResult<Option<User>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.users.iter().find(|u| u.id == user_id).cloned())
+    }
+
+    async fn set_base_currency(&self, user_id: ObjectId, currency: Currency) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(user) = tables.users.iter_mut().find(|u| u.id == user_id) else {
+            return Ok(false);
+        };
+        user.base_currency = currency;
+        Ok(true)
+    }
+
+    async fn set_email(&self, user_id: ObjectId, email: Option<String>) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(user) = tables.users.iter_mut().find(|u| u.id == user_id) else {
+            return Ok(false);
+        };
+        user.email = email;
+        Ok(true)
+    }
+
+    async fn set_default_ledger(&self, user_id: ObjectId, ledger_id: ObjectId) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(user) = tables.users.iter_mut().find(|u| u.id == user_id) else {
+            return Ok(false);
+        };
+        user.default_ledger_id = Some(ledger_id);
+        Ok(true)
+    }
+
+    async fn set_password(&self, user_id: ObjectId, password_hash: String) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(user) = tables.users.iter_mut().find(|u| u.id == user_id) else {
+            return Ok(false);
+        };
+        user.password = password_hash;
+        Ok(true)
+    }
+
+    async fn set_two_factor(&self, user_id: ObjectId, two_factor: Option<TwoFactor>) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(user) = tables.users.iter_mut().find(|u| u.id == user_id) else {
+            return Ok(false);
+        };
+        user.two_factor = two_factor;
+        Ok(true)
+    }
+
+    async fn use_totp_step(&self, user_id: ObjectId, step: i64) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(two_factor) = tables.users.iter_mut()
+            .find(|u| u.id == user_id)
+            .and_then(|u| u.two_factor.as_mut())
+            .filter(|t| t.last_step < step) else {
+            return Ok(false);
+        };
+        two_factor.last_step = step;
+        Ok(true)
+    }
+
+    async fn use_recovery_code(&self, user_id: ObjectId, code_hash: &str) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(two_factor) = tables.users.iter_mut().find(|u| u.id == user_id).and_then(|u| u.two_factor.as_mut()) else {
+            return Ok(false);
+        };
+        let Some(pos) = two_factor.recovery_hashes.iter().position(|h| h == code_hash) else {
+            return Ok(false);
+        };
+        two_factor.recovery_hashes.remove(pos);
+        Ok(true)
+    }
+
+    async fn rename_user(&self, user_id: ObjectId, username: String) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        if tables.users.iter().any(|u| u.username == username && u.id != user_id) {
+            return Err(StoreError::Conflict("用户名已存在".to_string()));
+        }
+        let Some(user) = tables.users.iter_mut().find(|u| u.id == user_id) else {
+            return Ok(false);
+        };
+        user.username = username;
+        Ok(true)
+    }
+
+    async fn delete_user(&self, user_id: ObjectId) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(pos) = tables.users.iter().position(|u| u.id == user_id) else {
+            return Ok(false);
+        };
+        tables.users.remove(pos);
+        let mut unshared = Vec::new();
+        for ledger in tables.ledgers.iter_mut().filter(|l| l.role_of(user_id) == Some(Role::Owner)) {
+            match ledger.successor() {
+                Some(successor) => ledger.members.iter_mut()
+                    .filter(|m| m.user_id == successor)
+                    .for_each(|m| m.role = Role::Owner),
+                None => unshared.push(ledger.id),
+            }
+        }
+        drop_ledgers(&mut tables, &unshared);
+        for ledger in tables.ledgers.iter_mut() {
+            ledger.members.retain(|m| m.user_id != user_id);
+        }
+        tables.invitations.retain(|i| i.invitee_id != user_id && i.inviter_id != user_id);
+        tables.rates.retain(|r| r.user_id != user_id);
+        tables.sessions.retain(|s| s.user_id != user_id);
+        tables.api_keys.retain(|k| k.user_id != user_id);
+        tables.notifications.retain(|n| n.user_id != user_id);
+        Ok(true)
+    }
+
+    // 账户相关
+    async fn create_account(&self, account: Account) -> DBResult<Account> {
+        self.tables.write().unwrap().accounts.push(account.clone());
+        Ok(account)
+    }
+
+    async fn get_accounts_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Account>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.accounts.iter().filter(|a| a.ledger_id == ledger_id).cloned().collect())
+    }
+
+    async fn get_account(&self, ledger_id: ObjectId, account_id: ObjectId) -> DBResult<Option<Account>> {
+        let tables = self.tables.read().unwrap();
+        Ok(find_owned(&tables.accounts, ledger_id, account_id))
+    }
+
+    async fn update_account(&self, account: Account) -> DBResult<Option<Account>> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(current) = tables.accounts.iter_mut().find(|a| a.is_owned(account.ledger_id, account.id)) else {
+            return Ok(None);
+        };
+        let diff = account.initial_balance.minor - current.initial_balance.minor;
+        let balance = add_balance(current.balance, diff)?;
+        *current = Account { balance: Money::new(balance.minor, account.initial_balance.currency), ..account };
+        Ok(Some(current.clone()))
+    }
+
+    async fn delete_account(&self, ledger_id: ObjectId, account_id: ObjectId) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let orders = tables.orders.iter().filter(|o| o.ledger_id == ledger_id && o.account_id == Some(account_id)).count();
+        let assets = tables.assets.iter().filter(|a| a.ledger_id == ledger_id && a.account_id == account_id).count();
+        if orders > 0 || assets > 0 {
+            return Err(StoreError::Conflict(format!("账户仍被 {} 条订单、{} 项资产引用，无法删除", orders, assets)));
+        }
+        Ok(remove_owned(&mut tables.accounts, ledger_id, account_id).is_some())
+    }
+
+    async fn recompute_account_balance(&self, ledger_id: ObjectId, account_id: ObjectId) -> DBResult<Account> {
+        let mut tables = self.tables.write().unwrap();
+        let mut balance = find_owned(&tables.accounts, ledger_id, account_id)
+            .ok_or(StoreError::NotFound("账户"))?
+            .initial_balance;
+        for order in tables.orders.iter().filter(|o| o.ledger_id == ledger_id && o.account_id == Some(account_id)) {
+            balance = add_balance(balance, order.balance_delta())?;
+        }
+        let account = tables.accounts.iter_mut()
+            .find(|a| a.is_owned(ledger_id, account_id))
+            .ok_or(StoreError::NotFound("账户"))?;
+        account.balance = balance;
+        Ok(account.clone())
+    }
+
+    // 分类相关
+    async fn create_category(&self, category: Category) -> DBResult<Category> {
+        self.tables.write().unwrap().categories.push(category.clone());
+        Ok(category)
+    }
+
+    async fn get_categories_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Category>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.categories.iter().filter(|c| c.ledger_id == ledger_id).cloned().collect())
+    }
+
+    async fn get_category(&self, ledger_id: ObjectId, category_id: ObjectId) -> DBResult<Option<Category>> {
+        let tables = self.tables.read().unwrap();
+        Ok(find_owned(&tables.categories, ledger_id, category_id))
+    }
+
+    async fn update_category(&self, category: Category) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let categories: Vec<Category> = tables.categories.iter().filter(|c| c.ledger_id == category.ledger_id).cloned().collect();
+        let Some(current) = categories.iter().find(|c| c.id == category.id) else {
+            return Ok(false);
+        };
+        if let Some(parent_id) = category.parent_id && current.parent_id != Some(parent_id) {
+            check_parent(&categories, category.id, parent_id).map_err(StoreError::Conflict)?;
+        }
+        Ok(replace_owned(&mut tables.categories, category))
+    }
+
+    async fn delete_category(&self, ledger_id: ObjectId, category_id: ObjectId, reassign_to: Option<ObjectId>) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let categories: Vec<Category> = tables.categories.iter().filter(|c| c.ledger_id == ledger_id).cloned().collect();
+        if !categories.iter().any(|c| c.id == category_id) {
+            return Ok(false);
+        }
+        let budgets = tables.budgets.iter().filter(|b| b.ledger_id == ledger_id && b.category_id == category_id).count();
+        let children = categories.iter().filter(|c| c.parent_id == Some(category_id)).count();
+        if let Some(target) = reassign_to {
+            if !categories.iter().any(|c| c.id == target) {
+                return Err(StoreError::NotFound("目标分类"));
+            }
+            check_reassign(&categories, category_id, target).map_err(StoreError::Conflict)?;
+            for budget in tables.budgets.iter_mut().filter(|b| b.ledger_id == ledger_id && b.category_id == category_id) {
+                budget.category_id = target;
+            }
+            for child in tables.categories.iter_mut().filter(|c| c.ledger_id == ledger_id && c.parent_id == Some(category_id)) {
+                child.parent_id = Some(target);
+            }
+        } else if budgets > 0 || children > 0 {
+            return Err(StoreError::Conflict(format!(
+                "分类仍被 {} 个预算、{} 个子分类引用，请指定 reassign_to 转移后再删除", budgets, children,
+            )));
+        }
+        for order in tables.orders.iter_mut().filter(|o| o.ledger_id == ledger_id && o.category_id == Some(category_id)) {
+            order.category_id = reassign_to;
+        }
+        remove_owned(&mut tables.categories, ledger_id, category_id);
+        Ok(true)
+    }
+
+    // 资产相关
+    async fn create_asset(&self, asset: Asset) -> DBResult<Asset> {
+        self.tables.write().unwrap().assets.push(asset.clone());
+        Ok(asset)
+    }
+
+    async fn get_assets_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Asset>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.assets.iter().filter(|a| a.ledger_id == ledger_id).cloned().collect())
+    }
+
+    async fn get_asset(&self, ledger_id: ObjectId, asset_id: ObjectId) -> DBResult<Option<Asset>> {
+        let tables = self.tables.read().unwrap();
+        Ok(find_owned(&tables.assets, ledger_id, asset_id))
+    }
+
+    async fn update_asset(&self, asset: Asset) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        Ok(replace_owned(&mut tables.assets, asset))
+    }
+
+    async fn delete_asset(&self, ledger_id: ObjectId, asset_id: ObjectId) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        Ok(remove_owned(&mut tables.assets, ledger_id, asset_id).is_some())
+    }
+
+    // 订单相关
+    async fn create_order(&self, order: Order) -> DBResult<Order> {
+        let mut tables = self.tables.write().unwrap();
+        if let Some(account_id) = order.account_id {
+            let account = tables.accounts.iter_mut()
+                .find(|a| a.id == account_id && a.ledger_id == order.ledger_id && a.balance.currency == order.amount.currency)
+                .ok_or(StoreError::NotFound("账户"))?;
+            account.balance = add_balance(account.balance, order.balance_delta())?;
+        }
+        tables.orders.push(order.clone());
+        Ok(order)
+    }
+
+    async fn get_orders_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Order>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.orders.iter().filter(|o| o.ledger_id == ledger_id).cloned().collect())
+    }
+
+    async fn get_order(&self, ledger_id: ObjectId, order_id: ObjectId) -> DBResult<Option<Order>> {
+        let tables = self.tables.read().unwrap();
+        Ok(find_owned(&tables.orders, ledger_id, order_id))
+    }
+
+    async fn find_orders(&self, ledger_id: ObjectId, filter: &OrderFilter, page: &OrderPage) -> DBResult<Vec<Order>> {
+        let tables = self.tables.read().unwrap();
+        let mut orders: Vec<Order> = tables.orders.iter()
+            .filter(|o| o.ledger_id == ledger_id && filter.matches(o) && page.is_after_cursor(o))
+            .cloned()
+            .collect();
+        orders.sort_by_key(|o| (o.date, o.id));
+        if page.sort == OrderSort::DateDesc {
+            orders.reverse();
+        }
+        let skip = if page.after.is_some() { 0 } else { page.skip as usize };
+        Ok(orders.into_iter().skip(skip).take(page.limit.max(0) as usize).collect())
+    }
+
+    async fn count_orders(&self, ledger_id: ObjectId, filter: &OrderFilter) -> DBResult<u64> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.orders.iter().filter(|o| o.ledger_id == ledger_id && filter.matches(o)).count() as u64)
+    }
+
+    async fn order_totals(&self, ledger_id: ObjectId, filter: &OrderFilter, base: Option<Currency>) -> DBResult<Vec<OrderTotal>> {
+        self.order_totals_by_period(ledger_id, filter, base, &[]).await
+    }
+
+    async fn order_totals_by_period(&self, ledger_id: ObjectId, filter: &OrderFilter, base: Option<Currency>, starts: &[DateTime]) -> DBResult<Vec<OrderTotal>> {
+        let tables = self.tables.read().unwrap();
+        let mut totals: Vec<OrderTotal> = Vec::new();
+        for o in tables.orders.iter().filter(|o| o.ledger_id == ledger_id && filter.matches(o)) {
+            let period = if starts.is_empty() {
+                0
+            } else {
+                match starts.partition_point(|s| *s <= o.date).checked_sub(1) {
+                    Some(i) => i,
+                    None => continue,
+                }
+            };
+            let date = day_start(o.date);
+            let undated = base == Some(o.amount.currency);
+            let same_group = |t: &&mut OrderTotal| t.order_type == o.order_type && t.category_id == o.category_id
+                && t.amount.currency == o.amount.currency && t.period == period && (undated || t.date == date);
+            match totals.iter_mut().find(same_group) {
+                Some(total) => {
+                    total.amount = total.amount.checked_add(o.amount.minor)
+                        .ok_or_else(|| StoreError::Conflict("订单金额合计超出范围".to_string()))?;
+                    total.date = total.date.min(date);
+                }
+                None => totals.push(OrderTotal { order_type: o.order_type.clone(), category_id: o.category_id, date, amount: o.amount, period }),
+            }
+        }
+        Ok(totals)
+    }
+
+    async fn delete_order(&self, ledger_id: ObjectId, order_id: ObjectId) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(order) = find_owned(&tables.orders, ledger_id, order_id) else {
+            return Ok(false);
+        };
+        let mut deleted = vec![order];
+        if let Some(link) = &deleted[0].transfer
+            && let Some(peer) = find_owned(&tables.orders, ledger_id, link.peer_order_id) {
+            deleted.push(peer);
+        }
+        let changes: Vec<_> = deleted.iter().filter_map(|o| Some((o.account_id?, -o.balance_delta()))).collect();
+        apply_balances(&mut tables.accounts, &changes)?;
+        for order in &deleted {
+            remove_owned(&mut tables.orders, ledger_id, order.id);
+        }
+        Ok(true)
+    }
+
+    async fn create_transfer(&self, outgoing: Order, incoming: Order) -> DBResult<(Order, Order)> {
+        let mut tables = self.tables.write().unwrap();
+        let owns = |tables: &Tables, order: &Order| tables.accounts.iter()
+            .any(|a| Some(a.id) == order.account_id && a.ledger_id == order.ledger_id && a.balance.currency == order.amount.currency);
+        if !owns(&tables, &outgoing) || !owns(&tables, &incoming) {
+            return Err(StoreError::NotFound("账户"));
+        }
+        let changes: Vec<_> = [&outgoing, &incoming].iter().filter_map(|o| Some((o.account_id?, o.balance_delta()))).collect();
+        apply_balances(&mut tables.accounts, &changes)?;
+        tables.orders.push(outgoing.clone());
+        tables.orders.push(incoming.clone());
+        Ok((outgoing, incoming))
+    }
+
+    async fn get_orders_pending_alerts(&self) -> DBResult<Vec<Order>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.orders.iter().filter(|o| o.alerts_pending).cloned().collect())
+    }
+
+    async fn clear_alerts_pending(&self, order_id: ObjectId) -> DBResult<()> {
+        let mut tables = self.tables.write().unwrap();
+        if let Some(order) = tables.orders.iter_mut().find(|o| o.id == order_id) {
+            order.alerts_pending = false;
+        }
+        Ok(())
+    }
+
+    // 预算相关
+    async fn create_budget(&self, budget: Budget) -> DBResult<Budget> {
+        self.tables.write().unwrap().budgets.push(budget.clone());
+        Ok(budget)
+    }
+
+    async fn get_budgets_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Budget>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.budgets.iter().filter(|b| b.ledger_id == ledger_id).cloned().collect())
+    }
+
+    async fn get_budget(&self, ledger_id: ObjectId, budget_id: ObjectId) -> DBResult<Option<Budget>> {
+        let tables = self.tables.read().unwrap();
+        Ok(find_owned(&tables.budgets, ledger_id, budget_id))
+    }
+
+    async fn update_budget(&self, budget: Budget) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        Ok(replace_owned(&mut tables.budgets, budget))
+    }
+
+    async fn delete_budget(&self, ledger_id: ObjectId, budget_id: ObjectId) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        Ok(remove_owned(&mut tables.budgets, ledger_id, budget_id).is_some())
+    }
+
+    // 账本相关
+    async fn create_ledger(&self, ledger: Ledger) -> DBResult<Ledger> {
+        self.tables.write().unwrap().ledgers.push(ledger.clone());
+        Ok(ledger)
+    }
+
+    async fn get_ledger(&self, ledger_id: ObjectId) -> DBResult<Option<Ledger>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.ledgers.iter().find(|l| l.id == ledger_id).cloned())
+    }
+
+    async fn get_ledgers_for_user(&self, user_id: ObjectId) -> DBResult<Vec<Ledger>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.ledgers.iter().filter(|l| l.role_of(user_id).is_some()).cloned().collect())
+    }
+
+    async fn rename_ledger(&self, ledger_id: ObjectId, name: String) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(ledger) = tables.ledgers.iter_mut().find(|l| l.id == ledger_id) else {
+            return Ok(false);
+        };
+        ledger.name = name;
+        Ok(true)
+    }
+
+    async fn delete_ledger(&self, ledger_id: ObjectId) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        if !tables.ledgers.iter().any(|l| l.id == ledger_id) {
+            return Ok(false);
+        }
+        drop_ledgers(&mut tables, &[ledger_id]);
+        Ok(true)
+    }
+
+    async fn set_member_role(&self, ledger_id: ObjectId, user_id: ObjectId, role: Role) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(member) = tables.ledgers.iter_mut()
+            .find(|l| l.id == ledger_id)
+            .and_then(|l| l.members.iter_mut().find(|m| m.user_id == user_id && m.role != Role::Owner)) else {
+            return Ok(false);
+        };
+        member.role = role;
+        Ok(true)
+    }
+
+    async fn remove_member(&self, ledger_id: ObjectId, user_id: ObjectId) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(ledger) = tables.ledgers.iter_mut().find(|l| l.id == ledger_id) else {
+            return Ok(false);
+        };
+        let before = ledger.members.len();
+        ledger.members.retain(|m| m.user_id != user_id || m.role == Role::Owner);
+        Ok(ledger.members.len() < before)
+    }
+
+    async fn create_invitation(&self, invitation: Invitation) -> DBResult<Invitation> {
+        self.tables.write().unwrap().invitations.push(invitation.clone());
+        Ok(invitation)
+    }
+
+    async fn get_invitation(&self, invitation_id: ObjectId) -> DBResult<Option<Invitation>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.invitations.iter().find(|i| i.id == invitation_id).cloned())
+    }
+
+    async fn get_invitations_for_user(&self, invitee_id: ObjectId) -> DBResult<Vec<Invitation>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.invitations.iter().filter(|i| i.invitee_id == invitee_id).cloned().collect())
+    }
+
+    async fn get_invitations_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Invitation>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.invitations.iter().filter(|i| i.ledger_id == ledger_id).cloned().collect())
+    }
+
+    async fn delete_invitation(&self, invitation_id: ObjectId) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let before = tables.invitations.len();
+        tables.invitations.retain(|i| i.id != invitation_id);
+        Ok(tables.invitations.len() < before)
+    }
+
+    async fn accept_invitation(&self, invitation_id: ObjectId) -> DBResult<Ledger> {
+        let mut tables = self.tables.write().unwrap();
+        let invitation = tables.invitations.iter().find(|i| i.id == invitation_id).cloned().ok_or(StoreError::NotFound("邀请"))?;
+        let ledger = tables.ledgers.iter_mut().find(|l| l.id == invitation.ledger_id).ok_or(StoreError::NotFound("账本"))?;
+        if ledger.role_of(invitation.invitee_id).is_some() {
+            return Err(StoreError::Conflict("已是账本成员".to_string()));
+        }
+        ledger.members.push(Member { user_id: invitation.invitee_id, role: invitation.role });
+        let ledger = ledger.clone();
+        tables.invitations.retain(|i| i.id != invitation_id);
+        Ok(ledger)
+    }
+
+    // 汇率相关
+    async fn create_rates(&self, rates: Vec<ExchangeRate>) -> DBResult<Vec<ExchangeRate>> {
+        self.tables.write().unwrap().rates.extend(rates.iter().cloned());
+        Ok(rates)
+    }
+
+    async fn get_rates_by_user(&self, user_id: ObjectId) -> DBResult<Vec<ExchangeRate>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.rates.iter().filter(|r| r.user_id == user_id).cloned().collect())
+    }
+
+    // 会话相关
+    async fn create_session(&self, session: Session) -> DBResult<Session> {
+        self.tables.write().unwrap().sessions.push(session.clone());
+        Ok(session)
+    }
+
+    async fn get_session(&self, session_id: ObjectId) -> DBResult<Option<Session>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.sessions.iter().find(|s| s.id == session_id).cloned())
+    }
+
+    async fn rotate_session(&self, session_id: ObjectId, old_hash: &str, new_hash: String, expires_at: DateTime) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(session) = tables.sessions.iter_mut()
+            .find(|s| s.id == session_id && s.refresh_hash == old_hash && !s.revoked) else {
+            return Ok(false);
+        };
+        session.previous_hash = Some(std::mem::replace(&mut session.refresh_hash, new_hash));
+        session.expires_at = expires_at;
+        Ok(true)
+    }
+
+    async fn revoke_session(&self, user_id: ObjectId, session_id: ObjectId) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(session) = tables.sessions.iter_mut().find(|s| s.is_owned(user_id, session_id)) else {
+            return Ok(false);
+        };
+        session.revoked = true;
+        Ok(true)
+    }
+
+    async fn revoke_all_sessions(&self, user_id: ObjectId) -> DBResult<u64> {
+        let mut tables = self.tables.write().unwrap();
+        let mut revoked = 0;
+        for session in tables.sessions.iter_mut().filter(|s| s.user_id == user_id && !s.revoked) {
+            session.revoked = true;
+            revoked += 1;
+        }
+        Ok(revoked)
+    }
+
+    async fn use_challenge(&self, jti: &str, expires_at: DateTime) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        let now = DateTime::now();
+        tables.used_challenges.retain(|c| c.expires_at > now);
+        if tables.used_challenges.iter().any(|c| c.jti == jti) {
+            return Ok(false);
+        }
+        tables.used_challenges.push(UsedChallenge { jti: jti.to_string(), expires_at });
+        Ok(true)
+    }
+
+    // API 密钥相关
+    async fn create_api_key(&self, key: ApiKey) -> DBResult<ApiKey> {
+        self.tables.write().unwrap().api_keys.push(key.clone());
+        Ok(key)
+    }
+
+    async fn get_api_key(&self, key_id: ObjectId) -> DBResult<Option<ApiKey>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.api_keys.iter().find(|k| k.id == key_id).cloned())
+    }
+
+    async fn get_api_keys_by_user(&self, user_id: ObjectId) -> DBResult<Vec<ApiKey>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.api_keys.iter().filter(|k| k.user_id == user_id).cloned().collect())
+    }
+
+    async fn delete_api_key(&self, user_id: ObjectId, key_id: ObjectId) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        Ok(remove_owned(&mut tables.api_keys, user_id, key_id).is_some())
+    }
+
+    // 通知相关
+    async fn create_notification(&self, notification: Notification) -> DBResult<bool> {
+        let mut tables = self.tables.write().unwrap();
+        if tables.notifications.iter().any(|n| n.user_id == notification.user_id && n.key == notification.key) {
+            return Ok(false);
+        }
+        tables.notifications.push(notification);
+        Ok(true)
+    }
+
+    async fn get_notifications_for_user(&self, user_id: ObjectId, unread_only: bool) -> DBResult<Vec<Notification>> {
+        let tables = self.tables.read().unwrap();
+        let mut notifications: Vec<Notification> = tables.notifications.iter()
+            .filter(|n| n.user_id == user_id && !(unread_only && n.read))
+            .cloned()
+            .collect();
+        notifications.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+        Ok(notifications)
+    }
+
+    async fn mark_notification_read(&self, user_id: ObjectId, notification_id: ObjectId) -> DBResult<Option<Notification>> {
+        let mut tables = self.tables.write().unwrap();
+        let Some(notification) = tables.notifications.iter_mut().find(|n| n.id == notification_id && n.user_id == user_id) else {
+            return Ok(None);
+        };
+        notification.read = true;
+        Ok(Some(notification.clone()))
+    }
+
+    async fn mark_all_notifications_read(&self, user_id: ObjectId) -> DBResult<u64> {
+        let mut tables = self.tables.write().unwrap();
+        let mut count = 0;
+        for notification in tables.notifications.iter_mut().filter(|n| n.user_id == user_id && !n.read) {
+            notification.read = true;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    async fn get_pending_notifications(&self, max_attempts: u32) -> DBResult<Vec<Notification>> {
+        let tables = self.tables.read().unwrap();
+        let mut notifications: Vec<Notification> = tables.notifications.iter()
+            .filter(|n| n.delivered_at.is_none() && n.attempts < max_attempts)
+            .cloned()
+            .collect();
+        notifications.sort_by_key(|n| n.created_at);
+        Ok(notifications)
+    }
+
+    async fn record_delivery(&self, notification_id: ObjectId, error: Option<String>) -> DBResult<()> {
+        let mut tables = self.tables.write().unwrap();
+        if let Some(notification) = tables.notifications.iter_mut().find(|n| n.id == notification_id) {
+            notification.attempts += 1;
+            if error.is_none() {
+                notification.delivered_at = Some(DateTime::now());
+            }
+            notification.last_error = error;
+        }
+        Ok(())
+    }
+
+    // 登录失败记录
+    async fn get_login_attempt(&self, key: &str) -> DBResult<Option<LoginAttempt>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.login_attempts.iter().find(|a| a.key == key).cloned())
+    }
+
+    async fn record_login_failure(&self, key: &str, window_start: DateTime) -> DBResult<LoginAttempt> {
+        let mut tables = self.tables.write().unwrap();
+        let now = DateTime::now();
+        if let Some(attempt) = tables.login_attempts.iter_mut().find(|a| a.key == key) {
+            attempt.failures = if attempt.last_failure >= window_start { attempt.failures + 1 } else { 1 };
+            attempt.last_failure = now;
+            return Ok(attempt.clone());
+        }
+        let attempt = LoginAttempt { key: key.to_string(), failures: 1, last_failure: now };
+        tables.login_attempts.push(attempt.clone());
+        Ok(attempt)
+    }
+
+    async fn clear_login_attempts(&self, key: &str) -> DBResult<()> {
+        self.tables.write().unwrap().login_attempts.retain(|a| a.key != key);
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use crate::db::{DBResult, MongoDB, StoreError};
+use crate::models::currency::Currency;
+use crate::models::ledger::Ledger;
+use crate::models::user::normalize_username;
+use futures::future::BoxFuture;
+use futures::stream::TryStreamExt;
+use std::collections::HashMap;
+use mongodb::bson::{doc, Bson, DateTime, Document};
+use mongodb::Collection;
+
+type Migration = fn(&MongoDB) -> BoxFuture<'_, DBResult<()>>;
+
+/// 按顺序执行的数据迁移，已执行的名称记录在 `migrations` 集合中，只会执行一次。
+const MIGRATIONS: &[(&str, Migration)] = &[
+    ("0001_account_initial_balance", account_initial_balance),
+    ("0002_money_minor_units", money_minor_units),
+    ("0003_normalize_currency_codes", normalize_currency_codes),
+    ("0004_normalize_usernames", normalize_usernames),
+    ("0005_personal_ledgers", personal_ledgers),
+    ("0006_budget_periods", budget_periods),
+];
+
+pub async fn run(db: &MongoDB) -> DBResult<()> {
+    let applied = db.db.collection::<mongodb::bson::Document>("migrations");
+    for (name, migration) in MIGRATIONS {
+        if applied.find_one(doc! {"name": name}).await?.is_some() {
+            continue;
+        }
+        tracing::info!("[迁移] 执行数据迁移: {}", name);
+        migration(db).await?;
+        applied.insert_one(doc! {"name": name, "applied_at": DateTime::now()}).await?;
+    }
+    Ok(())
+}
+
+// 旧账户的余额从未被订单修改过，直接作为期初余额
+fn account_initial_balance(db: &MongoDB) -> BoxFuture<'_, DBResult<()>> {
+    Box::pin(async move {
+        db.accounts
+            .update_many(
+                doc! {"initial_balance": {"$exists": false}},
+                vec![doc! {"$set": {"initial_balance": "$balance"}}],
+            )
+            .await?;
+        Ok(())
+    })
+}
+
+// 把 `field` 中的浮点金额换算成 `currency` 表达式对应币种的最小单位整数
+fn minor_expr(field: &str, currency: impl Into<Bson>) -> Document {
+    let currency = currency.into();
+    // 此时币种仍可能是中文名称或小写代码，与 `Currency` 解析一样去掉首尾空白、代码不区分大小写后再匹配
+    let zero_decimal: Vec<&str> = Currency::ALL.into_iter()
+        .filter(|c| c.decimals() == 0)
+        .flat_map(|c| [c.code(), c.display_name()])
+        .collect();
+    doc! {
+        "minor": {"$toLong": {"$round": [{"$multiply": [
+            format!("${}", field),
+            {"$cond": [{"$in": [{"$toUpper": {"$trim": {"input": currency.clone()}}}, zero_decimal]}, 1, 100]},
+        ]}, 0]}},
+        "currency": currency,
+    }
+}
+
+// f64 金额 + 独立 currency 字段 => Money { minor, currency }
+fn money_minor_units(db: &MongoDB) -> BoxFuture<'_, DBResult<()>> {
+    Box::pin(async move {
+        db.accounts.clone_with_type::<Document>()
+            .update_many(
+                doc! {"balance": {"$type": "number"}},
+                vec![
+                    doc! {"$set": {
+                        "balance": minor_expr("balance", "$currency"),
+                        "initial_balance": minor_expr("initial_balance", "$currency"),
+                    }},
+                    doc! {"$unset": "currency"},
+                ],
+            )
+            .await?;
+        db.assets.clone_with_type::<Document>()
+            .update_many(
+                doc! {"value": {"$type": "number"}},
+                vec![
+                    doc! {"$set": {"value": minor_expr("value", "$currency")}},
+                    doc! {"$unset": "currency"},
+                ],
+            )
+            .await?;
+        let orders = db.orders.clone_with_type::<Document>();
+        orders
+            .update_many(
+                doc! {"transfer.fee": {"$type": "number"}},
+                vec![doc! {"$set": {"transfer.fee": minor_expr("transfer.fee", "$currency")}}],
+            )
+            .await?;
+        orders
+            .update_many(
+                doc! {"amount": {"$type": "number"}},
+                vec![
+                    doc! {"$set": {"amount": minor_expr("amount", "$currency")}},
+                    doc! {"$unset": "currency"},
+                ],
+            )
+            .await?;
+        // 旧预算没有币种字段，按默认币种人民币处理
+        db.budgets.clone_with_type::<Document>()
+            .update_many(
+                doc! {"amount": {"$type": "number"}},
+                vec![doc! {"$set": {"amount": minor_expr("amount", "CNY")}}],
+            )
+            .await?;
+        Ok(())
+    })
+}
+
+// 把 `field` 中出现过的币种统一改写为 ISO 4217 代码，遇到无法识别的值时中止迁移
+async fn normalize_field(coll: &Collection<Document>, field: &str) -> DBResult<()> {
+    for value in coll.distinct(field, doc! {}).await? {
+        let Bson::String(raw) = value else { continue };
+        let currency: Currency = raw.parse().map_err(|_| StoreError::Migration(
+            format!("集合 {} 的字段 {} 存在无法识别的币种 \"{}\"，请手动修正后重启", coll.name(), field, raw),
+        ))?;
+        if currency.code() != raw {
+            coll.update_many(doc! {field: &raw}, doc! {"$set": {field: currency.code()}}).await?;
+        }
+    }
+    Ok(())
+}
+
+// “人民币”“cny”等历史写法 => “CNY”
+fn normalize_currency_codes(db: &MongoDB) -> BoxFuture<'_, DBResult<()>> {
+    Box::pin(async move {
+        let fields = [
+            (db.accounts.clone_with_type::<Document>(), "balance.currency"),
+            (db.accounts.clone_with_type::<Document>(), "initial_balance.currency"),
+            (db.assets.clone_with_type::<Document>(), "value.currency"),
+            (db.orders.clone_with_type::<Document>(), "amount.currency"),
+            (db.orders.clone_with_type::<Document>(), "transfer.fee.currency"),
+            (db.budgets.clone_with_type::<Document>(), "amount.currency"),
+        ];
+        for (coll, field) in &fields {
+            normalize_field(coll, field).await?;
+        }
+        Ok(())
+    })
+}
+
+// 用户名去除首尾空白并转小写；规范化后重名的用户需要人工处理，否则无法建立唯一索引
+fn normalize_usernames(db: &MongoDB) -> BoxFuture<'_, DBResult<()>> {
+    Box::pin(async move {
+        let users = db.users_collection();
+        let mut cursor = users.find(doc! {}).await?;
+        let mut renames = Vec::new();
+        let mut seen: HashMap<String, String> = HashMap::new();
+        while let Some(user) = cursor.try_next().await? {
+            let normalized = normalize_username(&user.username);
+            if let Some(other) = seen.insert(normalized.clone(), user.username.clone()) {
+                return Err(StoreError::Migration(format!(
+                    "用户名 \"{}\" 与 \"{}\" 规范化后重复，请手动修改后重启", other, user.username,
+                )));
+            }
+            if normalized != user.username {
+                renames.push((user.id, normalized));
+            }
+        }
+        for (id, username) in renames {
+            users.update_one(doc! {"id": id}, doc! {"$set": {"username": username}}).await?;
+        }
+        Ok(())
+    })
+}
+
+// 每个用户建一个个人账本设为默认账本，原来按 user_id 归属的数据全部移入该账本
+fn personal_ledgers(db: &MongoDB) -> BoxFuture<'_, DBResult<()>> {
+    Box::pin(async move {
+        let users = db.users_collection();
+        let mut cursor = users.find(doc! {"default_ledger_id": {"$exists": false}}).await?;
+        let mut user_ids = Vec::new();
+        while let Some(user) = cursor.try_next().await? {
+            user_ids.push(user.id);
+        }
+        let collections = ["accounts", "categories", "assets", "orders", "budgets"];
+        for user_id in user_ids {
+            let ledger = Ledger::personal(user_id);
+            db.ledgers.insert_one(&ledger).await?;
+            users.update_one(doc! {"id": user_id}, doc! {"$set": {"default_ledger_id": ledger.id}}).await?;
+            for name in collections {
+                db.db.collection::<Document>(name)
+                    .update_many(
+                        doc! {"user_id": user_id, "ledger_id": {"$exists": false}},
+                        doc! {"$set": {"ledger_id": ledger.id}},
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    })
+}
+
+// 预算周期的中文写法 => BudgetPeriod；无法识别的按自定义周期处理，沿用原有的开始和结束日期
+fn budget_periods(db: &MongoDB) -> BoxFuture<'_, DBResult<()>> {
+    Box::pin(async move {
+        let budgets = db.budgets.clone_with_type::<Document>();
+        let mapping = [
+            ("weekly", vec!["周", "每周"]),
+            ("monthly", vec!["月", "每月"]),
+            ("quarterly", vec!["季", "季度"]),
+            ("yearly", vec!["年", "每年"]),
+        ];
+        for (period, legacy) in &mapping {
+            budgets.update_many(doc! {"period": {"$in": legacy}}, doc! {"$set": {"period": period}}).await?;
+        }
+        let known: Vec<&str> = mapping.iter().map(|(period, _)| *period).chain(["custom"]).collect();
+        budgets.update_many(doc! {"period": {"$nin": known}}, doc! {"$set": {"period": "custom"}}).await?;
+        budgets.update_many(doc! {"rollover": {"$exists": false}}, doc! {"$set": {"rollover": false}}).await?;
+        Ok(())
+    })
+}
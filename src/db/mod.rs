@@ -0,0 +1,207 @@
+use crate::models::user::{TwoFactor, User};
+use crate::models::account::Account;
+use crate::models::category::Category;
+use crate::models::asset::Asset;
+use crate::models::transaction::Order;
+use crate::models::budget::Budget;
+use crate::models::currency::Currency;
+use crate::models::exchange_rate::ExchangeRate;
+use crate::models::session::Session;
+use crate::models::api_key::ApiKey;
+use crate::models::login_attempt::LoginAttempt;
+use crate::models::ledger::{Invitation, Ledger, Role};
+use crate::models::notification::Notification;
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+pub mod mongo;
+pub mod memory;
+pub mod migrations;
+pub mod query;
+
+pub use mongo::MongoDB;
+pub use memory::MemoryStore;
+pub use query::{add_balance, OrderFilter, OrderPage, OrderSort, OrderTotal};
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("数据库错误: {0}")]
+    Mongo(#[from] mongodb::error::Error),
+    #[error("{0}不存在")]
+    NotFound(&'static str),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("数据迁移失败: {0}")]
+    Migration(String),
+}
+
+pub type DBResult<T> = Result<T, StoreError>;
+
+/// 属于某个用户或账本的文档。按ID读取、修改、删除时必须同时匹配 `id` 与归属字段
+/// （[`Owned::SCOPE`]），范围之外的文档一律视为不存在。
+pub trait Owned {
+    /// 归属字段名：`user_id` 或 `ledger_id`
+    const SCOPE: &'static str;
+
+    fn id(&self) -> ObjectId;
+    fn owner(&self) -> ObjectId;
+
+    fn is_owned(&self, owner: ObjectId, id: ObjectId) -> bool {
+        self.id() == id && self.owner() == owner
+    }
+}
+
+macro_rules! impl_owned {
+    ($field:ident => $($t:ty),*) => {
+        $(impl Owned for $t {
+            const SCOPE: &'static str = stringify!($field);
+            fn id(&self) -> ObjectId { self.id }
+            fn owner(&self) -> ObjectId { self.$field }
+        })*
+    };
+}
+
+impl_owned!(ledger_id => Account, Category, Asset, Order, Budget);
+impl_owned!(user_id => ExchangeRate, Session, ApiKey, Notification);
+
+/// 账本存储接口，所有路由只依赖该 trait，
+/// 生产环境使用 [`MongoDB`]，测试使用 [`MemoryStore`]。
+#[async_trait]
+pub trait LedgerStore: Send + Sync {
+    // 用户相关
+    /// 创建用户；用户名已存在时返回 [`StoreError::Conflict`]。
+    async fn create_user(&self, user: User) -> DBResult<User>;
+    async fn get_user_by_username(&self, username: &str) -> DBResult<Option<User>>;
+    async fn get_user(&self, user_id: ObjectId) -> DBResult<Option<User>>;
+    async fn set_base_currency(&self, user_id: ObjectId, currency: Currency) -> DBResult<bool>;
+    async fn set_password(&self, user_id: ObjectId, password_hash: String) -> DBResult<bool>;
+    async fn set_email(&self, user_id: ObjectId, email: Option<String>) -> DBResult<bool>;
+    async fn set_default_ledger(&self, user_id: ObjectId, ledger_id: ObjectId) -> DBResult<bool>;
+    async fn set_two_factor(&self, user_id: ObjectId, two_factor: Option<TwoFactor>) -> DBResult<bool>;
+    /// 仅当 `step` 比上次使用的步长新时记录并返回 true，同一验证码不能用两次。
+    async fn use_totp_step(&self, user_id: ObjectId, step: i64) -> DBResult<bool>;
+    /// 消耗一个恢复码，哈希不存在时返回 false。
+    async fn use_recovery_code(&self, user_id: ObjectId, code_hash: &str) -> DBResult<bool>;
+    /// 修改用户名；新用户名已被其他用户占用时返回 [`StoreError::Conflict`]。
+    async fn rename_user(&self, user_id: ObjectId, username: String) -> DBResult<bool>;
+    /// 删除用户：其拥有的账本有其他成员时转交给 [`Ledger::successor`]，没有其他成员时连同账本内
+    /// 全部数据一并删除；在其他账本中的成员身份移除，汇率、会话、API 密钥、邀请和通知同时删除。
+    async fn delete_user(&self, user_id: ObjectId) -> DBResult<bool>;
+
+    // 账户相关
+    async fn create_account(&self, account: Account) -> DBResult<Account>;
+    async fn get_accounts_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Account>>;
+    async fn get_account(&self, ledger_id: ObjectId, account_id: ObjectId) -> DBResult<Option<Account>>;
+    /// 更新账户信息；期初余额变化的差额同步计入当前余额，返回更新后的账户。
+    async fn update_account(&self, account: Account) -> DBResult<Option<Account>>;
+    /// 删除账户；仍被订单或资产引用时返回 [`StoreError::Conflict`]。
+    async fn delete_account(&self, ledger_id: ObjectId, account_id: ObjectId) -> DBResult<bool>;
+    /// 以期初余额加上所有关联订单的影响重建账户余额。
+    async fn recompute_account_balance(&self, ledger_id: ObjectId, account_id: ObjectId) -> DBResult<Account>;
+
+    // 分类相关
+    async fn create_category(&self, category: Category) -> DBResult<Category>;
+    async fn get_categories_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Category>>;
+    async fn get_category(&self, ledger_id: ObjectId, category_id: ObjectId) -> DBResult<Option<Category>>;
+    /// 修改分类；父分类变化时在写入的同时用 [`check_parent`](crate::models::category::check_parent) 再校验一次，
+    /// 并发移动会形成环或超出层数时返回 [`StoreError::Conflict`]。
+    async fn update_category(&self, category: Category) -> DBResult<bool>;
+    /// 删除分类；给出 `reassign_to` 时先把预算、子分类和订单转移到该分类，否则仍被预算或子分类引用时返回
+    /// [`StoreError::Conflict`]，订单变为未分类。
+    async fn delete_category(&self, ledger_id: ObjectId, category_id: ObjectId, reassign_to: Option<ObjectId>) -> DBResult<bool>;
+
+    // 资产相关
+    async fn create_asset(&self, asset: Asset) -> DBResult<Asset>;
+    async fn get_assets_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Asset>>;
+    async fn get_asset(&self, ledger_id: ObjectId, asset_id: ObjectId) -> DBResult<Option<Asset>>;
+    async fn update_asset(&self, asset: Asset) -> DBResult<bool>;
+    async fn delete_asset(&self, ledger_id: ObjectId, asset_id: ObjectId) -> DBResult<bool>;
+
+    // 订单相关（带 account_id 的订单在同一事务内调整账户余额）
+    async fn create_order(&self, order: Order) -> DBResult<Order>;
+    async fn get_orders_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Order>>;
+    async fn get_order(&self, ledger_id: ObjectId, order_id: ObjectId) -> DBResult<Option<Order>>;
+    /// 按条件筛选、排序并分页查询订单
+    async fn find_orders(&self, ledger_id: ObjectId, filter: &OrderFilter, page: &OrderPage) -> DBResult<Vec<Order>>;
+    async fn count_orders(&self, ledger_id: ObjectId, filter: &OrderFilter) -> DBResult<u64>;
+    /// 按类型、分类、币种、自然日分组汇总符合条件的订单金额；`base` 币种的金额不需要换算，不按日期分组
+    async fn order_totals(&self, ledger_id: ObjectId, filter: &OrderFilter, base: Option<Currency>) -> DBResult<Vec<OrderTotal>>;
+    /// 同 [`order_totals`](Self::order_totals)，另按订单的确切时间分到各周期：`starts` 为升序的各周期开始时间，
+    /// 早于第一个周期的订单不计入。同一天跨两个周期的订单分别汇总
+    async fn order_totals_by_period(&self, ledger_id: ObjectId, filter: &OrderFilter, base: Option<Currency>, starts: &[DateTime]) -> DBResult<Vec<OrderTotal>>;
+    /// 删除订单；若为转账订单，另一侧订单一并删除。
+    async fn delete_order(&self, ledger_id: ObjectId, order_id: ObjectId) -> DBResult<bool>;
+    /// 原子地写入一笔转账的转出、转入两条订单并调整两个账户余额。
+    async fn create_transfer(&self, outgoing: Order, incoming: Order) -> DBResult<(Order, Order)>;
+    /// 所有账本中尚未完成预算提醒检查（`alerts_pending`）的订单
+    async fn get_orders_pending_alerts(&self) -> DBResult<Vec<Order>>;
+    async fn clear_alerts_pending(&self, order_id: ObjectId) -> DBResult<()>;
+
+    // 预算相关
+    async fn create_budget(&self, budget: Budget) -> DBResult<Budget>;
+    async fn get_budgets_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Budget>>;
+    async fn get_budget(&self, ledger_id: ObjectId, budget_id: ObjectId) -> DBResult<Option<Budget>>;
+    async fn update_budget(&self, budget: Budget) -> DBResult<bool>;
+    async fn delete_budget(&self, ledger_id: ObjectId, budget_id: ObjectId) -> DBResult<bool>;
+
+    // 账本相关
+    async fn create_ledger(&self, ledger: Ledger) -> DBResult<Ledger>;
+    async fn get_ledger(&self, ledger_id: ObjectId) -> DBResult<Option<Ledger>>;
+    /// 用户作为成员（含所有者）的全部账本
+    async fn get_ledgers_for_user(&self, user_id: ObjectId) -> DBResult<Vec<Ledger>>;
+    async fn rename_ledger(&self, ledger_id: ObjectId, name: String) -> DBResult<bool>;
+    /// 删除账本，账本内的账户、分类、资产、订单、预算、邀请和通知一并删除。
+    async fn delete_ledger(&self, ledger_id: ObjectId) -> DBResult<bool>;
+    /// 修改非所有者成员的角色
+    async fn set_member_role(&self, ledger_id: ObjectId, user_id: ObjectId, role: Role) -> DBResult<bool>;
+    /// 移除非所有者成员
+    async fn remove_member(&self, ledger_id: ObjectId, user_id: ObjectId) -> DBResult<bool>;
+    async fn create_invitation(&self, invitation: Invitation) -> DBResult<Invitation>;
+    async fn get_invitation(&self, invitation_id: ObjectId) -> DBResult<Option<Invitation>>;
+    async fn get_invitations_for_user(&self, invitee_id: ObjectId) -> DBResult<Vec<Invitation>>;
+    async fn get_invitations_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Invitation>>;
+    async fn delete_invitation(&self, invitation_id: ObjectId) -> DBResult<bool>;
+    /// 接受邀请：被邀请人加入账本与删除邀请在同一次操作中完成，返回加入后的账本。
+    /// 邀请或账本不存在时返回 [`StoreError::NotFound`]，已是成员时返回 [`StoreError::Conflict`]，邀请均保持不变。
+    async fn accept_invitation(&self, invitation_id: ObjectId) -> DBResult<Ledger>;
+
+    // 汇率相关
+    async fn create_rates(&self, rates: Vec<ExchangeRate>) -> DBResult<Vec<ExchangeRate>>;
+    async fn get_rates_by_user(&self, user_id: ObjectId) -> DBResult<Vec<ExchangeRate>>;
+
+    // 会话相关
+    async fn create_session(&self, session: Session) -> DBResult<Session>;
+    async fn get_session(&self, session_id: ObjectId) -> DBResult<Option<Session>>;
+    /// 仅当会话未吊销且当前刷新令牌哈希为 `old_hash` 时轮换，返回是否成功。
+    async fn rotate_session(&self, session_id: ObjectId, old_hash: &str, new_hash: String, expires_at: DateTime) -> DBResult<bool>;
+    async fn revoke_session(&self, user_id: ObjectId, session_id: ObjectId) -> DBResult<bool>;
+    /// 吊销用户的全部会话，返回吊销数量。
+    async fn revoke_all_sessions(&self, user_id: ObjectId) -> DBResult<u64>;
+    /// 记录挑战令牌已使用；此前已记录过时返回 false，同一挑战令牌不能用两次。
+    async fn use_challenge(&self, jti: &str, expires_at: DateTime) -> DBResult<bool>;
+
+    // API 密钥相关
+    async fn create_api_key(&self, key: ApiKey) -> DBResult<ApiKey>;
+    async fn get_api_key(&self, key_id: ObjectId) -> DBResult<Option<ApiKey>>;
+    async fn get_api_keys_by_user(&self, user_id: ObjectId) -> DBResult<Vec<ApiKey>>;
+    async fn delete_api_key(&self, user_id: ObjectId, key_id: ObjectId) -> DBResult<bool>;
+
+    // 通知相关
+    /// 写入通知；该用户已有相同 `key` 的通知时不写入并返回 false。
+    async fn create_notification(&self, notification: Notification) -> DBResult<bool>;
+    /// 用户的通知，按创建时间倒序
+    async fn get_notifications_for_user(&self, user_id: ObjectId, unread_only: bool) -> DBResult<Vec<Notification>>;
+    async fn mark_notification_read(&self, user_id: ObjectId, notification_id: ObjectId) -> DBResult<Option<Notification>>;
+    /// 把用户的全部未读通知标记为已读，返回标记数量。
+    async fn mark_all_notifications_read(&self, user_id: ObjectId) -> DBResult<u64>;
+    /// 尚未投递成功且尝试次数少于 `max_attempts` 的通知，按创建时间顺序
+    async fn get_pending_notifications(&self, max_attempts: u32) -> DBResult<Vec<Notification>>;
+    /// 记录一次投递结果：`error` 为空表示投递成功。
+    async fn record_delivery(&self, notification_id: ObjectId, error: Option<String>) -> DBResult<()>;
+
+    // 登录失败记录
+    async fn get_login_attempt(&self, key: &str) -> DBResult<Option<LoginAttempt>>;
+    /// 记一次失败并返回最新记录；上次失败早于 `window_start` 时从 1 重新计数。
+    async fn record_login_failure(&self, key: &str, window_start: DateTime) -> DBResult<LoginAttempt>;
+    async fn clear_login_attempts(&self, key: &str) -> DBResult<()>;
+}
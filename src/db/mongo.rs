@@ -0,0 +1,1116 @@
+use crate::models::user::{TwoFactor, User};
+use crate::models::account::Account;
+use crate::models::category::{check_parent, check_reassign, Category};
+use crate::models::asset::Asset;
+use crate::models::transaction::Order;
+use crate::models::budget::Budget;
+use crate::models::money::Money;
+use crate::models::currency::Currency;
+use crate::models::exchange_rate::ExchangeRate;
+use crate::models::session::{Session, UsedChallenge};
+use crate::models::api_key::ApiKey;
+use crate::models::login_attempt::LoginAttempt;
+use crate::models::ledger::{Invitation, Ledger, Member, Role};
+use crate::models::notification::Notification;
+use crate::db::{add_balance, DBResult, LedgerStore, Owned, StoreError, OrderFilter, OrderPage, OrderSort, OrderTotal};
+use async_trait::async_trait;
+use mongodb::{Client, ClientSession, Collection, IndexModel};
+use mongodb::error::{ErrorKind, WriteFailure, TRANSIENT_TRANSACTION_ERROR, UNKNOWN_TRANSACTION_COMMIT_RESULT};
+use mongodb::options::IndexOptions;
+use mongodb::bson::{doc, Bson, DateTime, Document};
+use futures::future::BoxFuture;
+use futures::stream::TryStreamExt;
+use serde::{de::DeserializeOwned, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+pub struct MongoDB {
+    pub client: Client,
+    pub db: mongodb::Database,
+    pub accounts: Collection<Account>,
+    pub categories: Collection<Category>,
+    pub assets: Collection<Asset>,
+    pub orders: Collection<Order>,
+    pub budgets: Collection<Budget>,
+    pub rates: Collection<ExchangeRate>,
+    pub sessions: Collection<Session>,
+    pub used_challenges: Collection<UsedChallenge>,
+    pub api_keys: Collection<ApiKey>,
+    pub login_attempts: Collection<LoginAttempt>,
+    pub ledgers: Collection<Ledger>,
+    pub invitations: Collection<Invitation>,
+    pub notifications: Collection<Notification>,
+}
+
+impl MongoDB {
+    pub async fn new(uri: &str, db_name: &str) -> DBResult<Self> {
+        let client = Client::with_uri_str(uri).await?;
+        let db = client.database(db_name);
+        Ok(Self {
+            client,
+            db: db.clone(),
+            accounts: db.collection::<Account>("accounts"),
+            categories: db.collection::<Category>("categories"),
+            assets: db.collection::<Asset>("assets"),
+            orders: db.collection::<Order>("orders"),
+            budgets: db.collection::<Budget>("budgets"),
+            rates: db.collection::<ExchangeRate>("exchange_rates"),
+            sessions: db.collection::<Session>("sessions"),
+            used_challenges: db.collection::<UsedChallenge>("used_challenges"),
+            api_keys: db.collection::<ApiKey>("api_keys"),
+            login_attempts: db.collection::<LoginAttempt>("login_attempts"),
+            ledgers: db.collection::<Ledger>("ledgers"),
+            invitations: db.collection::<Invitation>("invitations"),
+            notifications: db.collection::<Notification>("notifications"),
+        })
+    }
+
+    pub fn users_collection(&self) -> mongodb::Collection<User> {
+        self.db.collection::<User>("users")
+    }
+
+    /// 在事务中执行 `body`：返回错误时回滚；遇到带 TransientTransactionError 标签的错误（如并发写冲突）
+    /// 时整体重试，提交结果未知（UnknownTransactionCommitResult）时重试提交，最多 [`TRANSACTION_ATTEMPTS`] 次。
+    async fn transaction<T, F>(&self, mut body: F) -> DBResult<T>
+    where
+        F: for<'s> FnMut(&'s MongoDB, &'s mut ClientSession) -> BoxFuture<'s, DBResult<T>>,
+    {
+        let mut session = self.client.start_session().await?;
+        let mut attempt = 1;
+        'run: loop {
+            session.start_transaction().await?;
+            let value = match body(self, &mut session).await {
+                Ok(value) => value,
+                Err(e) => {
+                    // 服务端可能已经中止了事务，回滚失败不影响返回原来的错误
+                    let _ = session.abort_transaction().await;
+                    if attempt < TRANSACTION_ATTEMPTS && has_label(&e, TRANSIENT_TRANSACTION_ERROR) {
+                        attempt += 1;
+                        continue 'run;
+                    }
+                    return Err(e);
+                }
+            };
+            loop {
+                let Err(e) = session.commit_transaction().await else {
+                    return Ok(value);
+                };
+                if attempt >= TRANSACTION_ATTEMPTS {
+                    return Err(e.into());
+                }
+                attempt += 1;
+                if e.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT) {
+                    continue;
+                }
+                if e.contains_label(TRANSIENT_TRANSACTION_ERROR) {
+                    continue 'run;
+                }
+                return Err(e.into());
+            }
+        }
+    }
+
+    // 在事务内改写账本文档的分类版本号。修改分类树的事务都先写这一文档，并发的两次移动会发生写冲突后重试，
+    // 重试时读到对方的结果，避免各自校验通过却合起来形成环
+    async fn lock_category_tree(&self, session: &mut ClientSession, ledger_id: ObjectId) -> DBResult<()> {
+        self.ledgers
+            .update_one(doc! {"id": ledger_id}, doc! {"$inc": {"category_rev": 1}})
+            .session(&mut *session)
+            .await?;
+        Ok(())
+    }
+
+    // 在事务内读取账本的全部分类
+    async fn categories_in(&self, session: &mut ClientSession, ledger_id: ObjectId) -> DBResult<Vec<Category>> {
+        let mut cursor = self.categories.find(doc! {"ledger_id": ledger_id}).session(&mut *session).await?;
+        let mut categories = Vec::new();
+        while let Some(category) = cursor.next(&mut *session).await.transpose()? {
+            categories.push(category);
+        }
+        Ok(categories)
+    }
+
+    // 在事务内删除账本及账本内的全部数据和邀请
+    async fn drop_ledgers(&self, session: &mut ClientSession, ids: &[ObjectId]) -> DBResult<u64> {
+        let in_ledgers = doc! {"ledger_id": {"$in": ids}};
+        self.orders.delete_many(in_ledgers.clone()).session(&mut *session).await?;
+        self.assets.delete_many(in_ledgers.clone()).session(&mut *session).await?;
+        self.budgets.delete_many(in_ledgers.clone()).session(&mut *session).await?;
+        self.categories.delete_many(in_ledgers.clone()).session(&mut *session).await?;
+        self.accounts.delete_many(in_ledgers.clone()).session(&mut *session).await?;
+        self.invitations.delete_many(in_ledgers.clone()).session(&mut *session).await?;
+        self.notifications.delete_many(in_ledgers).session(&mut *session).await?;
+        let res = self.ledgers.delete_many(doc! {"id": {"$in": ids}}).session(&mut *session).await?;
+        Ok(res.deleted_count)
+    }
+
+    // 在事务内调整 `filter` 匹配的账户余额；账户不存在返回 `false`，余额溢出返回冲突
+    async fn adjust_balance(&self, session: &mut ClientSession, filter: Document, delta: i64) -> DBResult<bool> {
+        let Some(account) = self.accounts.find_one(filter.clone()).session(&mut *session).await? else {
+            return Ok(false);
+        };
+        let balance = add_balance(account.balance, delta)?;
+        self.accounts
+            .update_one(filter, doc! {"$set": {"balance.minor": balance.minor}})
+            .session(&mut *session)
+            .await?;
+        Ok(true)
+    }
+
+    /// 创建查询所需索引，重复执行无副作用
+    pub async fn create_indexes(&self) -> DBResult<()> {
+        let index = |keys: Document| IndexModel::builder().keys(keys).build();
+        self.orders
+            .create_indexes([
+                index(doc! {"id": 1}),
+                index(doc! {"ledger_id": 1, "date": -1, "id": -1}),
+                index(doc! {"ledger_id": 1, "order_type": 1, "date": -1}),
+                index(doc! {"account_id": 1}),
+                index(doc! {"ledger_id": 1, "category_id": 1, "date": -1}),
+                index(doc! {"alerts_pending": 1}),
+            ])
+            .await?;
+        self.accounts.create_indexes([index(doc! {"id": 1}), index(doc! {"ledger_id": 1})]).await?;
+        self.categories.create_index(index(doc! {"ledger_id": 1})).await?;
+        self.assets.create_index(index(doc! {"ledger_id": 1})).await?;
+        self.budgets.create_index(index(doc! {"ledger_id": 1})).await?;
+        self.ledgers.create_indexes([index(doc! {"id": 1}), index(doc! {"members.user_id": 1})]).await?;
+        self.invitations.create_indexes([index(doc! {"invitee_id": 1}), index(doc! {"ledger_id": 1})]).await?;
+        self.rates.create_index(index(doc! {"user_id": 1, "date": 1})).await?;
+        self.sessions.create_indexes([index(doc! {"id": 1}), index(doc! {"user_id": 1})]).await?;
+        // 挑战令牌过期后记录由 TTL 索引自动清除
+        self.used_challenges
+            .create_indexes([
+                IndexModel::builder().keys(doc! {"jti": 1}).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder()
+                    .keys(doc! {"expires_at": 1})
+                    .options(IndexOptions::builder().expire_after(std::time::Duration::ZERO).build())
+                    .build(),
+            ])
+            .await?;
+        self.api_keys.create_indexes([index(doc! {"id": 1}), index(doc! {"user_id": 1})]).await?;
+        // 同一用户的同一事件只写入一次，并发创建订单时由唯一索引去重
+        self.notifications
+            .create_indexes([
+                IndexModel::builder().keys(doc! {"user_id": 1, "key": 1}).options(IndexOptions::builder().unique(true).build()).build(),
+                index(doc! {"user_id": 1, "created_at": -1}),
+                index(doc! {"delivered_at": 1, "attempts": 1}),
+            ])
+            .await?;
+        self.login_attempts
+            .create_index(IndexModel::builder().keys(doc! {"key": 1}).options(IndexOptions::builder().unique(true).build()).build())
+            .await?;
+        // 用户名唯一，并发注册时由数据库保证不会重复
+        let unique = IndexOptions::builder().unique(true).build();
+        self.users_collection()
+            .create_index(IndexModel::builder().keys(doc! {"username": 1}).options(unique).build())
+            .await?;
+        Ok(())
+    }
+}
+
+/// 事务（含提交）最多尝试的次数
+const TRANSACTION_ATTEMPTS: u32 = 5;
+
+fn has_label(e: &StoreError, label: &str) -> bool {
+    matches!(e, StoreError::Mongo(e) if e.contains_label(label))
+}
+
+// 违反唯一索引（E11000）
+fn is_duplicate_key(e: &mongodb::error::Error) -> bool {
+    match e.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(we)) => we.code == 11000,
+        ErrorKind::Command(ce) => ce.code == 11000,
+        _ => false,
+    }
+}
+
+fn username_conflict(e: mongodb::error::Error) -> StoreError {
+    if is_duplicate_key(&e) {
+        StoreError::Conflict("用户名已存在".to_string())
+    } else {
+        StoreError::Mongo(e)
+    }
+}
+
+// 按ID定位单个文档的过滤条件，始终带上归属字段（user_id 或 ledger_id），保证只能操作范围内的数据
+fn scoped<T: Owned>(owner: ObjectId, id: ObjectId) -> Document {
+    doc! {"id": id, T::SCOPE: owner}
+}
+
+async fn find_owned<T>(coll: &Collection<T>, owner: ObjectId, id: ObjectId) -> DBResult<Option<T>>
+where
+    T: Owned + DeserializeOwned + Send + Sync,
+{
+    Ok(coll.find_one(scoped::<T>(owner, id)).await?)
+}
+
+async fn replace_owned<T>(coll: &Collection<T>, owner: ObjectId, id: ObjectId, value: &T) -> DBResult<bool>
+where
+    T: Owned + Serialize + Send + Sync,
+{
+    let res = coll.replace_one(scoped::<T>(owner, id), value).await?;
+    Ok(res.matched_count > 0)
+}
+
+async fn delete_owned<T>(coll: &Collection<T>, owner: ObjectId, id: ObjectId) -> DBResult<bool>
+where
+    T: Owned + Send + Sync,
+{
+    let res = coll.delete_one(scoped::<T>(owner, id)).await?;
+    Ok(res.deleted_count > 0)
+}
+
+// 转义正则元字符，使名称筛选按字面量“包含”匹配
+fn escape_regex(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn order_filter_doc(ledger_id: ObjectId, filter: &OrderFilter) -> Document {
+    let mut query = doc! {"ledger_id": ledger_id};
+    if let Some(account_id) = filter.account_id {
+        query.insert("account_id", account_id);
+    }
+    if let Some(category_ids) = &filter.category_ids {
+        query.insert("category_id", doc! {"$in": category_ids});
+    }
+    if let Some(name) = &filter.name {
+        query.insert("name", doc! {"$regex": escape_regex(name)});
+    }
+    if let Some(order_type) = &filter.order_type {
+        query.insert("order_type", order_type);
+    }
+    let mut date = Document::new();
+    if let Some(start) = filter.date_start {
+        date.insert("$gte", start);
+    }
+    if let Some(end) = filter.date_end {
+        date.insert("$lte", end);
+    }
+    if !date.is_empty() {
+        query.insert("date", date);
+    }
+    query
+}
+
+#[async_trait]
+impl LedgerStore for MongoDB {
+    // 用户相关
+    async fn create_user(&self, user: User) -> DBResult<User> {
+        self.users_collection().insert_one(&user).await.map_err(username_conflict)?;
+        Ok(user)
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> DBResult<Option<User>> {
+        Ok(self.users_collection().find_one(doc! {"username": username}).await?)
+    }
+
+    async fn get_user(&self, user_id: ObjectId) -> DBResult<Option<User>> {
+        Ok(self.users_collection().find_one(doc! {"id": user_id}).await?)
+    }
+
+    async fn set_base_currency(&self, user_id: ObjectId, currency: Currency) -> DBResult<bool> {
+        let res = self.users_collection()
+            .update_one(doc! {"id": user_id}, doc! {"$set": {"base_currency": currency.code()}})
+            .await?;
+        Ok(res.matched_count > 0)
+    }
+
+    async fn set_email(&self, user_id: ObjectId, email: Option<String>) -> DBResult<bool> {
+        let res = self.users_collection()
+            .update_one(doc! {"id": user_id}, doc! {"$set": {"email": email}})
+            .await?;
+        Ok(res.matched_count > 0)
+    }
+
+    async fn set_default_ledger(&self, user_id: ObjectId, ledger_id: ObjectId) -> DBResult<bool> {
+        let res = self.users_collection()
+            .update_one(doc! {"id": user_id}, doc! {"$set": {"default_ledger_id": ledger_id}})
+            .await?;
+        Ok(res.matched_count > 0)
+    }
+
+    async fn set_password(&self, user_id: ObjectId, password_hash: String) -> DBResult<bool> {
+        let res = self.users_collection()
+            .update_one(doc! {"id": user_id}, doc! {"$set": {"password": password_hash}})
+            .await?;
+        Ok(res.matched_count > 0)
+    }
+
+    async fn set_two_factor(&self, user_id: ObjectId, two_factor: Option<TwoFactor>) -> DBResult<bool> {
+        let value = mongodb::bson::to_bson(&two_factor).map_err(mongodb::error::Error::custom)?;
+        let res = self.users_collection()
+            .update_one(doc! {"id": user_id}, doc! {"$set": {"two_factor": value}})
+            .await?;
+        Ok(res.matched_count > 0)
+    }
+
+    async fn use_totp_step(&self, user_id: ObjectId, step: i64) -> DBResult<bool> {
+        let res = self.users_collection()
+            .update_one(
+                doc! {"id": user_id, "two_factor.last_step": {"$lt": step}},
+                doc! {"$set": {"two_factor.last_step": step}},
+            )
+            .await?;
+        Ok(res.modified_count > 0)
+    }
+
+    async fn use_recovery_code(&self, user_id: ObjectId, code_hash: &str) -> DBResult<bool> {
+        let res = self.users_collection()
+            .update_one(
+                doc! {"id": user_id, "two_factor.recovery_hashes": code_hash},
+                doc! {"$pull": {"two_factor.recovery_hashes": code_hash}},
+            )
+            .await?;
+        Ok(res.modified_count > 0)
+    }
+
+    async fn rename_user(&self, user_id: ObjectId, username: String) -> DBResult<bool> {
+        let res = self.users_collection()
+            .update_one(doc! {"id": user_id}, doc! {"$set": {"username": username}})
+            .await
+            .map_err(username_conflict)?;
+        Ok(res.matched_count > 0)
+    }
+
+    async fn delete_user(&self, user_id: ObjectId) -> DBResult<bool> {
+        self.transaction(move |db, session| Box::pin(async move {
+            let mut cursor = db.ledgers
+                .find(doc! {"members": {"$elemMatch": {"user_id": user_id, "role": "owner"}}})
+                .session(&mut *session)
+                .await?;
+            let mut owned_ledgers = Vec::new();
+            while let Some(ledger) = cursor.next(&mut *session).await.transpose()? {
+                owned_ledgers.push(ledger);
+            }
+            // 有其他成员的账本转交给接手人，只删除没有其他成员的账本
+            let mut unshared = Vec::new();
+            for ledger in owned_ledgers {
+                match ledger.successor() {
+                    Some(successor) => {
+                        db.ledgers
+                            .update_one(
+                                doc! {"id": ledger.id, "members.user_id": successor},
+                                doc! {"$set": {"members.$.role": "owner"}},
+                            )
+                            .session(&mut *session)
+                            .await?;
+                    }
+                    None => unshared.push(ledger.id),
+                }
+            }
+            db.drop_ledgers(&mut *session, &unshared).await?;
+            db.invitations
+                .delete_many(doc! {"$or": [{"invitee_id": user_id}, {"inviter_id": user_id}]})
+                .session(&mut *session)
+                .await?;
+            db.ledgers
+                .update_many(doc! {"members.user_id": user_id}, doc! {"$pull": {"members": {"user_id": user_id}}})
+                .session(&mut *session)
+                .await?;
+            let owned_by = doc! {"user_id": user_id};
+            db.rates.delete_many(owned_by.clone()).session(&mut *session).await?;
+            db.sessions.delete_many(owned_by.clone()).session(&mut *session).await?;
+            db.api_keys.delete_many(owned_by.clone()).session(&mut *session).await?;
+            db.notifications.delete_many(owned_by).session(&mut *session).await?;
+            let res = db.users_collection()
+                .delete_one(doc! {"id": user_id})
+                .session(&mut *session)
+                .await?;
+            Ok(res.deleted_count > 0)
+        })).await
+    }
+
+    // 账户相关
+    async fn create_account(&self, account: Account) -> DBResult<Account> {
+        self.accounts.insert_one(&account).await?;
+        Ok(account)
+    }
+
+    async fn get_accounts_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Account>> {
+        let mut cursor = self.accounts.find(doc! {"ledger_id": &ledger_id}).await?;
+        let mut accounts = Vec::new();
+        while let Some(account) = cursor.try_next().await? {
+            accounts.push(account);
+        }
+        Ok(accounts)
+    }
+
+    async fn get_account(&self, ledger_id: ObjectId, account_id: ObjectId) -> DBResult<Option<Account>> {
+        find_owned(&self.accounts, ledger_id, account_id).await
+    }
+
+    async fn update_account(&self, account: Account) -> DBResult<Option<Account>> {
+        self.transaction(move |db, session| {
+            let account = account.clone();
+            Box::pin(async move {
+                let Some(current) = db.accounts
+                    .find_one(scoped::<Account>(account.ledger_id, account.id))
+                    .session(&mut *session)
+                    .await? else {
+                    return Ok(None);
+                };
+                let diff = account.initial_balance.minor - current.initial_balance.minor;
+                let balance = add_balance(current.balance, diff)?;
+                let currency = account.initial_balance.currency.code();
+                let set = doc! {
+                    "name": &account.name,
+                    "account_type": &account.account_type,
+                    "remark": account.remark.clone(),
+                    "initial_balance": {"minor": account.initial_balance.minor, "currency": currency},
+                    "balance": {"minor": balance.minor, "currency": currency},
+                };
+                db.accounts
+                    .update_one(scoped::<Account>(account.ledger_id, account.id), doc! {"$set": set})
+                    .session(&mut *session)
+                    .await?;
+                let balance = Money::new(balance.minor, account.initial_balance.currency);
+                Ok(Some(Account { balance, ..account }))
+            })
+        }).await
+    }
+
+    async fn delete_account(&self, ledger_id: ObjectId, account_id: ObjectId) -> DBResult<bool> {
+        self.transaction(move |db, session| Box::pin(async move {
+            let orders = db.orders
+                .count_documents(doc! {"ledger_id": ledger_id, "account_id": account_id})
+                .session(&mut *session)
+                .await?;
+            let assets = db.assets
+                .count_documents(doc! {"ledger_id": ledger_id, "account_id": account_id})
+                .session(&mut *session)
+                .await?;
+            if orders > 0 || assets > 0 {
+                return Err(StoreError::Conflict(format!("账户仍被 {} 条订单、{} 项资产引用，无法删除", orders, assets)));
+            }
+            let res = db.accounts
+                .delete_one(scoped::<Account>(ledger_id, account_id))
+                .session(&mut *session)
+                .await?;
+            Ok(res.deleted_count > 0)
+        })).await
+    }
+
+    async fn recompute_account_balance(&self, ledger_id: ObjectId, account_id: ObjectId) -> DBResult<Account> {
+        self.transaction(move |db, session| Box::pin(async move {
+            let account = db.accounts
+                .find_one(scoped::<Account>(ledger_id, account_id))
+                .session(&mut *session)
+                .await?
+                .ok_or(StoreError::NotFound("账户"))?;
+            let mut cursor = db.orders
+                .find(doc! {"account_id": account_id, "ledger_id": ledger_id})
+                .session(&mut *session)
+                .await?;
+            let mut balance = account.initial_balance;
+            while let Some(order) = cursor.next(&mut *session).await.transpose()? {
+                balance = add_balance(balance, order.balance_delta())?;
+            }
+            db.accounts
+                .update_one(scoped::<Account>(ledger_id, account_id), doc! {"$set": {"balance.minor": balance.minor}})
+                .session(&mut *session)
+                .await?;
+            Ok(Account { balance, ..account })
+        })).await
+    }
+
+    // 分类相关
+    async fn create_category(&self, category: Category) -> DBResult<Category> {
+        self.categories.insert_one(&category).await?;
+        Ok(category)
+    }
+
+    async fn get_categories_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Category>> {
+        let mut cursor = self.categories.find(doc! {"ledger_id": &ledger_id}).await?;
+        let mut categories = Vec::new();
+        while let Some(category) = cursor.try_next().await? {
+            categories.push(category);
+        }
+        Ok(categories)
+    }
+
+    async fn get_category(&self, ledger_id: ObjectId, category_id: ObjectId) -> DBResult<Option<Category>> {
+        find_owned(&self.categories, ledger_id, category_id).await
+    }
+
+    async fn update_category(&self, category: Category) -> DBResult<bool> {
+        self.transaction(move |db, session| {
+            let category = category.clone();
+            Box::pin(async move {
+                db.lock_category_tree(&mut *session, category.ledger_id).await?;
+                let categories = db.categories_in(&mut *session, category.ledger_id).await?;
+                let Some(current) = categories.iter().find(|c| c.id == category.id) else {
+                    return Ok(false);
+                };
+                if let Some(parent_id) = category.parent_id && current.parent_id != Some(parent_id) {
+                    check_parent(&categories, category.id, parent_id).map_err(StoreError::Conflict)?;
+                }
+                db.categories
+                    .replace_one(scoped::<Category>(category.ledger_id, category.id), &category)
+                    .session(&mut *session)
+                    .await?;
+                Ok(true)
+            })
+        }).await
+    }
+
+    async fn delete_category(&self, ledger_id: ObjectId, category_id: ObjectId, reassign_to: Option<ObjectId>) -> DBResult<bool> {
+        self.transaction(move |db, session| Box::pin(async move {
+            db.lock_category_tree(&mut *session, ledger_id).await?;
+            let categories = db.categories_in(&mut *session, ledger_id).await?;
+            if !categories.iter().any(|c| c.id == category_id) {
+                return Ok(false);
+            }
+            let budgets = db.budgets
+                .count_documents(doc! {"ledger_id": ledger_id, "category_id": category_id})
+                .session(&mut *session)
+                .await?;
+            let children = categories.iter().filter(|c| c.parent_id == Some(category_id)).count();
+            if let Some(target) = reassign_to {
+                if !categories.iter().any(|c| c.id == target) {
+                    return Err(StoreError::NotFound("目标分类"));
+                }
+                if let Err(message) = check_reassign(&categories, category_id, target) {
+                    return Err(StoreError::Conflict(message));
+                }
+                db.budgets
+                    .update_many(doc! {"ledger_id": ledger_id, "category_id": category_id}, doc! {"$set": {"category_id": target}})
+                    .session(&mut *session)
+                    .await?;
+                db.categories
+                    .update_many(doc! {"ledger_id": ledger_id, "parent_id": category_id}, doc! {"$set": {"parent_id": target}})
+                    .session(&mut *session)
+                    .await?;
+            } else if budgets > 0 || children > 0 {
+                return Err(StoreError::Conflict(format!(
+                    "分类仍被 {} 个预算、{} 个子分类引用，请指定 reassign_to 转移后再删除", budgets, children,
+                )));
+            }
+            // 订单转到目标分类，未指定时变为未分类
+            db.orders
+                .update_many(doc! {"ledger_id": ledger_id, "category_id": category_id}, doc! {"$set": {"category_id": reassign_to}})
+                .session(&mut *session)
+                .await?;
+            db.categories
+                .delete_one(scoped::<Category>(ledger_id, category_id))
+                .session(&mut *session)
+                .await?;
+            Ok(true)
+        })).await
+    }
+
+    // 资产相关
+    async fn create_asset(&self, asset: Asset) -> DBResult<Asset> {
+        self.assets.insert_one(&asset).await?;
+        Ok(asset)
+    }
+
+    async fn get_assets_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Asset>> {
+        let mut cursor = self.assets.find(doc! {"ledger_id": &ledger_id}).await?;
+        let mut assets = Vec::new();
+        while let Some(asset) = cursor.try_next().await? {
+            assets.push(asset);
+        }
+        Ok(assets)
+    }
+
+    async fn get_asset(&self, ledger_id: ObjectId, asset_id: ObjectId) -> DBResult<Option<Asset>> {
+        find_owned(&self.assets, ledger_id, asset_id).await
+    }
+
+    async fn update_asset(&self, asset: Asset) -> DBResult<bool> {
+        replace_owned(&self.assets, asset.ledger_id, asset.id, &asset).await
+    }
+
+    async fn delete_asset(&self, ledger_id: ObjectId, asset_id: ObjectId) -> DBResult<bool> {
+        delete_owned(&self.assets, ledger_id, asset_id).await
+    }
+
+    // 订单相关
+    async fn create_order(&self, order: Order) -> DBResult<Order> {
+        let Some(account_id) = order.account_id else {
+            self.orders.insert_one(&order).await?;
+            return Ok(order);
+        };
+        self.transaction(move |db, session| {
+            let order = order.clone();
+            Box::pin(async move {
+                let filter = doc! {"id": account_id, "ledger_id": order.ledger_id, "balance.currency": order.amount.currency.code()};
+                if !db.adjust_balance(&mut *session, filter, order.balance_delta()).await? {
+                    return Err(StoreError::NotFound("账户"));
+                }
+                db.orders.insert_one(&order).session(&mut *session).await?;
+                Ok(order)
+            })
+        }).await
+    }
+
+    async fn get_orders_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Order>> {
+        let mut cursor = self.orders.find(doc! {"ledger_id": &ledger_id}).await?;
+        let mut orders = Vec::new();
+        while let Some(order) = cursor.try_next().await? {
+            orders.push(order);
+        }
+        Ok(orders)
+    }
+
+    async fn get_order(&self, ledger_id: ObjectId, order_id: ObjectId) -> DBResult<Option<Order>> {
+        find_owned(&self.orders, ledger_id, order_id).await
+    }
+
+    async fn find_orders(&self, ledger_id: ObjectId, filter: &OrderFilter, page: &OrderPage) -> DBResult<Vec<Order>> {
+        let mut query = order_filter_doc(ledger_id, filter);
+        let (op, dir) = match page.sort {
+            OrderSort::DateDesc => ("$lt", -1),
+            OrderSort::DateAsc => ("$gt", 1),
+        };
+        if let Some((date, id)) = page.after {
+            query.insert("$or", vec![
+                doc! {"date": {op: date}},
+                doc! {"date": date, "id": {op: id}},
+            ]);
+        }
+        let mut find = self.orders
+            .find(query)
+            .sort(doc! {"date": dir, "id": dir})
+            .limit(page.limit);
+        if page.after.is_none() {
+            find = find.skip(page.skip);
+        }
+        let mut cursor = find.await?;
+        let mut orders = Vec::new();
+        while let Some(order) = cursor.try_next().await? {
+            orders.push(order);
+        }
+        Ok(orders)
+    }
+
+    async fn count_orders(&self, ledger_id: ObjectId, filter: &OrderFilter) -> DBResult<u64> {
+        Ok(self.orders.count_documents(order_filter_doc(ledger_id, filter)).await?)
+    }
+
+    async fn order_totals(&self, ledger_id: ObjectId, filter: &OrderFilter, base: Option<Currency>) -> DBResult<Vec<OrderTotal>> {
+        self.order_totals_by_period(ledger_id, filter, base, &[]).await
+    }
+
+    async fn order_totals_by_period(&self, ledger_id: ObjectId, filter: &OrderFilter, base: Option<Currency>, starts: &[DateTime]) -> DBResult<Vec<OrderTotal>> {
+        let day = doc! {"$dateTrunc": {"date": "$date", "unit": "day"}};
+        let base = base.map(|c| c.code());
+        let mut key = doc! {
+            "order_type": "$order_type",
+            "category_id": "$category_id",
+            "currency": "$amount.currency",
+            "date": {"$cond": [{"$eq": ["$amount.currency", base]}, Bson::Null, day.clone()]},
+        };
+        let mut pipeline = vec![doc! {"$match": order_filter_doc(ledger_id, filter)}];
+        if !starts.is_empty() {
+            // 所在周期为开始时间不晚于订单时间的周期个数减一
+            let starts: Vec<Bson> = starts.iter().map(|s| Bson::DateTime(*s)).collect();
+            pipeline.push(doc! {"$set": {"period": {"$subtract": [
+                {"$size": {"$filter": {"input": starts, "cond": {"$lte": ["$$this", "$date"]}}}},
+                1,
+            ]}}});
+            pipeline.push(doc! {"$match": {"period": {"$gte": 0}}});
+            key.insert("period", "$period");
+        }
+        pipeline.push(doc! {"$group": {
+            "_id": key,
+            "date": {"$min": day},
+            "minor": {"$sum": "$amount.minor"},
+        }});
+        let mut cursor = self.orders.aggregate(pipeline).await?;
+        let mut totals = Vec::new();
+        while let Some(row) = cursor.try_next().await? {
+            let key = row.get_document("_id").map_err(mongodb::error::Error::custom)?;
+            let currency = key.get_str("currency").map_err(mongodb::error::Error::custom)?;
+            let minor = match row.get("minor") {
+                Some(Bson::Int64(v)) => *v,
+                Some(Bson::Int32(v)) => *v as i64,
+                // 合计超出 i64 范围时 $sum 会返回浮点数
+                _ => return Err(StoreError::Conflict("订单金额合计超出范围".to_string())),
+            };
+            let period = match key.get("period") {
+                Some(Bson::Int32(v)) => *v as usize,
+                Some(Bson::Int64(v)) => *v as usize,
+                _ => 0,
+            };
+            totals.push(OrderTotal {
+                order_type: key.get_str("order_type").map_err(mongodb::error::Error::custom)?.to_string(),
+                category_id: key.get_object_id("category_id").ok(),
+                date: *row.get_datetime("date").map_err(mongodb::error::Error::custom)?,
+                amount: Money::new(minor, currency.parse().map_err(mongodb::error::Error::custom)?),
+                period,
+            });
+        }
+        Ok(totals)
+    }
+
+    async fn delete_order(&self, ledger_id: ObjectId, order_id: ObjectId) -> DBResult<bool> {
+        self.transaction(move |db, session| Box::pin(async move {
+            let Some(order) = db.orders
+                .find_one_and_delete(scoped::<Order>(ledger_id, order_id))
+                .session(&mut *session)
+                .await? else {
+                return Ok(false);
+            };
+            let mut deleted = vec![order];
+            if let Some(link) = &deleted[0].transfer {
+                let peer = db.orders
+                    .find_one_and_delete(scoped::<Order>(ledger_id, link.peer_order_id))
+                    .session(&mut *session)
+                    .await?;
+                deleted.extend(peer);
+            }
+            for order in &deleted {
+                if let Some(account_id) = order.account_id {
+                    db.adjust_balance(&mut *session, scoped::<Account>(ledger_id, account_id), -order.balance_delta()).await?;
+                }
+            }
+            Ok(true)
+        })).await
+    }
+
+    async fn create_transfer(&self, outgoing: Order, incoming: Order) -> DBResult<(Order, Order)> {
+        self.transaction(move |db, session| {
+            let outgoing = outgoing.clone();
+            let incoming = incoming.clone();
+            Box::pin(async move {
+                for order in [&outgoing, &incoming] {
+                    let filter = doc! {"id": order.account_id, "ledger_id": order.ledger_id, "balance.currency": order.amount.currency.code()};
+                    if !db.adjust_balance(&mut *session, filter, order.balance_delta()).await? {
+                        return Err(StoreError::NotFound("账户"));
+                    }
+                }
+                db.orders
+                    .insert_many([&outgoing, &incoming])
+                    .session(&mut *session)
+                    .await?;
+                Ok((outgoing, incoming))
+            })
+        }).await
+    }
+
+    async fn get_orders_pending_alerts(&self) -> DBResult<Vec<Order>> {
+        let mut cursor = self.orders.find(doc! {"alerts_pending": true}).await?;
+        let mut orders = Vec::new();
+        while let Some(order) = cursor.try_next().await? {
+            orders.push(order);
+        }
+        Ok(orders)
+    }
+
+    async fn clear_alerts_pending(&self, order_id: ObjectId) -> DBResult<()> {
+        self.orders.update_one(doc! {"id": order_id}, doc! {"$unset": {"alerts_pending": ""}}).await?;
+        Ok(())
+    }
+
+    // 预算相关
+    async fn create_budget(&self, budget: Budget) -> DBResult<Budget> {
+        self.budgets.insert_one(&budget).await?;
+        Ok(budget)
+    }
+
+    async fn get_budgets_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Budget>> {
+        let mut cursor = self.budgets.find(doc! {"ledger_id": &ledger_id}).await?;
+        let mut budgets = Vec::new();
+        while let Some(budget) = cursor.try_next().await? {
+            budgets.push(budget);
+        }
+        Ok(budgets)
+    }
+
+    async fn get_budget(&self, ledger_id: ObjectId, budget_id: ObjectId) -> DBResult<Option<Budget>> {
+        find_owned(&self.budgets, ledger_id, budget_id).await
+    }
+
+    async fn update_budget(&self, budget: Budget) -> DBResult<bool> {
+        replace_owned(&self.budgets, budget.ledger_id, budget.id, &budget).await
+    }
+
+    async fn delete_budget(&self, ledger_id: ObjectId, budget_id: ObjectId) -> DBResult<bool> {
+        delete_owned(&self.budgets, ledger_id, budget_id).await
+    }
+
+    // 账本相关
+    async fn create_ledger(&self, ledger: Ledger) -> DBResult<Ledger> {
+        self.ledgers.insert_one(&ledger).await?;
+        Ok(ledger)
+    }
+
+    async fn get_ledger(&self, ledger_id: ObjectId) -> DBResult<Option<Ledger>> {
+        Ok(self.ledgers.find_one(doc! {"id": ledger_id}).await?)
+    }
+
+    async fn get_ledgers_for_user(&self, user_id: ObjectId) -> DBResult<Vec<Ledger>> {
+        let mut cursor = self.ledgers.find(doc! {"members.user_id": &user_id}).await?;
+        let mut ledgers = Vec::new();
+        while let Some(ledger) = cursor.try_next().await? {
+            ledgers.push(ledger);
+        }
+        Ok(ledgers)
+    }
+
+    async fn rename_ledger(&self, ledger_id: ObjectId, name: String) -> DBResult<bool> {
+        let res = self.ledgers
+            .update_one(doc! {"id": ledger_id}, doc! {"$set": {"name": name}})
+            .await?;
+        Ok(res.matched_count > 0)
+    }
+
+    async fn delete_ledger(&self, ledger_id: ObjectId) -> DBResult<bool> {
+        self.transaction(move |db, session| Box::pin(async move {
+            let deleted = db.drop_ledgers(&mut *session, &[ledger_id]).await?;
+            Ok(deleted > 0)
+        })).await
+    }
+
+    async fn set_member_role(&self, ledger_id: ObjectId, user_id: ObjectId, role: Role) -> DBResult<bool> {
+        let role = mongodb::bson::to_bson(&role).map_err(mongodb::error::Error::custom)?;
+        let res = self.ledgers
+            .update_one(
+                doc! {"id": ledger_id, "members": {"$elemMatch": {"user_id": user_id, "role": {"$ne": "owner"}}}},
+                doc! {"$set": {"members.$.role": role}},
+            )
+            .await?;
+        Ok(res.matched_count > 0)
+    }
+
+    async fn remove_member(&self, ledger_id: ObjectId, user_id: ObjectId) -> DBResult<bool> {
+        let res = self.ledgers
+            .update_one(
+                doc! {"id": ledger_id},
+                doc! {"$pull": {"members": {"user_id": user_id, "role": {"$ne": "owner"}}}},
+            )
+            .await?;
+        Ok(res.modified_count > 0)
+    }
+
+    async fn create_invitation(&self, invitation: Invitation) -> DBResult<Invitation> {
+        self.invitations.insert_one(&invitation).await?;
+        Ok(invitation)
+    }
+
+    async fn get_invitation(&self, invitation_id: ObjectId) -> DBResult<Option<Invitation>> {
+        Ok(self.invitations.find_one(doc! {"id": invitation_id}).await?)
+    }
+
+    async fn get_invitations_for_user(&self, invitee_id: ObjectId) -> DBResult<Vec<Invitation>> {
+        let mut cursor = self.invitations.find(doc! {"invitee_id": &invitee_id}).await?;
+        let mut invitations = Vec::new();
+        while let Some(invitation) = cursor.try_next().await? {
+            invitations.push(invitation);
+        }
+        Ok(invitations)
+    }
+
+    async fn get_invitations_by_ledger(&self, ledger_id: ObjectId) -> DBResult<Vec<Invitation>> {
+        let mut cursor = self.invitations.find(doc! {"ledger_id": &ledger_id}).await?;
+        let mut invitations = Vec::new();
+        while let Some(invitation) = cursor.try_next().await? {
+            invitations.push(invitation);
+        }
+        Ok(invitations)
+    }
+
+    async fn delete_invitation(&self, invitation_id: ObjectId) -> DBResult<bool> {
+        let res = self.invitations.delete_one(doc! {"id": invitation_id}).await?;
+        Ok(res.deleted_count > 0)
+    }
+
+    async fn accept_invitation(&self, invitation_id: ObjectId) -> DBResult<Ledger> {
+        self.transaction(move |db, session| Box::pin(async move {
+            let Some(invitation) = db.invitations.find_one_and_delete(doc! {"id": invitation_id}).session(&mut *session).await? else {
+                return Err(StoreError::NotFound("邀请"));
+            };
+            let Some(mut ledger) = db.ledgers.find_one(doc! {"id": invitation.ledger_id}).session(&mut *session).await? else {
+                return Err(StoreError::NotFound("账本"));
+            };
+            if ledger.role_of(invitation.invitee_id).is_some() {
+                return Err(StoreError::Conflict("已是账本成员".to_string()));
+            }
+            let member = Member { user_id: invitation.invitee_id, role: invitation.role };
+            let value = mongodb::bson::to_bson(&member).map_err(mongodb::error::Error::custom)?;
+            db.ledgers
+                .update_one(doc! {"id": ledger.id}, doc! {"$push": {"members": value}})
+                .session(&mut *session)
+                .await?;
+            ledger.members.push(member);
+            Ok(ledger)
+        })).await
+    }
+
+    // 汇率相关
+    async fn create_rates(&self, rates: Vec<ExchangeRate>) -> DBResult<Vec<ExchangeRate>> {
+        if !rates.is_empty() {
+            self.rates.insert_many(&rates).await?;
+        }
+        Ok(rates)
+    }
+
+    async fn get_rates_by_user(&self, user_id: ObjectId) -> DBResult<Vec<ExchangeRate>> {
+        let mut cursor = self.rates.find(doc! {"user_id": &user_id}).await?;
+        let mut rates = Vec::new();
+        while let Some(rate) = cursor.try_next().await? {
+            rates.push(rate);
+        }
+        Ok(rates)
+    }
+
+    // 会话相关
+    async fn create_session(&self, session: Session) -> DBResult<Session> {
+        self.sessions.insert_one(&session).await?;
+        Ok(session)
+    }
+
+    async fn get_session(&self, session_id: ObjectId) -> DBResult<Option<Session>> {
+        Ok(self.sessions.find_one(doc! {"id": session_id}).await?)
+    }
+
+    async fn rotate_session(&self, session_id: ObjectId, old_hash: &str, new_hash: String, expires_at: DateTime) -> DBResult<bool> {
+        let res = self.sessions
+            .update_one(
+                doc! {"id": session_id, "refresh_hash": old_hash, "revoked": false},
+                doc! {"$set": {"refresh_hash": new_hash, "previous_hash": old_hash, "expires_at": expires_at}},
+            )
+            .await?;
+        Ok(res.modified_count > 0)
+    }
+
+    async fn revoke_session(&self, user_id: ObjectId, session_id: ObjectId) -> DBResult<bool> {
+        let res = self.sessions
+            .update_one(scoped::<Session>(user_id, session_id), doc! {"$set": {"revoked": true}})
+            .await?;
+        Ok(res.matched_count > 0)
+    }
+
+    async fn revoke_all_sessions(&self, user_id: ObjectId) -> DBResult<u64> {
+        let res = self.sessions
+            .update_many(doc! {"user_id": user_id, "revoked": false}, doc! {"$set": {"revoked": true}})
+            .await?;
+        Ok(res.modified_count)
+    }
+
+    async fn use_challenge(&self, jti: &str, expires_at: DateTime) -> DBResult<bool> {
+        match self.used_challenges.insert_one(UsedChallenge { jti: jti.to_string(), expires_at }).await {
+            Ok(_) => Ok(true),
+            Err(e) if is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // API 密钥相关
+    async fn create_api_key(&self, key: ApiKey) -> DBResult<ApiKey> {
+        self.api_keys.insert_one(&key).await?;
+        Ok(key)
+    }
+
+    async fn get_api_key(&self, key_id: ObjectId) -> DBResult<Option<ApiKey>> {
+        Ok(self.api_keys.find_one(doc! {"id": key_id}).await?)
+    }
+
+    async fn get_api_keys_by_user(&self, user_id: ObjectId) -> DBResult<Vec<ApiKey>> {
+        let mut cursor = self.api_keys.find(doc! {"user_id": &user_id}).await?;
+        let mut keys = Vec::new();
+        while let Some(key) = cursor.try_next().await? {
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+
+    async fn delete_api_key(&self, user_id: ObjectId, key_id: ObjectId) -> DBResult<bool> {
+        delete_owned(&self.api_keys, user_id, key_id).await
+    }
+
+    // 通知相关
+    async fn create_notification(&self, notification: Notification) -> DBResult<bool> {
+        match self.notifications.insert_one(&notification).await {
+            Ok(_) => Ok(true),
+            Err(e) if is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_notifications_for_user(&self, user_id: ObjectId, unread_only: bool) -> DBResult<Vec<Notification>> {
+        let mut filter = doc! {"user_id": user_id};
+        if unread_only {
+            filter.insert("read", false);
+        }
+        let mut cursor = self.notifications.find(filter).sort(doc! {"created_at": -1, "id": -1}).await?;
+        let mut notifications = Vec::new();
+        while let Some(notification) = cursor.try_next().await? {
+            notifications.push(notification);
+        }
+        Ok(notifications)
+    }
+
+    async fn mark_notification_read(&self, user_id: ObjectId, notification_id: ObjectId) -> DBResult<Option<Notification>> {
+        Ok(self.notifications
+            .find_one_and_update(scoped::<Notification>(user_id, notification_id), doc! {"$set": {"read": true}})
+            .return_document(mongodb::options::ReturnDocument::After)
+            .await?)
+    }
+
+    async fn mark_all_notifications_read(&self, user_id: ObjectId) -> DBResult<u64> {
+        let res = self.notifications
+            .update_many(doc! {"user_id": user_id, "read": false}, doc! {"$set": {"read": true}})
+            .await?;
+        Ok(res.modified_count)
+    }
+
+    async fn get_pending_notifications(&self, max_attempts: u32) -> DBResult<Vec<Notification>> {
+        let mut cursor = self.notifications
+            .find(doc! {"delivered_at": null, "attempts": {"$lt": max_attempts}})
+            .sort(doc! {"created_at": 1})
+            .await?;
+        let mut notifications = Vec::new();
+        while let Some(notification) = cursor.try_next().await? {
+            notifications.push(notification);
+        }
+        Ok(notifications)
+    }
+
+    async fn record_delivery(&self, notification_id: ObjectId, error: Option<String>) -> DBResult<()> {
+        let update = match error {
+            None => doc! {"$inc": {"attempts": 1}, "$set": {"delivered_at": DateTime::now(), "last_error": null}},
+            Some(error) => doc! {"$inc": {"attempts": 1}, "$set": {"last_error": error}},
+        };
+        self.notifications.update_one(doc! {"id": notification_id}, update).await?;
+        Ok(())
+    }
+
+    // 登录失败记录
+    async fn get_login_attempt(&self, key: &str) -> DBResult<Option<LoginAttempt>> {
+        Ok(self.login_attempts.find_one(doc! {"key": key}).await?)
+    }
+
+    async fn record_login_failure(&self, key: &str, window_start: DateTime) -> DBResult<LoginAttempt> {
+        // 管道更新保证并发失败时计数不会丢失
+        let attempt = self.login_attempts
+            .find_one_and_update(
+                doc! {"key": key},
+                vec![doc! {"$set": {
+                    "failures": {"$cond": [
+                        {"$gte": ["$last_failure", window_start]},
+                        {"$add": ["$failures", 1]},
+                        1,
+                    ]},
+                    "last_failure": DateTime::now(),
+                }}],
+            )
+            .upsert(true)
+            .return_document(mongodb::options::ReturnDocument::After)
+            .await?;
+        attempt.ok_or_else(|| StoreError::Mongo(mongodb::error::Error::custom("登录失败记录写入后未返回")))
+    }
+
+    async fn clear_login_attempts(&self, key: &str) -> DBResult<()> {
+        self.login_attempts.delete_one(doc! {"key": key}).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use crate::db::{DBResult, StoreError};
+use crate::models::money::Money;
+use crate::models::transaction::Order;
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+/// 订单筛选条件，由存储层下推到数据库执行
+#[derive(Debug, Clone, Default)]
+pub struct OrderFilter {
+    pub account_id: Option<ObjectId>,
+    pub category_ids: Option<Vec<ObjectId>>, // 属于其中任一分类
+    pub name: Option<String>,       // 名称包含
+    pub order_type: Option<String>,
+    pub date_start: Option<DateTime>,
+    pub date_end: Option<DateTime>,
+}
+
+impl OrderFilter {
+    /// 内存实现使用的匹配逻辑，与 Mongo 查询条件保持一致
+    pub fn matches(&self, order: &Order) -> bool {
+        self.account_id.is_none_or(|id| order.account_id == Some(id))
+            && self.category_ids.as_ref().is_none_or(|ids| order.category_id.is_some_and(|id| ids.contains(&id)))
+            && self.name.as_ref().is_none_or(|name| order.name.contains(name.as_str()))
+            && self.order_type.as_ref().is_none_or(|t| order.order_type == *t)
+            && self.date_start.is_none_or(|start| order.date >= start)
+            && self.date_end.is_none_or(|end| order.date <= end)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OrderSort {
+    #[default]
+    DateDesc, // 最新的在前
+    DateAsc,
+}
+
+/// 分页方式：`after` 为游标（上一页最后一条的日期和ID），给定时忽略 `skip`
+#[derive(Debug, Clone, Default)]
+pub struct OrderPage {
+    pub sort: OrderSort,
+    pub after: Option<(DateTime, ObjectId)>,
+    pub skip: u64,
+    pub limit: i64,
+}
+
+impl OrderPage {
+    /// 订单是否排在游标之后
+    pub fn is_after_cursor(&self, order: &Order) -> bool {
+        let Some((date, id)) = self.after else { return true };
+        match self.sort {
+            OrderSort::DateDesc => (order.date, order.id) < (date, id),
+            OrderSort::DateAsc => (order.date, order.id) > (date, id),
+        }
+    }
+}
+
+/// 按类型、分类、币种和日期分组的订单金额合计。`date` 为订单所在 UTC 自然日的零点（汇率按天生效）；
+/// 不需要换算的币种不再按日期分组，`date` 取其中最早的一天
+#[derive(Debug, Clone)]
+pub struct OrderTotal {
+    pub order_type: String,
+    pub category_id: Option<ObjectId>,
+    pub date: DateTime,
+    pub amount: Money,
+    /// 按周期分组时订单所在周期的下标，否则为 0
+    pub period: usize,
+}
+
+/// 账户余额加上变动额，超出范围时返回冲突
+pub fn add_balance(balance: Money, delta: i64) -> DBResult<Money> {
+    balance.checked_add(delta).ok_or_else(|| StoreError::Conflict("账户余额超出范围".to_string()))
+}
@@ -0,0 +1,137 @@
+use axum::{http::{header, StatusCode}, response::{IntoResponse, Response}, Json};
+use serde_json::json;
+use crate::db::StoreError;
+use crate::models::currency::UnknownCurrency;
+use crate::models::exchange_rate::MissingRate;
+
+/// 接口统一的错误类型，响应体为 `{"code": "...", "message": "..."}`；5xx 错误只返回固定提示。
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("{0}")]
+    Validation(String),   // 400 请求参数不合法
+    #[error("{0}")]
+    Unauthorized(String), // 401 未登录或凭证无效
+    #[error("{0}")]
+    Forbidden(String),    // 403 凭证有效但权限不足
+    #[error("{0}")]
+    NotFound(String),     // 404 不存在或不属于当前用户
+    #[error("{0}")]
+    Conflict(String),     // 409 与现有数据冲突
+    #[error("{message}")]
+    TooManyRequests { message: String, retry_after: u64 }, // 429 请求过于频繁
+    #[error("{0}")]
+    Storage(StoreError),  // 500 数据库错误
+    #[error("{0}")]
+    Internal(String),     // 500 其他内部错误
+}
+
+impl AppError {
+    pub fn validation(message: impl Into<String>) -> Self {
+        AppError::Validation(message.into())
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        AppError::Unauthorized(message.into())
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        AppError::Forbidden(message.into())
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::NotFound(message.into())
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        AppError::Conflict(message.into())
+    }
+
+    pub fn too_many_requests(message: impl Into<String>, retry_after: u64) -> Self {
+        AppError::TooManyRequests { message: message.into(), retry_after }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Storage(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// 供客户端判断错误类型的机器可读代码
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "validation_error",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::TooManyRequests { .. } => "too_many_requests",
+            AppError::Storage(_) => "storage_error",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        // 服务端错误只记录在日志里，不把数据库等内部细节返回给客户端
+        let message = if status.is_server_error() {
+            tracing::error!("{}", self);
+            "服务器内部错误".to_string()
+        } else {
+            self.to_string()
+        };
+        let body = Json(json!({"code": self.code(), "message": message}));
+        if let AppError::TooManyRequests { retry_after, .. } = self {
+            return (status, [(header::RETRY_AFTER, retry_after.to_string())], body).into_response();
+        }
+        (status, body).into_response()
+    }
+}
+
+impl From<StoreError> for AppError {
+    fn from(e: StoreError) -> Self {
+        match e {
+            StoreError::NotFound(_) => AppError::NotFound(e.to_string()),
+            StoreError::Conflict(message) => AppError::Conflict(message),
+            e => AppError::Storage(e),
+        }
+    }
+}
+
+impl From<mongodb::bson::oid::Error> for AppError {
+    fn from(_: mongodb::bson::oid::Error) -> Self {
+        AppError::validation("无效的ID")
+    }
+}
+
+impl From<mongodb::bson::datetime::Error> for AppError {
+    fn from(e: mongodb::bson::datetime::Error) -> Self {
+        AppError::validation(format!("日期格式不合法: {}", e))
+    }
+}
+
+impl From<UnknownCurrency> for AppError {
+    fn from(e: UnknownCurrency) -> Self {
+        AppError::validation(e.to_string())
+    }
+}
+
+// 缺少汇率时提示用户先补充汇率
+impl From<MissingRate> for AppError {
+    fn from(e: MissingRate) -> Self {
+        AppError::validation(e.to_string())
+    }
+}
+
+impl From<bcrypt::BcryptError> for AppError {
+    fn from(e: bcrypt::BcryptError) -> Self {
+        AppError::Internal(format!("密码处理失败: {}", e))
+    }
+}
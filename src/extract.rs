@@ -0,0 +1,75 @@
+//! 请求提取器：与 axum 的 `Json`/`Query`/`Path` 相同，但解析失败时返回 [`AppError::Validation`]，
+//! 使格式错误的请求体、未知字段或枚举值、不合法的查询和路径参数也得到统一的 `{code, message}` 响应。
+
+use axum::extract::{FromRequest, FromRequestParts, Path, Query, Request};
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::http::request::Parts;
+use axum::Json;
+use serde::de::DeserializeOwned;
+use crate::error::AppError;
+
+/// JSON 请求体
+#[derive(Debug)]
+pub struct AppJson<T>(pub T);
+
+/// 查询参数
+#[derive(Debug)]
+pub struct AppQuery<T>(pub T);
+
+/// 路径参数
+#[derive(Debug)]
+pub struct AppPath<T>(pub T);
+
+impl From<JsonRejection> for AppError {
+    fn from(e: JsonRejection) -> Self {
+        AppError::validation(format!("请求体不合法: {}", e.body_text()))
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(e: QueryRejection) -> Self {
+        AppError::validation(format!("查询参数不合法: {}", e.body_text()))
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(e: PathRejection) -> Self {
+        AppError::validation(format!("路径参数不合法: {}", e.body_text()))
+    }
+}
+
+impl<T, S> FromRequest<S> for AppJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        Ok(AppJson(value))
+    }
+}
+
+impl<T, S> FromRequestParts<S> for AppQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(value) = Query::<T>::from_request_parts(parts, state).await?;
+        Ok(AppQuery(value))
+    }
+}
+
+impl<T, S> FromRequestParts<S> for AppPath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(value) = Path::<T>::from_request_parts(parts, state).await?;
+        Ok(AppPath(value))
+    }
+}
@@ -1,2 +1,12 @@
+pub mod config;
 pub mod models;
-pub mod db;
\ No newline at end of file
+pub mod db;
+pub mod auth;
+pub mod totp;
+pub mod notify;
+pub mod error;
+pub mod extract;
+pub mod routes;
+pub mod services;
+pub mod state;
+pub mod util;
@@ -1,33 +1,59 @@
-use axum::{Router};
-mod auth;
+use axum::{middleware, Router, ServiceExt};
+use axum::extract::Request;
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tower_http::cors::{CorsLayer, Any};
-mod db;
-mod models;
-mod routes;
-use db::MongoDB;
+use tower::Layer;
+use tower_http::cors::{AllowOrigin, CorsLayer, Any};
+use todo_list::auth::{API_KEY_HEADER, LEDGER_HEADER};
+use todo_list::config::{Config, CorsConfig};
+use todo_list::db::{migrations, LedgerStore, MongoDB};
+use todo_list::routes;
+use todo_list::state::AppState;
+
+// 允许的来源包含 "*" 时放开所有来源，否则只允许列表中的来源
+fn cors_layer(cors: &CorsConfig) -> CorsLayer {
+    let origin = if cors.allowed_origins.iter().any(|o| o == "*") {
+        AllowOrigin::from(Any)
+    } else {
+        AllowOrigin::list(cors.allowed_origins.iter().filter_map(|o| HeaderValue::from_str(o).ok()))
+    };
+    CorsLayer::new()
+        .allow_origin(origin)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE])
+        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION, HeaderName::from_static(API_KEY_HEADER), HeaderName::from_static(LEDGER_HEADER)])
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    println!("[启动] 理财系统服务启动中...");
-    let db = MongoDB::new("mongodb://localhost:27017", "finance").await?;
-    println!("[启动] MongoDB 连接成功，数据库: finance");
-    let db = Arc::new(db);
+    let config = Config::load()?;
+    // 全部日志都经由 tracing 输出，按配置的级别过滤
+    let level: tracing::Level = config.log.level.parse()?;
+    tracing_subscriber::fmt().with_max_level(level).init();
+    tracing::info!("[启动] 理财系统服务启动中...");
+    tracing::info!("[启动] 运行环境: {}，日志级别: {}", config.profile, config.log.level);
 
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([axum::http::Method::GET, axum::http::Method::POST])
-        .allow_headers([axum::http::header::CONTENT_TYPE]);
+    let db = MongoDB::new(&config.database.uri, &config.database.name).await?;
+    tracing::info!("[启动] MongoDB 连接成功，数据库: {}", config.database.name);
+    migrations::run(&db).await?;
+    db.create_indexes().await?;
+    let db: Arc<dyn LedgerStore> = Arc::new(db);
 
+    let cors = cors_layer(&config.cors);
+    let addr = config.server.bind.clone();
+    let state = AppState::new(db, config);
+    // 启动时先补做上次中断的预算提醒检查、补发未投递成功的通知，之后定期重试
+    state.notifier.spawn_retry_loop(state.db.clone());
     let app = Router::new()
         .nest("/api", routes::api::api_routes())
-        .with_state(db)
+        .with_state(state)
         .layer(cors);
+    let app = middleware::map_request(routes::api::select_book_by_path).layer(app);
 
-    let addr = "0.0.0.0:3000";
-    println!("[启动] 服务监听地址: http://{}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    println!("[启动] 服务已启动，等待请求...");
-    axum::serve(listener, app).await?;
+    tracing::info!("[启动] 服务监听地址: http://{}", addr);
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    tracing::info!("[启动] 服务已启动，等待请求...");
+    // 登录限流需要客户端地址
+    axum::serve(listener, ServiceExt::<Request>::into_make_service_with_connect_info::<SocketAddr>(app)).await?;
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,13 +1,15 @@
 use mongodb::bson::{oid::ObjectId};
 use serde::{Deserialize, Serialize};
+use crate::models::money::Money;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Account {
     pub id: ObjectId,
-    pub user_id: ObjectId,
+    pub ledger_id: ObjectId, // 所属账本
+    pub user_id: ObjectId,   // 创建者
     pub name: String,           // 账户名称
     pub account_type: String,  // 账户类型（如银行卡、现金、支付宝等）
-    pub balance: f64,          // 当前余额
-    pub currency: String,      // 币种
+    pub balance: Money,        // 当前余额（含币种）
+    pub initial_balance: Money, // 期初余额（重算余额的起点）
     pub remark: Option<String>,
 }
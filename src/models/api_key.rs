@@ -0,0 +1,29 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+/// API 密钥的权限范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    #[default]
+    ReadOnly,  // 只能调用 GET 接口
+    ReadWrite,
+}
+
+/// 供脚本、导入工具使用的个人 API 密钥，只保存密钥的哈希。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: ObjectId,
+    pub user_id: ObjectId,
+    pub name: String,
+    pub key_hash: String, // 密钥的 SHA-256
+    pub scope: ApiKeyScope,
+    pub created_at: DateTime,
+    pub expires_at: Option<DateTime>, // 为空表示永不过期
+}
+
+impl ApiKey {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|t| t <= DateTime::now())
+    }
+}
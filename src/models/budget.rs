@@ -1,13 +1,174 @@
+use chrono::{Duration, Months, Utc};
 use mongodb::bson::{oid::ObjectId, DateTime};
 use serde::{Deserialize, Serialize};
+use crate::error::AppError;
+use crate::models::exchange_rate::MissingRate;
+use crate::models::money::Money;
+
+/// 预算周期；除自定义外都会从开始日期起自动循环生成后续周期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPeriod {
+    #[serde(alias = "周", alias = "每周")]
+    Weekly,
+    #[serde(alias = "月", alias = "每月")]
+    Monthly,
+    #[serde(alias = "季", alias = "季度")]
+    Quarterly,
+    #[serde(alias = "年", alias = "每年")]
+    Yearly,
+    #[serde(alias = "自定义")]
+    Custom, // 只有开始到结束日期这一个周期
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Budget {
     pub id: ObjectId,
-    pub user_id: ObjectId,
+    pub ledger_id: ObjectId, // 所属账本
+    pub user_id: ObjectId,   // 创建者
     pub category_id: ObjectId,  // 预算分类
-    pub amount: f64,            // 预算金额
-    pub period: String,         // 预算周期（月/年/自定义）
-    pub start_date: DateTime,
-    pub end_date: DateTime,
+    pub amount: Money,          // 每个周期的预算金额（含币种）
+    pub period: BudgetPeriod,
+    pub start_date: DateTime,         // 第一个周期的开始
+    pub end_date: Option<DateTime>,   // 自定义周期的结束；循环预算到此为止，为空表示一直循环
+    #[serde(default)]
+    pub rollover: bool, // 上个周期的结余（或超支）是否结转到下个周期
+}
+
+/// 预算的一个周期，首尾都包含在内
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeriodWindow {
+    pub start: DateTime,
+    pub end: DateTime,
+}
+
+impl PeriodWindow {
+    pub fn contains(&self, at: DateTime) -> bool {
+        self.start <= at && at <= self.end
+    }
+}
+
+/// 一个周期的执行结果
+#[derive(Debug, Clone)]
+pub struct PeriodOutcome {
+    pub window: PeriodWindow,
+    pub carried_over: Money, // 从上个周期结转来的金额，超支时为负
+    pub spent: Money,
+    pub missing_rates: Vec<MissingRate>, // 缺少汇率、未计入 spent 的消费
+}
+
+/// 预算执行情况，金额均以预算币种表示
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetProgress {
+    pub budget_id: String,
+    pub category_id: String,
+    pub amount: Money,       // 本周期可用金额，含结转
+    pub carried_over: Money,
+    pub spent: Money,
+    pub remaining: Money, // 为负表示超支
+    pub percent: f64,     // 已用百分比，保留两位小数
+    pub projected: Money, // 按目前的支出速度推算的期末支出
+    pub start_date: String,
+    pub end_date: String,
+    pub missing_rates: Vec<MissingRate>,
+}
+
+// 开始日期之后第 `n` 个周期的开始；自定义周期只有第 0 个，超出可表示范围时返回 None
+fn shift(start: DateTime, period: BudgetPeriod, n: u32) -> Option<DateTime> {
+    let months = match period {
+        BudgetPeriod::Custom => return (n == 0).then_some(start),
+        BudgetPeriod::Weekly => 0,
+        BudgetPeriod::Monthly => n,
+        BudgetPeriod::Quarterly => n.checked_mul(3)?,
+        BudgetPeriod::Yearly => n.checked_mul(12)?,
+    };
+    let start = chrono::DateTime::<Utc>::from_timestamp_millis(start.timestamp_millis())?;
+    let shifted = if period == BudgetPeriod::Weekly {
+        start.checked_add_signed(Duration::weeks(n as i64))?
+    } else {
+        start.checked_add_months(Months::new(months))?
+    };
+    Some(DateTime::from_millis(shifted.timestamp_millis()))
+}
+
+impl Budget {
+    /// 第 `n` 个周期（从 0 开始）。每个周期都从开始日期按整月/整周推算，月末日期会落到较短月份的最后一天。
+    pub fn window(&self, n: u32) -> Option<PeriodWindow> {
+        let start = shift(self.start_date, self.period, n)?;
+        let mut end = match self.period {
+            BudgetPeriod::Custom => self.end_date.unwrap_or(DateTime::MAX),
+            period => DateTime::from_millis(shift(self.start_date, period, n + 1)?.timestamp_millis() - 1),
+        };
+        if let Some(limit) = self.end_date {
+            if start > limit {
+                return None;
+            }
+            end = end.min(limit);
+        }
+        Some(PeriodWindow { start, end })
+    }
+
+    /// 开始时间不晚于 `at` 的全部周期，按时间顺序
+    pub fn windows_until(&self, at: DateTime) -> Vec<PeriodWindow> {
+        (0..).map_while(|n| self.window(n)).take_while(|w| w.start <= at).collect()
+    }
+
+    /// `at` 是否落在某个周期内
+    pub fn is_active(&self, at: DateTime) -> bool {
+        self.windows_until(at).last().is_some_and(|w| w.contains(at))
+    }
+
+    /// 依次计算各周期的结转；`spent` 与 `windows` 一一对应，结转金额溢出时返回 400
+    pub fn outcomes(&self, windows: &[PeriodWindow], spent: &[Money]) -> Result<Vec<PeriodOutcome>, AppError> {
+        let mut carry = Money::zero(self.amount.currency);
+        let mut outcomes = Vec::with_capacity(windows.len());
+        for (window, spent) in windows.iter().zip(spent) {
+            let outcome = PeriodOutcome { window: *window, carried_over: carry, spent: *spent, missing_rates: Vec::new() };
+            if self.rollover {
+                carry.accumulate(self.amount.minor)?;
+                carry.deduct(spent.minor)?;
+            }
+            outcomes.push(outcome);
+        }
+        Ok(outcomes)
+    }
+
+    /// 某个周期截至 `now` 的执行进度；周期进行中时按已过时间的比例线性推算期末支出。
+    /// 可用、剩余或推算金额溢出时返回 400
+    pub fn progress(&self, outcome: &PeriodOutcome, now: DateTime) -> Result<BudgetProgress, AppError> {
+        let currency = self.amount.currency;
+        let mut amount = self.amount;
+        amount.accumulate(outcome.carried_over.minor)?;
+        let mut remaining = amount;
+        let spent = outcome.spent;
+        remaining.deduct(spent.minor)?;
+        let percent = match amount.minor {
+            total if total <= 0 && spent.minor > 0 => 100.0,
+            total if total <= 0 => 0.0,
+            total => (spent.minor as f64 * 10000.0 / total as f64).round() / 100.0,
+        };
+        let start = outcome.window.start.timestamp_millis();
+        let total = outcome.window.end.timestamp_millis() - start;
+        let elapsed = now.timestamp_millis() - start;
+        let projected = if elapsed > 0 && elapsed < total {
+            // 周期刚开始时倍数很大，推算结果可能超出 i64
+            i64::try_from(spent.minor as i128 * total as i128 / elapsed as i128)
+                .map_err(|_| AppError::validation("金额合计超出范围"))?
+        } else {
+            spent.minor
+        };
+        Ok(BudgetProgress {
+            budget_id: self.id.to_hex(),
+            category_id: self.category_id.to_hex(),
+            amount,
+            carried_over: outcome.carried_over,
+            spent,
+            remaining,
+            percent,
+            projected: Money::new(projected, currency),
+            start_date: outcome.window.start.try_to_rfc3339_string().unwrap_or_default(),
+            end_date: outcome.window.end.try_to_rfc3339_string().unwrap_or_default(),
+            missing_rates: outcome.missing_rates.clone(),
+        })
+    }
 }
@@ -1,11 +1,149 @@
 use mongodb::bson::{oid::ObjectId};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use crate::error::AppError;
+use crate::models::currency::Currency;
+use crate::models::money::Money;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Category {
     pub id: ObjectId,
-    pub user_id: ObjectId,
+    pub ledger_id: ObjectId, // 所属账本
+    pub user_id: ObjectId,   // 创建者
     pub name: String,           // 分类名称（如餐饮、交通、工资等）
     pub parent_id: Option<ObjectId>, // 父级分类
     pub category_type: String,  // 类型（收入/支出/转账）
 }
+
+/// 分类类型，依次对应订单类型 消费/收入/转账
+pub const CATEGORY_TYPES: [&str; 3] = ["支出", "收入", "转账"];
+
+/// 该类型分类可以用于的订单类型，未知类型返回 None
+pub fn order_type_for(category_type: &str) -> Option<&'static str> {
+    match category_type {
+        "支出" => Some("消费"),
+        "收入" => Some("收入"),
+        "转账" => Some("转账"),
+        _ => None,
+    }
+}
+
+/// 以 `root` 为根的子树中所有分类ID（含 `root` 本身）；已访问过的分类不再展开，数据中即使已有环也不会死循环
+pub fn subtree_ids(categories: &[Category], root: ObjectId) -> Vec<ObjectId> {
+    let mut ids = vec![root];
+    let mut visited = HashSet::from([root]);
+    let mut i = 0;
+    while i < ids.len() {
+        let parent = ids[i];
+        for child in categories.iter().filter(|c| c.parent_id == Some(parent)) {
+            if visited.insert(child.id) {
+                ids.push(child.id);
+            }
+        }
+        i += 1;
+    }
+    ids
+}
+
+/// 分类树的最大层数，顶级分类为第 1 层
+pub const MAX_DEPTH: usize = 5;
+
+/// `id` 所在的层数，顶级分类为 1
+pub fn depth_of(categories: &[Category], id: ObjectId) -> usize {
+    let mut depth = 1;
+    let mut current = id;
+    // 最多走分类总数步，数据中即使已有环也不会死循环
+    while let Some(parent) = categories.iter().find(|c| c.id == current).and_then(|c| c.parent_id) {
+        if depth > categories.len() {
+            break;
+        }
+        depth += 1;
+        current = parent;
+    }
+    depth
+}
+
+/// 以 `root` 为根的子树的层数，叶子分类为 1
+pub fn subtree_height(categories: &[Category], root: ObjectId) -> usize {
+    let mut level = vec![root];
+    let mut height = 0;
+    while !level.is_empty() && height <= categories.len() {
+        height += 1;
+        level = categories.iter().filter(|c| c.parent_id.is_some_and(|p| level.contains(&p))).map(|c| c.id).collect();
+    }
+    height
+}
+
+/// 检查能否把 `id`（连同其子分类）放到 `parent_id` 下：不能放到自身或自己的子分类下，
+/// 移动后整棵树不能超过 [`MAX_DEPTH`] 层
+pub fn check_parent(categories: &[Category], id: ObjectId, parent_id: ObjectId) -> Result<(), String> {
+    if subtree_ids(categories, id).contains(&parent_id) {
+        return Err("父分类不能是自身或其子分类".to_string());
+    }
+    if depth_of(categories, parent_id) + subtree_height(categories, id) > MAX_DEPTH {
+        return Err(format!("分类层级不能超过 {} 层", MAX_DEPTH));
+    }
+    Ok(())
+}
+
+/// 检查删除 `id` 时能否把它的子分类转移到 `target` 下：`target` 不能在被删除的子树内，
+/// 转移后整棵树不能超过 [`MAX_DEPTH`] 层
+pub fn check_reassign(categories: &[Category], id: ObjectId, target: ObjectId) -> Result<(), String> {
+    if subtree_ids(categories, id).contains(&target) {
+        return Err("不能转移到被删除的分类或其子分类".to_string());
+    }
+    for child in categories.iter().filter(|c| c.parent_id == Some(id)) {
+        check_parent(categories, child.id, target)?;
+    }
+    Ok(())
+}
+
+/// 树形结构中的分类；带统计时 `amount` 为分类本身的金额，`total` 含全部子分类
+#[derive(Debug, Serialize)]
+pub struct CategoryNode {
+    pub id: String,
+    pub name: String,
+    pub category_type: String,
+    pub parent_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Money>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<Money>,
+    pub children: Vec<CategoryNode>,
+}
+
+impl CategoryNode {
+    /// 按 `amounts`（分类ID => 金额）填入本分类金额，并把子分类合计汇总到 `total`
+    pub fn roll_up(&mut self, amounts: &HashMap<String, Money>, currency: Currency) -> Result<Money, AppError> {
+        let own = amounts.get(&self.id).copied().unwrap_or(Money::zero(currency));
+        let mut total = own;
+        for child in self.children.iter_mut() {
+            total.accumulate(child.roll_up(amounts, currency)?.minor)?;
+        }
+        self.amount = Some(own);
+        self.total = Some(total);
+        Ok(total)
+    }
+}
+
+/// 把平铺的分类组装成树，父分类不存在的作为顶级分类；同级保持原有顺序
+pub fn build_tree(categories: &[Category]) -> Vec<CategoryNode> {
+    fn node(categories: &[Category], category: &Category) -> CategoryNode {
+        CategoryNode {
+            id: category.id.to_hex(),
+            name: category.name.clone(),
+            category_type: category.category_type.clone(),
+            parent_id: category.parent_id.map(|id| id.to_hex()),
+            amount: None,
+            total: None,
+            children: categories.iter()
+                .filter(|c| c.parent_id == Some(category.id))
+                .map(|c| node(categories, c))
+                .collect(),
+        }
+    }
+    categories.iter()
+        .filter(|c| c.parent_id.is_none_or(|p| !categories.iter().any(|other| other.id == p)))
+        .map(|c| node(categories, c))
+        .collect()
+}
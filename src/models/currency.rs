@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// 币种，按 ISO 4217 代码存储；输入时同时接受中文名称（如“人民币”）。
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Currency {
+    #[default]
+    CNY,
+    USD,
+    EUR,
+    JPY,
+    GBP,
+    HKD,
+    KRW,
+    AUD,
+    CAD,
+    SGD,
+    CHF,
+}
+
+impl Currency {
+    pub const ALL: [Currency; 11] = [
+        Currency::CNY, Currency::USD, Currency::EUR, Currency::JPY, Currency::GBP, Currency::HKD,
+        Currency::KRW, Currency::AUD, Currency::CAD, Currency::SGD, Currency::CHF,
+    ];
+
+    /// ISO 4217 代码
+    pub fn code(self) -> &'static str {
+        match self {
+            Currency::CNY => "CNY",
+            Currency::USD => "USD",
+            Currency::EUR => "EUR",
+            Currency::JPY => "JPY",
+            Currency::GBP => "GBP",
+            Currency::HKD => "HKD",
+            Currency::KRW => "KRW",
+            Currency::AUD => "AUD",
+            Currency::CAD => "CAD",
+            Currency::SGD => "SGD",
+            Currency::CHF => "CHF",
+        }
+    }
+
+    /// 中文名称，同时作为输入别名
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Currency::CNY => "人民币",
+            Currency::USD => "美元",
+            Currency::EUR => "欧元",
+            Currency::JPY => "日元",
+            Currency::GBP => "英镑",
+            Currency::HKD => "港币",
+            Currency::KRW => "韩元",
+            Currency::AUD => "澳元",
+            Currency::CAD => "加元",
+            Currency::SGD => "新加坡元",
+            Currency::CHF => "瑞士法郎",
+        }
+    }
+
+    /// 最小货币单位对应的小数位数（ISO 4217 minor unit）
+    pub fn decimals(self) -> u32 {
+        match self {
+            Currency::JPY | Currency::KRW => 0,
+            _ => 2,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("不支持的币种: {0}")]
+pub struct UnknownCurrency(pub String);
+
+impl FromStr for Currency {
+    type Err = UnknownCurrency;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let alias = match s {
+            "元" | "人民币元" => Some(Currency::CNY),
+            "港元" => Some(Currency::HKD),
+            _ => None,
+        };
+        alias
+            .or_else(|| Currency::ALL.into_iter().find(|c| c.code().eq_ignore_ascii_case(s) || c.display_name() == s))
+            .ok_or_else(|| UnknownCurrency(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = UnknownCurrency;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
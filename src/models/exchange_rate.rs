@@ -0,0 +1,71 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+use crate::models::currency::Currency;
+use crate::models::money::Money;
+use crate::util::day_start;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    pub id: ObjectId,
+    pub user_id: ObjectId,
+    pub from: Currency,   // 源币种
+    pub to: Currency,     // 目标币种
+    pub rate: f64,        // 1 单位 from = rate 单位 to
+    pub date: DateTime,   // 生效日期，直到下一条同币种对的汇率为止
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, thiserror::Error)]
+#[error("缺少 {from} → {to} 在 {date} 的汇率")]
+pub struct MissingRate {
+    pub from: Currency,
+    pub to: Currency,
+    pub date: String,
+}
+
+/// 用户的汇率表：取订单日期当天或之前最近一条汇率，反向币种对按倒数使用。
+/// 汇率从登记日期所在 UTC 自然日的零点起生效，因此按订单时间查询与按自然日分组的合计查询结果一致。
+pub struct RateTable {
+    rates: Vec<ExchangeRate>,
+}
+
+impl RateTable {
+    pub fn new(mut rates: Vec<ExchangeRate>) -> Self {
+        rates.sort_by_key(|r| r.date);
+        RateTable { rates }
+    }
+
+    pub fn rate(&self, from: Currency, to: Currency, date: DateTime) -> Option<f64> {
+        if from == to {
+            return Some(1.0);
+        }
+        self.rates.iter().rev()
+            .filter(|r| day_start(r.date) <= date)
+            .find_map(|r| match (r.from, r.to) {
+                (f, t) if f == from && t == to => Some(r.rate),
+                (f, t) if f == to && t == from => Some(1.0 / r.rate),
+                _ => None,
+            })
+    }
+
+    pub fn convert(&self, money: Money, to: Currency, date: DateTime) -> Result<Money, MissingRate> {
+        let rate = self.rate(money.currency, to, date).ok_or_else(|| MissingRate {
+            from: money.currency,
+            to,
+            date: date.try_to_rfc3339_string().unwrap_or_default(),
+        })?;
+        Ok(money.convert(rate, to))
+    }
+
+    /// 换算成功返回金额；缺少汇率时记入 `missing`（去重）并返回 `None`，由调用方跳过该金额
+    pub fn convert_or_record(&self, money: Money, to: Currency, date: DateTime, missing: &mut Vec<MissingRate>) -> Option<Money> {
+        match self.convert(money, to, date) {
+            Ok(converted) => Some(converted),
+            Err(rate) => {
+                if !missing.contains(&rate) {
+                    missing.push(rate);
+                }
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+/// 账本成员角色，按权限从低到高排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Viewer, // 只读
+    Editor, // 可记账、修改数据
+    Owner,  // 另外可以管理成员
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Member {
+    pub user_id: ObjectId,
+    pub role: Role,
+}
+
+pub const PERSONAL_LEDGER_NAME: &str = "我的账本";
+
+/// 账本：账户、分类、资产、订单、预算都归属于某个账本，成员按角色共享访问
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ledger {
+    pub id: ObjectId,
+    pub name: String,
+    pub members: Vec<Member>, // 有且只有一个 Owner
+    pub created_at: DateTime,
+}
+
+impl Ledger {
+    /// 注册时为用户创建的个人账本
+    pub fn personal(owner_id: ObjectId) -> Self {
+        Ledger {
+            id: ObjectId::new(),
+            name: PERSONAL_LEDGER_NAME.to_string(),
+            members: vec![Member { user_id: owner_id, role: Role::Owner }],
+            created_at: DateTime::now(),
+        }
+    }
+
+    pub fn role_of(&self, user_id: ObjectId) -> Option<Role> {
+        self.members.iter().find(|m| m.user_id == user_id).map(|m| m.role)
+    }
+
+    /// 所有者注销时接手账本的成员：权限最高的成员中最早加入的一位，没有其他成员时为 `None`
+    pub fn successor(&self) -> Option<ObjectId> {
+        let others = self.members.iter().filter(|m| m.role != Role::Owner);
+        let best = others.clone().map(|m| m.role).max()?;
+        others.filter(|m| m.role == best).map(|m| m.user_id).next()
+    }
+}
+
+/// 邀请某个用户加入账本，被邀请人接受后成为成员
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invitation {
+    pub id: ObjectId,
+    pub ledger_id: ObjectId,
+    pub inviter_id: ObjectId,
+    pub invitee_id: ObjectId,
+    pub role: Role, // Editor 或 Viewer
+    pub created_at: DateTime,
+}
@@ -0,0 +1,20 @@
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// 某个用户名或 IP 的连续登录失败记录，持久化保存，重启后锁定依然有效。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginAttempt {
+    pub key: String,     // "user:<用户名>" 或 "ip:<地址>"
+    pub failures: u32,   // 统计窗口内的连续失败次数
+    pub last_failure: DateTime,
+}
+
+impl LoginAttempt {
+    pub fn username_key(username: &str) -> String {
+        format!("user:{}", username)
+    }
+
+    pub fn ip_key(ip: &std::net::IpAddr) -> String {
+        format!("ip:{}", ip)
+    }
+}
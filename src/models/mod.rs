@@ -1,6 +1,14 @@
+pub mod currency;
+pub mod money;
 pub mod account;
 pub mod category;
 pub mod asset;
 pub mod transaction;
 pub mod budget;
+pub mod exchange_rate;
 pub mod user;
+pub mod session;
+pub mod api_key;
+pub mod login_attempt;
+pub mod ledger;
+pub mod notification;
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::error::AppError;
+use crate::models::currency::Currency;
+
+/// 单笔金额允许的最大绝对值（最小货币单位），留出足够余量避免余额和合计溢出
+pub const MAX_MINOR: i64 = 1_000_000_000_000_000;
+
+/// 金额：以最小货币单位（如“分”）的整数保存，并与币种绑定，避免浮点累加误差。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Money {
+    pub minor: i64,         // 最小货币单位数量
+    pub currency: Currency, // 币种
+}
+
+impl Money {
+    pub fn new(minor: i64, currency: Currency) -> Self {
+        Money { minor, currency }
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Money::new(0, currency)
+    }
+
+    /// 由主单位金额（如 12.34 元）构造，按币种小数位四舍五入到最小单位；
+    /// 非有限值或超出 [`MAX_MINOR`] 的金额返回 400。
+    pub fn from_major(amount: f64, currency: Currency) -> Result<Self, AppError> {
+        let minor = (amount * 10f64.powi(currency.decimals() as i32)).round();
+        if !minor.is_finite() || minor.abs() > MAX_MINOR as f64 {
+            return Err(AppError::validation(format!("金额无效或超出范围: {}", amount)));
+        }
+        Ok(Money::new(minor as i64, currency))
+    }
+
+    /// PATCH 请求更新后的金额：没给金额和币种时原样保留，不经过浮点往返；
+    /// 只改币种而不给金额时返回 400，避免按新币种的小数位重新解释原金额。
+    pub fn patched(self, amount: Option<f64>, currency: Option<Currency>) -> Result<Self, AppError> {
+        match (amount, currency) {
+            (Some(amount), currency) => Money::from_major(amount, currency.unwrap_or(self.currency)),
+            (None, Some(currency)) if currency != self.currency => Err(AppError::validation("修改币种时需同时提供金额")),
+            (None, _) => Ok(self),
+        }
+    }
+
+    /// 加上若干最小单位，溢出时返回 `None`
+    pub fn checked_add(self, minor: i64) -> Option<Self> {
+        self.minor.checked_add(minor).map(|minor| Money::new(minor, self.currency))
+    }
+
+    /// 合计时累加同币种金额，溢出时返回 400
+    pub fn accumulate(&mut self, minor: i64) -> Result<(), AppError> {
+        *self = self.checked_add(minor).ok_or_else(|| AppError::validation("金额合计超出范围"))?;
+        Ok(())
+    }
+
+    /// 扣减同币种金额，溢出时返回 400
+    pub fn deduct(&mut self, minor: i64) -> Result<(), AppError> {
+        let minor = self.minor.checked_sub(minor).ok_or_else(|| AppError::validation("金额合计超出范围"))?;
+        self.minor = minor;
+        Ok(())
+    }
+
+    /// 主单位金额（如 12.34 元）
+    pub fn to_major(&self) -> f64 {
+        self.minor as f64 / 10f64.powi(self.currency.decimals() as i32)
+    }
+
+    /// 按汇率换算成另一币种，结果四舍五入到目标币种的最小单位。
+    pub fn convert(&self, rate: f64, currency: Currency) -> Self {
+        let shift = currency.decimals() as i32 - self.currency.decimals() as i32;
+        Money::new((self.minor as f64 * rate * 10f64.powi(shift)).round() as i64, currency)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dp = self.currency.decimals();
+        if dp == 0 {
+            return write!(f, "{} {}", self.minor, self.currency);
+        }
+        let factor = 10u64.pow(dp);
+        let sign = if self.minor < 0 { "-" } else { "" };
+        let abs = self.minor.unsigned_abs();
+        write!(f, "{}{}.{:0width$} {}", sign, abs / factor, abs % factor, self.currency, width = dp as usize)
+    }
+}
@@ -0,0 +1,26 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    BudgetThreshold, // 预算使用比例达到提醒阈值
+}
+
+/// 通知发件箱中的一条通知：先写入数据库，再由投递任务发送到 webhook、邮件等渠道，失败时重试。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: ObjectId,
+    pub user_id: ObjectId,   // 接收人
+    pub ledger_id: ObjectId, // 触发通知的账本
+    pub kind: NotificationKind,
+    pub key: String, // 去重键，同一用户的同一事件只通知一次
+    pub title: String,
+    pub message: String,
+    pub budget_id: Option<ObjectId>,
+    pub read: bool,
+    pub created_at: DateTime,
+    pub delivered_at: Option<DateTime>, // 所有渠道都投递成功的时间
+    pub attempts: u32,                  // 已尝试投递的次数
+    pub last_error: Option<String>,
+}
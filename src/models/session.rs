@@ -0,0 +1,27 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+/// 登录会话：每次登录创建一条，刷新令牌只保存哈希，每次刷新都会轮换。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: ObjectId,
+    pub user_id: ObjectId,
+    pub refresh_hash: String,          // 当前刷新令牌的 SHA-256
+    pub previous_hash: Option<String>, // 上一个刷新令牌，被重复使用时说明令牌泄露
+    pub created_at: DateTime,
+    pub expires_at: DateTime,
+    pub revoked: bool,
+}
+
+impl Session {
+    pub fn is_active(&self) -> bool {
+        !self.revoked && self.expires_at > DateTime::now()
+    }
+}
+
+/// 已用于换取令牌的两步验证挑战令牌，过期后即可删除
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsedChallenge {
+    pub jti: String,
+    pub expires_at: DateTime,
+}
@@ -1,14 +1,55 @@
 use mongodb::bson::{oid::ObjectId, DateTime};
 use serde::{Deserialize, Serialize};
+use crate::models::money::Money;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Order {
     pub id: ObjectId,
-    pub user_id: ObjectId,
+    pub ledger_id: ObjectId, // 所属账本
+    pub user_id: ObjectId,   // 创建者
+    #[serde(default)]
+    pub account_id: Option<ObjectId>, // 关联账户，创建/删除时同步调整账户余额
+    #[serde(default)]
+    pub category_id: Option<ObjectId>, // 所属分类，类型须与订单方向一致；预算统计和按分类筛选、统计都依赖它
     pub name: String,              // 新增：订单名称
     pub order_type: String,        // 类型（消费/收入/转账）
-    pub amount: f64,               // 金额
-    pub currency: String,          // 币种（人民币/美元/欧元等）
+    pub amount: Money,             // 金额（含币种）
     pub date: DateTime,            // 日期
     pub remark: Option<String>,
+    #[serde(default)]
+    pub transfer: Option<TransferLink>, // 转账订单的另一侧信息
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub alerts_pending: bool, // 与订单一起写入，预算提醒检查完成后清除；未清除的由后台重新检查
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferDirection {
+    Out, // 转出
+    In,  // 转入
+}
+
+/// 一笔转账由转出、转入两条订单组成，两者互相引用，删除任意一侧会同时删除另一侧。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferLink {
+    pub transfer_id: ObjectId,     // 两侧订单共用的转账ID
+    pub peer_order_id: ObjectId,   // 另一侧订单ID
+    pub direction: TransferDirection,
+    pub exchange_rate: f64,        // 转入金额 = 转出金额 * 汇率
+    pub fee: Money,                // 手续费，仅从转出账户扣除
+}
+
+impl Order {
+    /// 订单对关联账户余额的影响（最小货币单位）：收入为正，消费为负，转账按方向计算，其余类型不影响余额。
+    pub fn balance_delta(&self) -> i64 {
+        match (self.order_type.as_str(), &self.transfer) {
+            ("收入", _) => self.amount.minor,
+            ("消费", _) => -self.amount.minor,
+            ("转账", Some(link)) => match link.direction {
+                TransferDirection::Out => -(self.amount.minor + link.fee.minor),
+                TransferDirection::In => self.amount.minor,
+            },
+            _ => 0,
+        }
+    }
 }
@@ -1,5 +1,6 @@
 use mongodb::bson::{oid::ObjectId, DateTime};
 use serde::{Deserialize, Serialize};
+use crate::models::currency::Currency;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
@@ -7,6 +8,51 @@ pub struct User {
     pub username: String,
     pub password: String,
     pub created_at: DateTime,
+    #[serde(default)]
+    pub base_currency: Currency, // 本位币，统计汇总时换算成该币种
+    #[serde(default)]
+    pub two_factor: Option<TwoFactor>,
+    #[serde(default)]
+    pub default_ledger_id: Option<ObjectId>, // 未指定账本时使用的账本
+    #[serde(default)]
+    pub email: Option<String>, // 接收邮件通知的地址
+}
+
+/// TOTP 两步验证设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactor {
+    pub secret: String,               // Base32 密钥
+    pub enabled: bool,                // 确认过验证码后才启用
+    pub recovery_hashes: Vec<String>, // 未使用的恢复码哈希，每个只能用一次
+    pub last_step: i64,               // 最近一次使用的验证码步长，防止重放
+}
+
+impl User {
+    pub fn two_factor_enabled(&self) -> bool {
+        self.two_factor.as_ref().is_some_and(|t| t.enabled)
+    }
+}
+
+/// 用户名最大长度（字符数）
+pub const USERNAME_MAX_CHARS: usize = 32;
+
+/// 用户名统一去掉首尾空白并转为小写后再存储和比较
+pub fn normalize_username(username: &str) -> String {
+    username.trim().to_lowercase()
+}
+
+/// 校验规范化后的用户名，不合法时返回提示信息
+pub fn check_username(username: &str) -> Result<(), String> {
+    if username.is_empty() {
+        return Err("用户名不能为空".to_string());
+    }
+    if username.chars().count() > USERNAME_MAX_CHARS {
+        return Err(format!("用户名不能超过 {} 个字符", USERNAME_MAX_CHARS));
+    }
+    if username.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err("用户名不能包含空白或控制字符".to_string());
+    }
+    Ok(())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,4 +66,18 @@ pub struct UserOut {
     pub id: ObjectId,
     pub username: String,
     pub created_at: DateTime,
+    pub base_currency: Currency,
+    pub two_factor_enabled: bool,
+}
+
+impl From<&User> for UserOut {
+    fn from(u: &User) -> Self {
+        UserOut {
+            id: u.id,
+            username: u.username.clone(),
+            created_at: u.created_at,
+            base_currency: u.base_currency,
+            two_factor_enabled: u.two_factor_enabled(),
+        }
+    }
 }
@@ -0,0 +1,293 @@
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde_json::json;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::config::{NotificationConfig, SmtpConfig, WebhookConfig};
+use crate::db::{DBResult, LedgerStore};
+use crate::error::AppError;
+use crate::models::category::subtree_ids;
+use crate::models::notification::{Notification, NotificationKind};
+use crate::models::transaction::Order;
+use crate::models::user::User;
+use crate::services::{budget_outcomes, load_rates};
+
+/// 通知投递渠道
+#[async_trait]
+pub trait NotificationSink: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// 投递给 `user`，失败时返回错误描述
+    async fn deliver(&self, user: &User, notification: &Notification) -> Result<(), String>;
+}
+
+/// 把通知以 JSON POST 到配置的地址
+pub struct WebhookSink {
+    client: reqwest::Client,
+    config: WebhookConfig,
+}
+
+impl WebhookSink {
+    pub fn new(config: WebhookConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("HTTP 客户端配置固定，不会失败");
+        WebhookSink { client, config }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for WebhookSink {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn deliver(&self, user: &User, notification: &Notification) -> Result<(), String> {
+        let body = json!({
+            "user": {"id": user.id.to_hex(), "username": user.username},
+            "notification": {
+                "id": notification.id.to_hex(),
+                "kind": notification.kind,
+                "title": notification.title,
+                "message": notification.message,
+                "ledger_id": notification.ledger_id.to_hex(),
+                "budget_id": notification.budget_id.map(|id| id.to_hex()),
+                "read": notification.read,
+                "created_at": notification.created_at.try_to_rfc3339_string().unwrap_or_default(),
+            },
+        })
+        .to_string();
+        let mut req = self.client.post(&self.config.url).header("content-type", "application/json");
+        if let Some(secret) = &self.config.secret {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC 接受任意长度的密钥");
+            mac.update(body.as_bytes());
+            req = req.header("x-signature", format!("sha256={}", hex::encode(mac.finalize().into_bytes())));
+        }
+        let res = req.body(body).send().await.map_err(|e| e.to_string())?;
+        if !res.status().is_success() {
+            return Err(format!("响应状态码 {}", res.status()));
+        }
+        Ok(())
+    }
+}
+
+/// 通过 SMTP 给设置了邮箱的用户发邮件，没有邮箱的用户直接跳过
+pub struct SmtpSink {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpSink {
+    pub fn new(config: &SmtpConfig) -> Result<Self, String> {
+        let from = config.from.parse::<Mailbox>().map_err(|e| e.to_string())?;
+        let mut builder = if config.starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host).map_err(|e| e.to_string())?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+        };
+        builder = builder.port(config.port).timeout(Some(Duration::from_secs(10)));
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        Ok(SmtpSink { transport: builder.build(), from })
+    }
+}
+
+#[async_trait]
+impl NotificationSink for SmtpSink {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    async fn deliver(&self, user: &User, notification: &Notification) -> Result<(), String> {
+        let Some(email) = &user.email else {
+            return Ok(());
+        };
+        let to = email.parse::<Mailbox>().map_err(|e| e.to_string())?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&notification.title)
+            .body(notification.message.clone())
+            .map_err(|e| e.to_string())?;
+        self.transport.send(message).await.map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// 写入通知并投递到各渠道。通知先落库（发件箱），投递失败的会按间隔重试，
+/// 直到成功或达到最大次数；重试时所有渠道都会重发，接收方可能收到重复的通知。
+pub struct Notifier {
+    config: NotificationConfig,
+    sinks: Vec<Box<dyn NotificationSink>>,
+    flushing: tokio::sync::Mutex<()>, // 同一时间只有一个投递任务，避免重复发送
+}
+
+impl Notifier {
+    pub fn new(config: &NotificationConfig) -> Self {
+        let mut sinks: Vec<Box<dyn NotificationSink>> = Vec::new();
+        if let Some(webhook) = &config.webhook {
+            sinks.push(Box::new(WebhookSink::new(webhook.clone())));
+        }
+        if let Some(smtp) = &config.smtp {
+            match SmtpSink::new(smtp) {
+                Ok(sink) => sinks.push(Box::new(sink)),
+                Err(e) => tracing::warn!("[Notifier] SMTP 配置不可用，不发送邮件通知: {}", e),
+            }
+        }
+        Notifier { config: config.clone(), sinks, flushing: tokio::sync::Mutex::new(()) }
+    }
+
+    /// 一笔消费计入的预算在订单所在周期内达到提醒阈值时，给账本的每个成员写入通知；
+    /// 每个预算周期的每个阈值只提醒一次，重复检查不会重复提醒。检查完成后清除订单的
+    /// `alerts_pending` 标记，返回新写入的通知数量。
+    pub async fn budget_alerts(&self, db: &dyn LedgerStore, order: &Order) -> Result<usize, AppError> {
+        let created = self.write_budget_alerts(db, order).await?;
+        if order.alerts_pending {
+            db.clear_alerts_pending(order.id).await?;
+        }
+        Ok(created)
+    }
+
+    async fn write_budget_alerts(&self, db: &dyn LedgerStore, order: &Order) -> Result<usize, AppError> {
+        let Some(category_id) = order.category_id else {
+            return Ok(0);
+        };
+        if order.order_type != "消费" || self.config.budget_thresholds.is_empty() {
+            return Ok(0);
+        }
+        let Some(ledger) = db.get_ledger(order.ledger_id).await? else {
+            return Ok(0);
+        };
+        let categories = db.get_categories_by_ledger(order.ledger_id).await?;
+        // 与预算进度接口一致，每个成员按自己的汇率表换算，提醒里的进度与其查看到的一致
+        let mut member_rates = Vec::with_capacity(ledger.members.len());
+        for member in &ledger.members {
+            let (_, rates) = load_rates(db, member.user_id).await?;
+            member_rates.push((member.user_id, rates));
+        }
+        let mut created = 0;
+        for budget in db.get_budgets_by_ledger(order.ledger_id).await? {
+            if !subtree_ids(&categories, budget.category_id).contains(&category_id) {
+                continue;
+            }
+            let Some(window) = budget.windows_until(order.date).pop().filter(|w| w.contains(order.date)) else {
+                continue;
+            };
+            let name = categories.iter().find(|c| c.id == budget.category_id).map_or("", |c| c.name.as_str());
+            for (user_id, rates) in &member_rates {
+                // 统计整个周期的支出，补记的早期订单同样会触发提醒
+                let Some(outcome) = budget_outcomes(db, &budget, &categories, rates, window.end).await?.pop() else {
+                    continue;
+                };
+                let progress = budget.progress(&outcome, window.end)?;
+                for &threshold in self.config.budget_thresholds.iter().filter(|&&t| progress.percent >= t as f64) {
+                    let title = if threshold >= 100 { "预算已超支" } else { "预算即将用完" };
+                    let notification = Notification {
+                        id: ObjectId::new(),
+                        user_id: *user_id,
+                        ledger_id: ledger.id,
+                        kind: NotificationKind::BudgetThreshold,
+                        key: format!("budget:{}:{}:{}", budget.id.to_hex(), window.start.timestamp_millis(), threshold),
+                        title: title.to_string(),
+                        message: format!(
+                            "「{}」预算本周期已使用 {}%（{} / {}），提醒阈值 {}%",
+                            name, progress.percent, progress.spent, progress.amount, threshold,
+                        ),
+                        budget_id: Some(budget.id),
+                        read: false,
+                        created_at: DateTime::now(),
+                        delivered_at: None,
+                        attempts: 0,
+                        last_error: None,
+                    };
+                    if db.create_notification(notification).await? {
+                        created += 1;
+                    }
+                }
+            }
+        }
+        if created > 0 {
+            tracing::info!("[budget_alerts] order_id: {:?}, notifications: {}", order.id, created);
+        }
+        Ok(created)
+    }
+
+    /// 重新检查仍带有 `alerts_pending` 标记的订单（创建后检查失败或进程中途退出），返回新写入的通知数量
+    pub async fn evaluate_pending(&self, db: &dyn LedgerStore) -> DBResult<usize> {
+        let mut created = 0;
+        for order in db.get_orders_pending_alerts().await? {
+            match self.budget_alerts(db, &order).await {
+                Ok(n) => created += n,
+                Err(e) => tracing::warn!("[Notifier] 订单 {:?} 预算提醒检查失败: {}", order.id, e),
+            }
+        }
+        Ok(created)
+    }
+
+    /// 投递所有待发送的通知，返回投递成功的数量
+    pub async fn flush(&self, db: &dyn LedgerStore) -> DBResult<usize> {
+        if self.sinks.is_empty() {
+            return Ok(0);
+        }
+        let _guard = self.flushing.lock().await;
+        let mut delivered = 0;
+        for notification in db.get_pending_notifications(self.config.max_attempts).await? {
+            let errors = match db.get_user(notification.user_id).await? {
+                Some(user) => {
+                    let mut errors = Vec::new();
+                    for sink in &self.sinks {
+                        if let Err(e) = sink.deliver(&user, &notification).await {
+                            errors.push(format!("{}: {}", sink.name(), e));
+                        }
+                    }
+                    errors
+                }
+                None => vec!["用户不存在".to_string()],
+            };
+            if errors.is_empty() {
+                delivered += 1;
+                db.record_delivery(notification.id, None).await?;
+            } else {
+                tracing::warn!("[Notifier] 通知 {:?} 投递失败: {}", notification.id, errors.join("; "));
+                db.record_delivery(notification.id, Some(errors.join("; "))).await?;
+            }
+        }
+        Ok(delivered)
+    }
+
+    /// 在后台立即投递一次，不阻塞当前请求
+    pub fn spawn_flush(self: &Arc<Self>, db: Arc<dyn LedgerStore>) {
+        if self.sinks.is_empty() {
+            return;
+        }
+        let notifier = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = notifier.flush(db.as_ref()).await {
+                tracing::warn!("[Notifier] 投递通知失败: {}", e);
+            }
+        });
+    }
+
+    /// 启动后立即、之后按配置的间隔定期补做未完成的预算提醒检查，并重新投递失败的通知
+    pub fn spawn_retry_loop(self: &Arc<Self>, db: Arc<dyn LedgerStore>) {
+        let notifier = self.clone();
+        let interval = Duration::from_secs(self.config.retry_interval_secs);
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = notifier.evaluate_pending(db.as_ref()).await {
+                    tracing::warn!("[Notifier] 补做预算提醒检查失败: {}", e);
+                }
+                if let Err(e) = notifier.flush(db.as_ref()).await {
+                    tracing::warn!("[Notifier] 投递通知失败: {}", e);
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+}
@@ -1,67 +1,200 @@
-use axum::{extract::State, Json, Router, routing::{get, post}, http::StatusCode, response::IntoResponse};
-use crate::auth::AuthUser;
+use axum::{extract::State, Json, Router, routing::{get, post}};
+use crate::state::AppState;
+use crate::auth::LedgerMember;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use crate::db::MongoDB;
+use crate::db::{LedgerStore, OrderFilter, StoreError};
+use crate::extract::{AppJson, AppPath};
+use crate::error::AppError;
 use crate::models::account::Account;
+use crate::models::money::Money;
+use crate::models::currency::Currency;
+use crate::models::exchange_rate::MissingRate;
+use crate::services::load_rates;
+use crate::util::double_option;
+use mongodb::bson::oid::ObjectId;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateAccount {
     pub name: String,
     pub account_type: String,
     pub balance: f64,
-    pub currency: String,
+    pub currency: Currency,
     pub remark: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct ApiError {
-    pub message: String,
+/// 局部更新（PATCH）的请求体，未提供的字段保持不变
+#[derive(Debug, Deserialize)]
+pub struct UpdateAccount {
+    pub name: Option<String>,
+    pub account_type: Option<String>,
+    pub balance: Option<f64>, // 期初余额
+    pub currency: Option<Currency>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub remark: Option<Option<String>>,
+}
+
+pub async fn create_account_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { user_id, ledger_id, .. }: LedgerMember,
+    AppJson(payload): AppJson<CreateAccount>,
+) -> Result<Json<Account>, AppError> {
+    tracing::info!("[create_account_handler] payload: {:?}", payload);
+    let balance = Money::from_major(payload.balance, payload.currency)?;
+    let account = db.create_account(Account {
+        id: ObjectId::new(),
+        ledger_id,
+        user_id,
+        name: payload.name,
+        account_type: payload.account_type,
+        balance,
+        initial_balance: balance,
+        remark: payload.remark,
+    }).await?;
+    tracing::info!("[create_account_handler] db_account: {:?}", account);
+    Ok(Json(account))
+}
+
+pub async fn get_accounts_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+) -> Result<Json<Vec<Account>>, AppError> {
+    tracing::info!("[get_accounts_handler] ledger_id: {:?}", ledger_id);
+    let accounts = db.get_accounts_by_ledger(ledger_id).await?;
+    tracing::info!("[get_accounts_handler] accounts count: {}", accounts.len());
+    Ok(Json(accounts))
+}
+
+pub async fn get_account_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(account_id): AppPath<String>,
+) -> Result<Json<Account>, AppError> {
+    let account_id = ObjectId::parse_str(&account_id)?;
+    let account = db.get_account(ledger_id, account_id).await?.ok_or(StoreError::NotFound("账户"))?;
+    Ok(Json(account))
 }
 
-impl<E: std::fmt::Display> From<E> for ApiError {
-    fn from(e: E) -> Self {
-        ApiError { message: e.to_string() }
+// 修改账户；期初余额变化时当前余额同步调整，已有订单的账户不允许更换币种
+async fn save_account(db: &dyn LedgerStore, current: Account, updated: Account) -> Result<Account, AppError> {
+    if updated.initial_balance.currency != current.initial_balance.currency {
+        let filter = OrderFilter { account_id: Some(current.id), ..Default::default() };
+        let orders = db.count_orders(current.ledger_id, &filter).await?;
+        if orders > 0 {
+            return Err(AppError::conflict(format!("账户已有 {} 条订单，不能更换币种", orders)));
+        }
     }
+    let account = db.update_account(updated).await?.ok_or(StoreError::NotFound("账户"))?;
+    tracing::info!("[save_account] db_account: {:?}", account);
+    Ok(account)
 }
 
-impl IntoResponse for ApiError {
-    fn into_response(self) -> axum::response::Response {
-        let body = Json(self);
-        (StatusCode::INTERNAL_SERVER_ERROR, body).into_response()
+pub async fn put_account_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(account_id): AppPath<String>,
+    AppJson(payload): AppJson<CreateAccount>,
+) -> Result<Json<Account>, AppError> {
+    tracing::info!("[put_account_handler] payload: {:?}", payload);
+    let account_id = ObjectId::parse_str(&account_id)?;
+    let current = db.get_account(ledger_id, account_id).await?.ok_or(StoreError::NotFound("账户"))?;
+    let updated = Account {
+        name: payload.name,
+        account_type: payload.account_type,
+        initial_balance: Money::from_major(payload.balance, payload.currency)?,
+        remark: payload.remark,
+        ..current.clone()
+    };
+    Ok(Json(save_account(db.as_ref(), current, updated).await?))
+}
+
+pub async fn patch_account_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(account_id): AppPath<String>,
+    AppJson(payload): AppJson<UpdateAccount>,
+) -> Result<Json<Account>, AppError> {
+    tracing::info!("[patch_account_handler] payload: {:?}", payload);
+    let account_id = ObjectId::parse_str(&account_id)?;
+    let current = db.get_account(ledger_id, account_id).await?.ok_or(StoreError::NotFound("账户"))?;
+    let updated = Account {
+        name: payload.name.unwrap_or_else(|| current.name.clone()),
+        account_type: payload.account_type.unwrap_or_else(|| current.account_type.clone()),
+        initial_balance: current.initial_balance.patched(payload.balance, payload.currency)?,
+        remark: payload.remark.unwrap_or_else(|| current.remark.clone()),
+        ..current.clone()
+    };
+    Ok(Json(save_account(db.as_ref(), current, updated).await?))
+}
+
+// 删除账户；仍被订单或资产引用时拒绝
+pub async fn delete_account_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(account_id): AppPath<String>,
+) -> Result<Json<bool>, AppError> {
+    let account_id = ObjectId::parse_str(&account_id)?;
+    if !db.delete_account(ledger_id, account_id).await? {
+        return Err(StoreError::NotFound("账户").into());
     }
+    tracing::info!("[delete_account_handler] deleted: {:?}", account_id);
+    Ok(Json(true))
 }
 
-pub async fn create_account_handler(
-    State(db): State<Arc<MongoDB>>,
-    AuthUser(user_id): AuthUser,
-    Json(payload): Json<CreateAccount>,
-) -> Result<Json<Account>, ApiError> {
-    println!("[INFO][create_account_handler] payload: {:?}", payload);
-    let account = db.create_account(
-        user_id,
-        payload.name,
-        payload.account_type,
-        payload.balance,
-        payload.currency,
-        payload.remark,
-    ).await?;
-    println!("[INFO][create_account_handler] db_account: {:?}", account);
+pub async fn recompute_balance_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(account_id): AppPath<String>,
+) -> Result<Json<Account>, AppError> {
+    let account_id = ObjectId::parse_str(&account_id)?;
+    let account = db.recompute_account_balance(ledger_id, account_id).await?;
+    tracing::info!("[recompute_balance_handler] account: {:?}, balance: {}", account.id, account.balance);
     Ok(Json(account))
 }
 
-pub async fn get_accounts_handler(
-    State(db): State<Arc<MongoDB>>,
-    AuthUser(user_id): AuthUser,
-) -> Result<Json<Vec<Account>>, ApiError> {
-    println!("[INFO][get_accounts_handler] user_id: {:?}", user_id);
-    let accounts = db.get_accounts_by_user(user_id).await?;
-    println!("[INFO][get_accounts_handler] accounts count: {}", accounts.len());
-    Ok(Json(accounts))
+/// 无法换算成本位币的账户和资产不计入合计，在 `missing_rates` 中列出
+#[derive(Debug, Serialize)]
+pub struct NetWorth {
+    pub base_currency: Currency,
+    pub accounts: Money, // 账户余额合计
+    pub assets: Money,   // 资产市值合计
+    pub total: Money,
+    pub missing_rates: Vec<MissingRate>,
+}
+
+// 净资产：账户余额与资产市值按当前汇率换算成本位币后求和，缺少汇率的跳过
+pub async fn net_worth_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { user_id, ledger_id, .. }: LedgerMember,
+) -> Result<Json<NetWorth>, AppError> {
+    let (base, rates) = load_rates(db.as_ref(), user_id).await?;
+    let now = mongodb::bson::DateTime::now();
+    let mut missing_rates = Vec::new();
+    let mut accounts = Money::zero(base);
+    for account in db.get_accounts_by_ledger(ledger_id).await? {
+        if let Some(converted) = rates.convert_or_record(account.balance, base, now, &mut missing_rates) {
+            accounts.accumulate(converted.minor)?;
+        }
+    }
+    let mut assets = Money::zero(base);
+    for asset in db.get_assets_by_ledger(ledger_id).await? {
+        if let Some(converted) = rates.convert_or_record(asset.value, base, now, &mut missing_rates) {
+            assets.accumulate(converted.minor)?;
+        }
+    }
+    let mut total = accounts;
+    total.accumulate(assets.minor)?;
+    Ok(Json(NetWorth { base_currency: base, accounts, assets, total, missing_rates }))
 }
 
-pub fn account_routes() -> Router<Arc<MongoDB>> {
-    println!("[INFO][account_routes] 账户路由已注册 /accounts");
+pub fn account_routes() -> Router<AppState> {
+    tracing::info!("[account_routes] 账户路由已注册 /accounts");
     Router::new()
         .route("/accounts", post(create_account_handler).get(get_accounts_handler))
+        .route("/accounts/{id}", get(get_account_handler)
+            .put(put_account_handler)
+            .patch(patch_account_handler)
+            .delete(delete_account_handler))
+        .route("/accounts/{id}/recompute", post(recompute_balance_handler))
+        .route("/net_worth", get(net_worth_handler))
 }
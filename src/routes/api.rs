@@ -1,8 +1,12 @@
-use axum::{Router, routing::get};
-use std::sync::Arc;
-use crate::db::MongoDB;
+use axum::Router;
+use axum::extract::Request;
+use axum::http::{HeaderValue, Uri};
+use crate::auth::LEDGER_HEADER;
+use crate::state::AppState;
 
-pub fn api_routes() -> Router<Arc<MongoDB>> {
+const BOOK_PREFIX: &str = "/api/books/";
+
+pub fn api_routes() -> Router<AppState> {
     Router::new()
         // 统一聚合各业务路由
     .nest("/account", crate::routes::account::account_routes())
@@ -11,6 +15,33 @@ pub fn api_routes() -> Router<Arc<MongoDB>> {
     .nest("/transaction", crate::routes::transaction::order_routes())
     .nest("/budget", crate::routes::budget::budget_routes())
     .nest("/user", crate::routes::user::user_routes())
-    .nest("/order", crate::routes::order::order_routes())
-    .nest("/order_query", crate::routes::order_query::order_query_routes())
+    .nest("/v1/orders", crate::routes::orders::orders_routes())
+    // 已废弃的旧订单接口，响应带 Deprecation 头
+    .nest("/order", crate::routes::orders::legacy_order_routes())
+    .nest("/order_query", crate::routes::orders::legacy_query_routes())
+    .nest("/rate", crate::routes::rate::rate_routes())
+    .nest("/ledger", crate::routes::ledger::ledger_routes())
+    .nest("/notification", crate::routes::notification::notification_routes())
+}
+
+/// 按路径选择账本：`/api/books/{ledger_id}/...` 改写为 `/api/...` 并设置 `X-Ledger-Id` 请求头。
+/// 改写要在路由匹配之前完成，所以用 `middleware::map_request` 包在整个应用外层。
+pub async fn select_book_by_path(mut req: Request) -> Request {
+    let Some(rest) = req.uri().path().strip_prefix(BOOK_PREFIX) else {
+        return req;
+    };
+    let (ledger_id, rest) = rest.split_once('/').unwrap_or((rest, ""));
+    let Ok(value) = HeaderValue::from_str(ledger_id) else {
+        return req;
+    };
+    let path = match req.uri().query() {
+        Some(query) => format!("/api/{}?{}", rest, query),
+        None => format!("/api/{}", rest),
+    };
+    let Ok(uri) = path.parse::<Uri>() else {
+        return req;
+    };
+    *req.uri_mut() = uri;
+    req.headers_mut().insert(LEDGER_HEADER, value);
+    req
 }
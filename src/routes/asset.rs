@@ -1,53 +1,161 @@
 use axum::{extract::State, Json, Router, routing::{get, post}};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use crate::db::MongoDB;
-use crate::auth::AuthUser;
+use crate::state::AppState;
+use crate::db::{LedgerStore, StoreError};
+use crate::auth::LedgerMember;
 use crate::models::asset::Asset;
-use crate::routes::account::ApiError;
+use crate::models::money::Money;
+use crate::models::currency::Currency;
+use crate::extract::{AppJson, AppPath};
+use crate::error::AppError;
+use crate::util::double_option;
+use mongodb::bson::oid::ObjectId;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateAsset {
     pub name: String,
     pub asset_type: String,
     pub value: f64,
-    pub currency: String,
+    pub currency: Currency,
     pub account_id: String,
     pub remark: Option<String>,
 }
 
+/// 局部更新（PATCH）的请求体，未提供的字段保持不变
+#[derive(Debug, Deserialize)]
+pub struct UpdateAsset {
+    pub name: Option<String>,
+    pub asset_type: Option<String>,
+    pub value: Option<f64>,
+    pub currency: Option<Currency>,
+    pub account_id: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub remark: Option<Option<String>>,
+}
+
+// 关联账户必须属于当前账本
+async fn resolve_account(db: &dyn LedgerStore, ledger_id: ObjectId, account_id: &str) -> Result<ObjectId, AppError> {
+    let account_id = ObjectId::parse_str(account_id)?;
+    db.get_account(ledger_id, account_id).await?.ok_or(StoreError::NotFound("账户"))?;
+    Ok(account_id)
+}
+
 pub async fn create_asset_handler(
-    State(db): State<Arc<MongoDB>>,
-    AuthUser(user_id): AuthUser,
-    Json(payload): Json<CreateAsset>,
-) -> Result<Json<Asset>, ApiError> {
-    println!("[INFO][create_asset_handler] payload: {:?}", payload);
-    let account_id = mongodb::bson::oid::ObjectId::parse_str(&payload.account_id).map_err(|e| ApiError { message: e.to_string() })?;
-    let asset = db.create_asset(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { user_id, ledger_id, .. }: LedgerMember,
+    AppJson(payload): AppJson<CreateAsset>,
+) -> Result<Json<Asset>, AppError> {
+    tracing::info!("[create_asset_handler] payload: {:?}", payload);
+    let account_id = resolve_account(db.as_ref(), ledger_id, &payload.account_id).await?;
+    let asset = db.create_asset(Asset {
+        id: ObjectId::new(),
+        ledger_id,
         user_id,
-        payload.name,
-        payload.asset_type,
-        payload.value,
-        payload.currency,
+        name: payload.name,
+        asset_type: payload.asset_type,
+        value: Money::from_major(payload.value, payload.currency)?,
         account_id,
-        payload.remark,
-    ).await?;
-    println!("[INFO][create_asset_handler] db_asset: {:?}", asset);
+        remark: payload.remark,
+    }).await?;
+    tracing::info!("[create_asset_handler] db_asset: {:?}", asset);
     Ok(Json(asset))
 }
 
 pub async fn get_assets_handler(
-    State(db): State<Arc<MongoDB>>,
-    AuthUser(user_id): AuthUser,
-) -> Result<Json<Vec<Asset>>, ApiError> {
-    println!("[INFO][get_assets_handler] user_id: {:?}", user_id);
-    let assets = db.get_assets_by_user(user_id).await?;
-    println!("[INFO][get_assets_handler] assets count: {}", assets.len());
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+) -> Result<Json<Vec<Asset>>, AppError> {
+    tracing::info!("[get_assets_handler] ledger_id: {:?}", ledger_id);
+    let assets = db.get_assets_by_ledger(ledger_id).await?;
+    tracing::info!("[get_assets_handler] assets count: {}", assets.len());
     Ok(Json(assets))
 }
 
-pub fn asset_routes() -> Router<Arc<MongoDB>> {
-    println!("[INFO][asset_routes] 资产路由已注册 /assets");
+pub async fn get_asset_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(asset_id): AppPath<String>,
+) -> Result<Json<Asset>, AppError> {
+    let asset_id = ObjectId::parse_str(&asset_id)?;
+    let asset = db.get_asset(ledger_id, asset_id).await?.ok_or(StoreError::NotFound("资产"))?;
+    Ok(Json(asset))
+}
+
+async fn save_asset(db: &dyn LedgerStore, asset: Asset) -> Result<Asset, AppError> {
+    if !db.update_asset(asset.clone()).await? {
+        return Err(StoreError::NotFound("资产").into());
+    }
+    tracing::info!("[save_asset] db_asset: {:?}", asset);
+    Ok(asset)
+}
+
+pub async fn put_asset_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(asset_id): AppPath<String>,
+    AppJson(payload): AppJson<CreateAsset>,
+) -> Result<Json<Asset>, AppError> {
+    tracing::info!("[put_asset_handler] payload: {:?}", payload);
+    let asset_id = ObjectId::parse_str(&asset_id)?;
+    let current = db.get_asset(ledger_id, asset_id).await?.ok_or(StoreError::NotFound("资产"))?;
+    let account_id = resolve_account(db.as_ref(), ledger_id, &payload.account_id).await?;
+    let asset = Asset {
+        id: asset_id,
+        ledger_id,
+        user_id: current.user_id,
+        name: payload.name,
+        asset_type: payload.asset_type,
+        value: Money::from_major(payload.value, payload.currency)?,
+        account_id,
+        remark: payload.remark,
+    };
+    Ok(Json(save_asset(db.as_ref(), asset).await?))
+}
+
+pub async fn patch_asset_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(asset_id): AppPath<String>,
+    AppJson(payload): AppJson<UpdateAsset>,
+) -> Result<Json<Asset>, AppError> {
+    tracing::info!("[patch_asset_handler] payload: {:?}", payload);
+    let asset_id = ObjectId::parse_str(&asset_id)?;
+    let current = db.get_asset(ledger_id, asset_id).await?.ok_or(StoreError::NotFound("资产"))?;
+    let account_id = match payload.account_id {
+        Some(account_id) => resolve_account(db.as_ref(), ledger_id, &account_id).await?,
+        None => current.account_id,
+    };
+    let asset = Asset {
+        name: payload.name.unwrap_or(current.name),
+        asset_type: payload.asset_type.unwrap_or(current.asset_type),
+        value: current.value.patched(payload.value, payload.currency)?,
+        account_id,
+        remark: payload.remark.unwrap_or(current.remark),
+        ..current
+    };
+    Ok(Json(save_asset(db.as_ref(), asset).await?))
+}
+
+pub async fn delete_asset_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(asset_id): AppPath<String>,
+) -> Result<Json<bool>, AppError> {
+    let asset_id = ObjectId::parse_str(&asset_id)?;
+    if !db.delete_asset(ledger_id, asset_id).await? {
+        return Err(StoreError::NotFound("资产").into());
+    }
+    tracing::info!("[delete_asset_handler] deleted: {:?}", asset_id);
+    Ok(Json(true))
+}
+
+pub fn asset_routes() -> Router<AppState> {
+    tracing::info!("[asset_routes] 资产路由已注册 /assets");
     Router::new()
         .route("/assets", post(create_asset_handler).get(get_assets_handler))
+        .route("/assets/{id}", get(get_asset_handler)
+            .put(put_asset_handler)
+            .patch(patch_asset_handler)
+            .delete(delete_asset_handler))
 }
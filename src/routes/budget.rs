@@ -1,53 +1,293 @@
 use axum::{extract::State, Json, Router, routing::{get, post}};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use crate::db::MongoDB;
-use crate::auth::AuthUser;
-use crate::models::budget::Budget;
-use crate::routes::account::ApiError;
+use crate::state::AppState;
+use crate::db::{LedgerStore, StoreError};
+use crate::auth::LedgerMember;
+use crate::models::budget::{Budget, BudgetPeriod, BudgetProgress, PeriodOutcome};
+use crate::models::category::Category;
+use crate::models::exchange_rate::{MissingRate, RateTable};
+use crate::models::money::Money;
+use crate::models::currency::Currency;
+use crate::extract::{AppJson, AppPath, AppQuery};
+use crate::error::AppError;
+use crate::services::{budget_outcomes, load_rates};
+use crate::util::{double_option, parse_date};
+use mongodb::bson::{oid::ObjectId, DateTime};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateBudget {
     pub category_id: String,
     pub amount: f64,
-    pub period: String,
-    pub start_date: String,
-    pub end_date: String,
+    pub currency: Currency,
+    pub period: BudgetPeriod,
+    pub start_date: String,       // RFC3339 或 YYYY-MM-DD
+    pub end_date: Option<String>, // 自定义周期必填
+    #[serde(default)]
+    pub rollover: bool,
+}
+
+/// 局部更新（PATCH）的请求体，未提供的字段保持不变
+#[derive(Debug, Deserialize)]
+pub struct UpdateBudget {
+    pub category_id: Option<String>,
+    pub amount: Option<f64>,
+    pub currency: Option<Currency>,
+    pub period: Option<BudgetPeriod>,
+    pub start_date: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub end_date: Option<Option<String>>,
+    pub rollover: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProgressQuery {
+    pub date: Option<String>, // 统计截至的时间，默认当前时间
+}
+
+/// 当前账本内所有在 `date` 时处于周期中的预算的执行情况，合计按本位币统计；
+/// 无法换算成本位币的预算不计入合计，在 `missing_rates` 中列出
+#[derive(Debug, Serialize)]
+pub struct BudgetSummary {
+    pub date: String,
+    pub base_currency: Currency,
+    pub total_amount: Money,
+    pub total_spent: Money,
+    pub total_remaining: Money,
+    pub budgets: Vec<BudgetProgress>,
+    pub missing_rates: Vec<MissingRate>,
+}
+
+// 预算分类必须属于当前账本
+async fn resolve_category(db: &dyn LedgerStore, ledger_id: ObjectId, category_id: &str) -> Result<ObjectId, AppError> {
+    let category_id = ObjectId::parse_str(category_id)?;
+    db.get_category(ledger_id, category_id).await?.ok_or(StoreError::NotFound("分类"))?;
+    Ok(category_id)
+}
+
+/// `now` 所在周期的执行结果；预算结束后取最后一个周期，尚未开始时取第一个周期
+async fn current_outcome(
+    db: &dyn LedgerStore,
+    budget: &Budget,
+    categories: &[Category],
+    rates: &RateTable,
+    now: DateTime,
+) -> Result<PeriodOutcome, AppError> {
+    if let Some(outcome) = budget_outcomes(db, budget, categories, rates, now).await?.pop() {
+        return Ok(outcome);
+    }
+    let window = budget.window(0).ok_or_else(|| AppError::validation("预算没有有效的周期"))?;
+    let zero = Money::zero(budget.amount.currency);
+    Ok(PeriodOutcome { window, carried_over: zero, spent: zero, missing_rates: Vec::new() })
+}
+
+fn progress_date(query: &ProgressQuery) -> Result<DateTime, AppError> {
+    Ok(query.date.as_deref().map(parse_date).transpose()?.unwrap_or_else(DateTime::now))
+}
+
+pub async fn budget_progress_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { user_id, ledger_id, .. }: LedgerMember,
+    AppPath(budget_id): AppPath<String>,
+    AppQuery(query): AppQuery<ProgressQuery>,
+) -> Result<Json<BudgetProgress>, AppError> {
+    let now = progress_date(&query)?;
+    let budget_id = ObjectId::parse_str(&budget_id)?;
+    let budget = db.get_budget(ledger_id, budget_id).await?.ok_or(StoreError::NotFound("预算"))?;
+    let categories = db.get_categories_by_ledger(ledger_id).await?;
+    let (_, rates) = load_rates(db.as_ref(), user_id).await?;
+    let outcome = current_outcome(db.as_ref(), &budget, &categories, &rates, now).await?;
+    Ok(Json(budget.progress(&outcome, now)?))
+}
+
+/// 截至 `date` 已开始的全部周期，按时间顺序
+pub async fn budget_periods_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { user_id, ledger_id, .. }: LedgerMember,
+    AppPath(budget_id): AppPath<String>,
+    AppQuery(query): AppQuery<ProgressQuery>,
+) -> Result<Json<Vec<BudgetProgress>>, AppError> {
+    let now = progress_date(&query)?;
+    let budget_id = ObjectId::parse_str(&budget_id)?;
+    let budget = db.get_budget(ledger_id, budget_id).await?.ok_or(StoreError::NotFound("预算"))?;
+    let categories = db.get_categories_by_ledger(ledger_id).await?;
+    let (_, rates) = load_rates(db.as_ref(), user_id).await?;
+    let outcomes = budget_outcomes(db.as_ref(), &budget, &categories, &rates, now).await?;
+    tracing::info!("[budget_periods_handler] budget_id: {:?}, periods: {}", budget_id, outcomes.len());
+    Ok(Json(outcomes.iter().map(|o| budget.progress(o, now)).collect::<Result<_, _>>()?))
+}
+
+pub async fn budget_summary_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { user_id, ledger_id, .. }: LedgerMember,
+    AppQuery(query): AppQuery<ProgressQuery>,
+) -> Result<Json<BudgetSummary>, AppError> {
+    let now = progress_date(&query)?;
+    let categories = db.get_categories_by_ledger(ledger_id).await?;
+    let (base, rates) = load_rates(db.as_ref(), user_id).await?;
+    let mut summary = BudgetSummary {
+        date: now.try_to_rfc3339_string().unwrap_or_default(),
+        base_currency: base,
+        total_amount: Money::zero(base),
+        total_spent: Money::zero(base),
+        total_remaining: Money::zero(base),
+        budgets: Vec::new(),
+        missing_rates: Vec::new(),
+    };
+    for budget in db.get_budgets_by_ledger(ledger_id).await?.into_iter().filter(|b| b.is_active(now)) {
+        let outcome = current_outcome(db.as_ref(), &budget, &categories, &rates, now).await?;
+        let progress = budget.progress(&outcome, now)?;
+        let amount = rates.convert_or_record(progress.amount, base, now, &mut summary.missing_rates);
+        let spent = rates.convert_or_record(progress.spent, base, now, &mut summary.missing_rates);
+        if let (Some(amount), Some(spent)) = (amount, spent) {
+            summary.total_amount.accumulate(amount.minor)?;
+            summary.total_spent.accumulate(spent.minor)?;
+        }
+        summary.budgets.push(progress);
+    }
+    summary.total_remaining = summary.total_amount;
+    summary.total_remaining.deduct(summary.total_spent.minor)?;
+    tracing::info!("[budget_summary_handler] ledger_id: {:?}, budgets: {}", ledger_id, summary.budgets.len());
+    Ok(Json(summary))
 }
 
 pub async fn create_budget_handler(
-    State(db): State<Arc<MongoDB>>,
-    AuthUser(user_id): AuthUser,
-    Json(payload): Json<CreateBudget>,
-) -> Result<Json<Budget>, ApiError> {
-    println!("[INFO][create_budget_handler] payload: {:?}", payload);
-    let category_id = mongodb::bson::oid::ObjectId::parse_str(&payload.category_id).map_err(|e| ApiError { message: e.to_string() })?;
-    let start_date = mongodb::bson::DateTime::parse_rfc3339_str(&payload.start_date).map_err(|e| ApiError { message: e.to_string() })?;
-    let end_date = mongodb::bson::DateTime::parse_rfc3339_str(&payload.end_date).map_err(|e| ApiError { message: e.to_string() })?;
-    let budget = db.create_budget(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { user_id, ledger_id, .. }: LedgerMember,
+    AppJson(payload): AppJson<CreateBudget>,
+) -> Result<Json<Budget>, AppError> {
+    tracing::info!("[create_budget_handler] payload: {:?}", payload);
+    let category_id = resolve_category(db.as_ref(), ledger_id, &payload.category_id).await?;
+    let budget = Budget {
+        id: ObjectId::new(),
+        ledger_id,
         user_id,
         category_id,
-        payload.amount,
-        payload.period,
-        start_date,
-        end_date,
-    ).await?;
-    println!("[INFO][create_budget_handler] db_budget: {:?}", budget);
+        amount: Money::from_major(payload.amount, payload.currency)?,
+        period: payload.period,
+        start_date: parse_date(&payload.start_date)?,
+        end_date: payload.end_date.as_deref().map(parse_date).transpose()?,
+        rollover: payload.rollover,
+    };
+    validate_budget(&budget)?;
+    let budget = db.create_budget(budget).await?;
+    tracing::info!("[create_budget_handler] db_budget: {:?}", budget);
     Ok(Json(budget))
 }
 
 pub async fn get_budgets_handler(
-    State(db): State<Arc<MongoDB>>,
-    AuthUser(user_id): AuthUser,
-) -> Result<Json<Vec<Budget>>, ApiError> {
-    println!("[INFO][get_budgets_handler] user_id: {:?}", user_id);
-    let budgets = db.get_budgets_by_user(user_id).await?;
-    println!("[INFO][get_budgets_handler] budgets count: {}", budgets.len());
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+) -> Result<Json<Vec<Budget>>, AppError> {
+    tracing::info!("[get_budgets_handler] ledger_id: {:?}", ledger_id);
+    let budgets = db.get_budgets_by_ledger(ledger_id).await?;
+    tracing::info!("[get_budgets_handler] budgets count: {}", budgets.len());
     Ok(Json(budgets))
 }
 
-pub fn budget_routes() -> Router<Arc<MongoDB>> {
-    println!("[INFO][budget_routes] 预算路由已注册 /budgets");
+pub async fn get_budget_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(budget_id): AppPath<String>,
+) -> Result<Json<Budget>, AppError> {
+    let budget_id = ObjectId::parse_str(&budget_id)?;
+    let budget = db.get_budget(ledger_id, budget_id).await?.ok_or(StoreError::NotFound("预算"))?;
+    Ok(Json(budget))
+}
+
+fn validate_budget(budget: &Budget) -> Result<(), AppError> {
+    if budget.amount.minor < 0 {
+        return Err(AppError::validation("预算金额不能为负"));
+    }
+    match budget.end_date {
+        None if budget.period == BudgetPeriod::Custom => Err(AppError::validation("自定义周期的预算必须指定结束日期")),
+        Some(end_date) if end_date < budget.start_date => Err(AppError::validation("结束日期不能早于开始日期")),
+        _ => Ok(()),
+    }
+}
+
+async fn save_budget(db: &dyn LedgerStore, budget: Budget) -> Result<Budget, AppError> {
+    validate_budget(&budget)?;
+    if !db.update_budget(budget.clone()).await? {
+        return Err(StoreError::NotFound("预算").into());
+    }
+    tracing::info!("[save_budget] db_budget: {:?}", budget);
+    Ok(budget)
+}
+
+pub async fn put_budget_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(budget_id): AppPath<String>,
+    AppJson(payload): AppJson<CreateBudget>,
+) -> Result<Json<Budget>, AppError> {
+    tracing::info!("[put_budget_handler] payload: {:?}", payload);
+    let budget_id = ObjectId::parse_str(&budget_id)?;
+    let current = db.get_budget(ledger_id, budget_id).await?.ok_or(StoreError::NotFound("预算"))?;
+    let budget = Budget {
+        id: budget_id,
+        ledger_id,
+        user_id: current.user_id,
+        category_id: resolve_category(db.as_ref(), ledger_id, &payload.category_id).await?,
+        amount: Money::from_major(payload.amount, payload.currency)?,
+        period: payload.period,
+        start_date: parse_date(&payload.start_date)?,
+        end_date: payload.end_date.as_deref().map(parse_date).transpose()?,
+        rollover: payload.rollover,
+    };
+    Ok(Json(save_budget(db.as_ref(), budget).await?))
+}
+
+pub async fn patch_budget_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(budget_id): AppPath<String>,
+    AppJson(payload): AppJson<UpdateBudget>,
+) -> Result<Json<Budget>, AppError> {
+    tracing::info!("[patch_budget_handler] payload: {:?}", payload);
+    let budget_id = ObjectId::parse_str(&budget_id)?;
+    let current = db.get_budget(ledger_id, budget_id).await?.ok_or(StoreError::NotFound("预算"))?;
+    let category_id = match payload.category_id {
+        Some(category_id) => resolve_category(db.as_ref(), ledger_id, &category_id).await?,
+        None => current.category_id,
+    };
+    let budget = Budget {
+        category_id,
+        amount: current.amount.patched(payload.amount, payload.currency)?,
+        period: payload.period.unwrap_or(current.period),
+        start_date: payload.start_date.as_deref().map(parse_date).transpose()?.unwrap_or(current.start_date),
+        end_date: match payload.end_date {
+            Some(end_date) => end_date.as_deref().map(parse_date).transpose()?,
+            None => current.end_date,
+        },
+        rollover: payload.rollover.unwrap_or(current.rollover),
+        ..current
+    };
+    Ok(Json(save_budget(db.as_ref(), budget).await?))
+}
+
+pub async fn delete_budget_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(budget_id): AppPath<String>,
+) -> Result<Json<bool>, AppError> {
+    let budget_id = ObjectId::parse_str(&budget_id)?;
+    if !db.delete_budget(ledger_id, budget_id).await? {
+        return Err(StoreError::NotFound("预算").into());
+    }
+    tracing::info!("[delete_budget_handler] deleted: {:?}", budget_id);
+    Ok(Json(true))
+}
+
+pub fn budget_routes() -> Router<AppState> {
+    tracing::info!("[budget_routes] 预算路由已注册 /budgets");
     Router::new()
         .route("/budgets", post(create_budget_handler).get(get_budgets_handler))
+        .route("/budgets/{id}", get(get_budget_handler)
+            .put(put_budget_handler)
+            .patch(patch_budget_handler)
+            .delete(delete_budget_handler))
+        .route("/budgets/{id}/progress", get(budget_progress_handler))
+        .route("/budgets/{id}/periods", get(budget_periods_handler))
+        .route("/summary", get(budget_summary_handler))
 }
@@ -1,9 +1,19 @@
-use axum::{extract::State, Json, Router, routing::{get, post}, http::StatusCode, response::IntoResponse};
+use axum::{extract::State, Json, Router, routing::{get, post}};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
-use crate::db::MongoDB;
-use crate::auth::AuthUser;
-use crate::models::category::Category;
+use crate::state::AppState;
+use crate::db::{LedgerStore, OrderFilter, StoreError};
+use crate::auth::LedgerMember;
+use crate::models::category::{build_tree, check_parent, Category, CategoryNode, CATEGORY_TYPES};
+use crate::models::currency::Currency;
+use crate::models::exchange_rate::MissingRate;
+use crate::models::money::Money;
+use crate::services::load_rates;
+use crate::util::{double_option, parse_date};
+use crate::extract::{AppJson, AppPath, AppQuery};
+use crate::error::AppError;
+use mongodb::bson::oid::ObjectId;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateCategory {
@@ -12,37 +22,268 @@ pub struct CreateCategory {
     pub category_type: String,
 }
 
-use crate::routes::account::ApiError;
+/// 局部更新（PATCH）的请求体，`parent_id` 显式为 null 时移到顶级
+#[derive(Debug, Deserialize)]
+pub struct UpdateCategory {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub parent_id: Option<Option<String>>,
+    pub category_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MoveCategory {
+    pub parent_id: Option<String>, // 为空时移到顶级
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CategoryStatsQuery {
+    pub order_type: Option<String>, // 默认统计消费
+    pub date_start: Option<String>,
+    pub date_end: Option<String>,
+}
+
+/// 各分类的金额（本位币），`total` 汇总了全部子分类；缺少汇率的订单不计入，在 `missing_rates` 中列出
+#[derive(Debug, Serialize)]
+pub struct CategoryStats {
+    pub order_type: String,
+    pub base_currency: Currency,
+    pub total: Money,
+    pub uncategorized: Money, // 没有分类的订单
+    pub categories: Vec<CategoryNode>,
+    pub missing_rates: Vec<MissingRate>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteCategoryQuery {
+    pub reassign_to: Option<String>, // 预算和子分类转移到的目标分类
+}
+
+// 解析并校验父分类：必须存在且属于当前账本，不能形成环，层数不能超过上限
+async fn resolve_parent(
+    db: &dyn LedgerStore,
+    ledger_id: ObjectId,
+    category_id: ObjectId,
+    parent_id: Option<String>,
+) -> Result<Option<ObjectId>, AppError> {
+    let Some(parent_id) = parent_id else { return Ok(None) };
+    let parent_id = ObjectId::parse_str(&parent_id).map_err(|_| AppError::validation(format!("无效的父分类ID: {}", parent_id)))?;
+    let categories = db.get_categories_by_ledger(ledger_id).await?;
+    if parent_id != category_id && !categories.iter().any(|c| c.id == parent_id) {
+        return Err(StoreError::NotFound("父分类").into());
+    }
+    check_parent(&categories, category_id, parent_id).map_err(AppError::validation)?;
+    Ok(Some(parent_id))
+}
+
+fn check_type(category_type: &str) -> Result<(), AppError> {
+    if !CATEGORY_TYPES.contains(&category_type) {
+        return Err(AppError::validation(format!("分类类型必须是 {} 之一", CATEGORY_TYPES.join("/"))));
+    }
+    Ok(())
+}
+
+async fn save_category(db: &dyn LedgerStore, current: &Category, category: Category) -> Result<Category, AppError> {
+    // 已有订单的分类不能改类型，否则这些订单的方向会与分类不符
+    if category.category_type != current.category_type {
+        check_type(&category.category_type)?;
+        let filter = OrderFilter { category_ids: Some(vec![category.id]), ..Default::default() };
+        let orders = db.count_orders(category.ledger_id, &filter).await?;
+        if orders > 0 {
+            return Err(StoreError::Conflict(format!("分类仍被 {} 条订单使用，不能修改类型", orders)).into());
+        }
+    }
+    if !db.update_category(category.clone()).await? {
+        return Err(StoreError::NotFound("分类").into());
+    }
+    tracing::info!("[save_category] db_category: {:?}", category);
+    Ok(category)
+}
 
 pub async fn create_category_handler(
-    State(db): State<Arc<MongoDB>>,
-    AuthUser(user_id): AuthUser,
-    Json(payload): Json<CreateCategory>,
-) -> Result<Json<Category>, ApiError> {
-    println!("[INFO][create_category_handler] payload: {:?}", payload);
-    let parent_id = payload.parent_id.and_then(|id| mongodb::bson::oid::ObjectId::parse_str(&id).ok());
-    let category = db.create_category(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { user_id, ledger_id, .. }: LedgerMember,
+    AppJson(payload): AppJson<CreateCategory>,
+) -> Result<Json<Category>, AppError> {
+    tracing::info!("[create_category_handler] payload: {:?}", payload);
+    check_type(&payload.category_type)?;
+    let id = ObjectId::new();
+    let parent_id = resolve_parent(db.as_ref(), ledger_id, id, payload.parent_id).await?;
+    let category = db.create_category(Category {
+        id,
+        ledger_id,
         user_id,
-        payload.name,
+        name: payload.name,
         parent_id,
-        payload.category_type,
-    ).await?;
-    println!("[INFO][create_category_handler] db_category: {:?}", category);
+        category_type: payload.category_type,
+    }).await?;
+    tracing::info!("[create_category_handler] db_category: {:?}", category);
     Ok(Json(category))
 }
 
 pub async fn get_categories_handler(
-    State(db): State<Arc<MongoDB>>,
-    AuthUser(user_id): AuthUser,
-) -> Result<Json<Vec<Category>>, ApiError> {
-    println!("[INFO][get_categories_handler] user_id: {:?}", user_id);
-    let categories = db.get_categories_by_user(user_id).await?;
-    println!("[INFO][get_categories_handler] categories count: {}", categories.len());
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+) -> Result<Json<Vec<Category>>, AppError> {
+    tracing::info!("[get_categories_handler] ledger_id: {:?}", ledger_id);
+    let categories = db.get_categories_by_ledger(ledger_id).await?;
+    tracing::info!("[get_categories_handler] categories count: {}", categories.len());
     Ok(Json(categories))
 }
 
-pub fn category_routes() -> Router<Arc<MongoDB>> {
-    println!("[INFO][category_routes] 分类路由已注册 /categories");
+/// 当前账本的分类树
+pub async fn get_category_tree_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+) -> Result<Json<Vec<CategoryNode>>, AppError> {
+    let categories = db.get_categories_by_ledger(ledger_id).await?;
+    tracing::info!("[get_category_tree_handler] ledger_id: {:?}, categories count: {}", ledger_id, categories.len());
+    Ok(Json(build_tree(&categories)))
+}
+
+/// 按分类树统计订单金额，子分类的金额逐级汇总到父分类
+pub async fn category_stats_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { user_id, ledger_id, .. }: LedgerMember,
+    AppQuery(query): AppQuery<CategoryStatsQuery>,
+) -> Result<Json<CategoryStats>, AppError> {
+    let order_type = query.order_type.filter(|t| !t.is_empty()).unwrap_or_else(|| "消费".to_string());
+    let filter = OrderFilter {
+        order_type: Some(order_type.clone()),
+        date_start: query.date_start.as_deref().filter(|s| !s.is_empty()).map(parse_date).transpose()?,
+        date_end: query.date_end.as_deref().filter(|s| !s.is_empty()).map(parse_date).transpose()?,
+        ..Default::default()
+    };
+    let (base, rates) = load_rates(db.as_ref(), user_id).await?;
+    let categories = db.get_categories_by_ledger(ledger_id).await?;
+    let mut amounts: HashMap<String, Money> = HashMap::new();
+    let mut stats = CategoryStats {
+        order_type,
+        base_currency: base,
+        total: Money::zero(base),
+        uncategorized: Money::zero(base),
+        categories: build_tree(&categories),
+        missing_rates: Vec::new(),
+    };
+    for t in db.order_totals(ledger_id, &filter, Some(base)).await? {
+        let Some(converted) = rates.convert_or_record(t.amount, base, t.date, &mut stats.missing_rates) else {
+            continue;
+        };
+        let converted = converted.minor;
+        stats.total.accumulate(converted)?;
+        match t.category_id {
+            Some(id) => amounts.entry(id.to_hex()).or_insert(Money::zero(base)).accumulate(converted)?,
+            None => stats.uncategorized.accumulate(converted)?,
+        }
+    }
+    for node in stats.categories.iter_mut() {
+        node.roll_up(&amounts, base)?;
+    }
+    tracing::info!("[category_stats_handler] ledger_id: {:?}, total: {}", ledger_id, stats.total);
+    Ok(Json(stats))
+}
+
+pub async fn get_category_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(category_id): AppPath<String>,
+) -> Result<Json<Category>, AppError> {
+    let category_id = ObjectId::parse_str(&category_id)?;
+    let category = db.get_category(ledger_id, category_id).await?.ok_or(StoreError::NotFound("分类"))?;
+    Ok(Json(category))
+}
+
+pub async fn put_category_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(category_id): AppPath<String>,
+    AppJson(payload): AppJson<CreateCategory>,
+) -> Result<Json<Category>, AppError> {
+    tracing::info!("[put_category_handler] payload: {:?}", payload);
+    let category_id = ObjectId::parse_str(&category_id)?;
+    let current = db.get_category(ledger_id, category_id).await?.ok_or(StoreError::NotFound("分类"))?;
+    let parent_id = resolve_parent(db.as_ref(), ledger_id, category_id, payload.parent_id).await?;
+    let category = Category {
+        id: category_id,
+        ledger_id,
+        user_id: current.user_id,
+        name: payload.name,
+        parent_id,
+        category_type: payload.category_type,
+    };
+    Ok(Json(save_category(db.as_ref(), &current, category).await?))
+}
+
+pub async fn patch_category_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(category_id): AppPath<String>,
+    AppJson(payload): AppJson<UpdateCategory>,
+) -> Result<Json<Category>, AppError> {
+    tracing::info!("[patch_category_handler] payload: {:?}", payload);
+    let category_id = ObjectId::parse_str(&category_id)?;
+    let current = db.get_category(ledger_id, category_id).await?.ok_or(StoreError::NotFound("分类"))?;
+    let parent_id = match payload.parent_id {
+        Some(parent_id) => resolve_parent(db.as_ref(), ledger_id, category_id, parent_id).await?,
+        None => current.parent_id,
+    };
+    let category = Category {
+        name: payload.name.unwrap_or_else(|| current.name.clone()),
+        parent_id,
+        category_type: payload.category_type.unwrap_or_else(|| current.category_type.clone()),
+        ..current.clone()
+    };
+    Ok(Json(save_category(db.as_ref(), &current, category).await?))
+}
+
+/// 把分类连同全部子分类移到 `parent_id` 下
+pub async fn move_category_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(category_id): AppPath<String>,
+    AppJson(payload): AppJson<MoveCategory>,
+) -> Result<Json<Category>, AppError> {
+    tracing::info!("[move_category_handler] payload: {:?}", payload);
+    let category_id = ObjectId::parse_str(&category_id)?;
+    let current = db.get_category(ledger_id, category_id).await?.ok_or(StoreError::NotFound("分类"))?;
+    let parent_id = resolve_parent(db.as_ref(), ledger_id, category_id, payload.parent_id).await?;
+    Ok(Json(save_category(db.as_ref(), &current, Category { parent_id, ..current.clone() }).await?))
+}
+
+// 删除分类；仍被预算或子分类引用时，需通过 `?reassign_to=` 指定转移目标。
+// 订单随之转到目标分类，未指定目标时变为未分类
+pub async fn delete_category_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(category_id): AppPath<String>,
+    AppQuery(query): AppQuery<DeleteCategoryQuery>,
+) -> Result<Json<bool>, AppError> {
+    let category_id = ObjectId::parse_str(&category_id)?;
+    let reassign_to = query.reassign_to.as_deref().map(ObjectId::parse_str).transpose()?;
+    // 订单也会转到目标分类，两者类型必须一致
+    if let Some(target) = reassign_to
+        && let Some(current) = db.get_category(ledger_id, category_id).await?
+        && let Some(target) = db.get_category(ledger_id, target).await?
+        && target.category_type != current.category_type
+    {
+        return Err(AppError::validation("目标分类的类型与被删除的分类不一致"));
+    }
+    if !db.delete_category(ledger_id, category_id, reassign_to).await? {
+        return Err(StoreError::NotFound("分类").into());
+    }
+    tracing::info!("[delete_category_handler] deleted: {:?}, reassign_to: {:?}", category_id, reassign_to);
+    Ok(Json(true))
+}
+
+pub fn category_routes() -> Router<AppState> {
+    tracing::info!("[category_routes] 分类路由已注册 /categories");
     Router::new()
         .route("/categories", post(create_category_handler).get(get_categories_handler))
+        .route("/tree", get(get_category_tree_handler))
+        .route("/stats", get(category_stats_handler))
+        .route("/categories/{id}", get(get_category_handler)
+            .put(put_category_handler)
+            .patch(patch_category_handler)
+            .delete(delete_category_handler))
+        .route("/categories/{id}/move", post(move_category_handler))
 }
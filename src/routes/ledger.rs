@@ -0,0 +1,353 @@
+use axum::{Router, Json, routing::{get, post, put}, extract::State};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use crate::auth::{default_ledger, AuthSession, AuthUser};
+use crate::db::LedgerStore;
+use crate::extract::{AppJson, AppPath};
+use crate::error::AppError;
+use crate::models::ledger::{Invitation, Ledger, Member, Role};
+use crate::models::user::normalize_username;
+use crate::state::AppState;
+
+const NAME_MAX_CHARS: usize = 32;
+
+#[derive(Debug, Deserialize)]
+pub struct LedgerPayload {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DefaultLedgerPayload {
+    pub ledger_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InvitePayload {
+    pub username: String,
+    pub role: Role, // editor 或 viewer
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RolePayload {
+    pub role: Role,
+}
+
+#[derive(Serialize)]
+pub struct MemberOut {
+    pub user_id: String,
+    pub username: String,
+    pub role: Role,
+}
+
+#[derive(Serialize)]
+pub struct LedgerOut {
+    pub id: String,
+    pub name: String,
+    pub role: Role, // 当前用户在账本中的角色
+    pub is_default: bool, // 是否为当前用户未指定账本时使用的账本
+    pub created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct LedgerDetail {
+    #[serde(flatten)]
+    pub ledger: LedgerOut,
+    pub members: Vec<MemberOut>,
+}
+
+#[derive(Serialize)]
+pub struct InvitationOut {
+    pub id: String,
+    pub ledger_id: String,
+    pub ledger_name: String,
+    pub inviter: String,
+    pub invitee: String,
+    pub role: Role,
+    pub created_at: String,
+}
+
+fn ledger_out(ledger: &Ledger, role: Role, is_default: bool) -> LedgerOut {
+    LedgerOut {
+        id: ledger.id.to_hex(),
+        name: ledger.name.clone(),
+        role,
+        is_default,
+        created_at: ledger.created_at.try_to_rfc3339_string().unwrap_or_default(),
+    }
+}
+
+async fn username_of(db: &dyn LedgerStore, user_id: ObjectId) -> Result<String, AppError> {
+    Ok(db.get_user(user_id).await?.map(|u| u.username).unwrap_or_default())
+}
+
+async fn invitation_out(db: &dyn LedgerStore, invitation: &Invitation) -> Result<InvitationOut, AppError> {
+    let ledger_name = db.get_ledger(invitation.ledger_id).await?.map(|l| l.name).unwrap_or_default();
+    Ok(InvitationOut {
+        id: invitation.id.to_hex(),
+        ledger_id: invitation.ledger_id.to_hex(),
+        ledger_name,
+        inviter: username_of(db, invitation.inviter_id).await?,
+        invitee: username_of(db, invitation.invitee_id).await?,
+        role: invitation.role,
+        created_at: invitation.created_at.try_to_rfc3339_string().unwrap_or_default(),
+    })
+}
+
+// 读取账本及当前用户的角色；非成员一律 404，不暴露账本是否存在
+async fn load_ledger(db: &dyn LedgerStore, user_id: ObjectId, ledger_id: &str) -> Result<(Ledger, Role), AppError> {
+    let ledger_id = ObjectId::parse_str(ledger_id)?;
+    let ledger = db.get_ledger(ledger_id).await?.ok_or(AppError::not_found("账本不存在"))?;
+    let role = ledger.role_of(user_id).ok_or(AppError::not_found("账本不存在"))?;
+    Ok((ledger, role))
+}
+
+// 只有所有者能管理成员和邀请
+async fn load_owned_ledger(db: &dyn LedgerStore, user_id: ObjectId, ledger_id: &str) -> Result<Ledger, AppError> {
+    let (ledger, role) = load_ledger(db, user_id, ledger_id).await?;
+    if role != Role::Owner {
+        return Err(AppError::forbidden("只有账本所有者可以管理成员"));
+    }
+    Ok(ledger)
+}
+
+fn valid_name(raw: &str) -> Result<String, AppError> {
+    let name = raw.trim();
+    if name.is_empty() {
+        return Err(AppError::validation("账本名称不能为空"));
+    }
+    if name.chars().count() > NAME_MAX_CHARS {
+        return Err(AppError::validation(format!("账本名称不能超过{}个字符", NAME_MAX_CHARS)));
+    }
+    Ok(name.to_string())
+}
+
+async fn default_ledger_id(db: &dyn LedgerStore, user_id: ObjectId) -> Result<Option<ObjectId>, AppError> {
+    Ok(default_ledger(db, user_id).await?.map(|l| l.id))
+}
+
+fn check_member_role(role: Role) -> Result<(), AppError> {
+    if role == Role::Owner {
+        return Err(AppError::validation("不能把成员设为所有者"));
+    }
+    Ok(())
+}
+
+pub async fn list_ledgers_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthUser(user_id): AuthUser,
+) -> Result<Json<Vec<LedgerOut>>, AppError> {
+    tracing::info!("[list_ledgers_handler] user_id: {:?}", user_id);
+    let default_id = default_ledger_id(db.as_ref(), user_id).await?;
+    let ledgers = db.get_ledgers_for_user(user_id).await?;
+    Ok(Json(ledgers.iter()
+        .filter_map(|l| Some(ledger_out(l, l.role_of(user_id)?, Some(l.id) == default_id)))
+        .collect()))
+}
+
+/// 新建账本，创建者是所有者；各账本的数据互相独立
+pub async fn create_ledger_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthUser(user_id): AuthUser,
+    AppJson(payload): AppJson<LedgerPayload>,
+) -> Result<Json<LedgerOut>, AppError> {
+    tracing::info!("[create_ledger_handler] payload: {:?}", payload);
+    let ledger = db.create_ledger(Ledger {
+        id: ObjectId::new(),
+        name: valid_name(&payload.name)?,
+        members: vec![Member { user_id, role: Role::Owner }],
+        created_at: DateTime::now(),
+    }).await?;
+    Ok(Json(ledger_out(&ledger, Role::Owner, false)))
+}
+
+pub async fn rename_ledger_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthUser(user_id): AuthUser,
+    AppPath(ledger_id): AppPath<String>,
+    AppJson(payload): AppJson<LedgerPayload>,
+) -> Result<Json<LedgerOut>, AppError> {
+    tracing::info!("[rename_ledger_handler] ledger_id: {}, payload: {:?}", ledger_id, payload);
+    let name = valid_name(&payload.name)?;
+    let ledger = load_owned_ledger(db.as_ref(), user_id, &ledger_id).await?;
+    db.rename_ledger(ledger.id, name.clone()).await?;
+    let is_default = default_ledger_id(db.as_ref(), user_id).await? == Some(ledger.id);
+    Ok(Json(ledger_out(&Ledger { name, ..ledger }, Role::Owner, is_default)))
+}
+
+/// 删除账本及其中全部数据；当前的默认账本需先切换默认账本才能删除
+pub async fn delete_ledger_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthSession { user_id, .. }: AuthSession,
+    AppPath(ledger_id): AppPath<String>,
+) -> Result<Json<bool>, AppError> {
+    let ledger = load_owned_ledger(db.as_ref(), user_id, &ledger_id).await?;
+    if default_ledger_id(db.as_ref(), user_id).await? == Some(ledger.id) {
+        return Err(AppError::conflict("不能删除默认账本，请先切换默认账本"));
+    }
+    db.delete_ledger(ledger.id).await?;
+    tracing::info!("[delete_ledger_handler] deleted: {:?}", ledger.id);
+    Ok(Json(true))
+}
+
+/// 设置未带账本 ID 的请求默认使用的账本
+pub async fn set_default_ledger_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthUser(user_id): AuthUser,
+    AppJson(payload): AppJson<DefaultLedgerPayload>,
+) -> Result<Json<LedgerOut>, AppError> {
+    let (ledger, role) = load_ledger(db.as_ref(), user_id, &payload.ledger_id).await?;
+    db.set_default_ledger(user_id, ledger.id).await?;
+    tracing::info!("[set_default_ledger_handler] user_id: {:?}, ledger_id: {:?}", user_id, ledger.id);
+    Ok(Json(ledger_out(&ledger, role, true)))
+}
+
+pub async fn get_ledger_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthUser(user_id): AuthUser,
+    AppPath(ledger_id): AppPath<String>,
+) -> Result<Json<LedgerDetail>, AppError> {
+    let (ledger, role) = load_ledger(db.as_ref(), user_id, &ledger_id).await?;
+    let mut members = Vec::new();
+    for m in &ledger.members {
+        members.push(MemberOut {
+            user_id: m.user_id.to_hex(),
+            username: username_of(db.as_ref(), m.user_id).await?,
+            role: m.role,
+        });
+    }
+    let is_default = default_ledger_id(db.as_ref(), user_id).await? == Some(ledger.id);
+    Ok(Json(LedgerDetail { ledger: ledger_out(&ledger, role, is_default), members }))
+}
+
+// 成员和所有权的变更（邀请、角色、移除、接受邀请、删除账本）只接受登录会话，不接受 API 密钥
+pub async fn invite_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthSession { user_id, .. }: AuthSession,
+    AppPath(ledger_id): AppPath<String>,
+    AppJson(payload): AppJson<InvitePayload>,
+) -> Result<Json<InvitationOut>, AppError> {
+    tracing::info!("[invite_handler] ledger_id: {}, payload: {:?}", ledger_id, payload);
+    check_member_role(payload.role)?;
+    let ledger = load_owned_ledger(db.as_ref(), user_id, &ledger_id).await?;
+    let invitee = db.get_user_by_username(&normalize_username(&payload.username)).await?
+        .ok_or(AppError::not_found("用户不存在"))?;
+    if ledger.role_of(invitee.id).is_some() {
+        return Err(AppError::conflict("该用户已是账本成员"));
+    }
+    if db.get_invitations_by_ledger(ledger.id).await?.iter().any(|i| i.invitee_id == invitee.id) {
+        return Err(AppError::conflict("已邀请过该用户"));
+    }
+    let invitation = db.create_invitation(Invitation {
+        id: ObjectId::new(),
+        ledger_id: ledger.id,
+        inviter_id: user_id,
+        invitee_id: invitee.id,
+        role: payload.role,
+        created_at: DateTime::now(),
+    }).await?;
+    Ok(Json(invitation_out(db.as_ref(), &invitation).await?))
+}
+
+pub async fn ledger_invitations_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthUser(user_id): AuthUser,
+    AppPath(ledger_id): AppPath<String>,
+) -> Result<Json<Vec<InvitationOut>>, AppError> {
+    let ledger = load_owned_ledger(db.as_ref(), user_id, &ledger_id).await?;
+    let mut result = Vec::new();
+    for invitation in db.get_invitations_by_ledger(ledger.id).await? {
+        result.push(invitation_out(db.as_ref(), &invitation).await?);
+    }
+    Ok(Json(result))
+}
+
+pub async fn set_member_role_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthSession { user_id, .. }: AuthSession,
+    AppPath((ledger_id, member_id)): AppPath<(String, String)>,
+    AppJson(payload): AppJson<RolePayload>,
+) -> Result<Json<bool>, AppError> {
+    tracing::info!("[set_member_role_handler] ledger_id: {}, member: {}, role: {:?}", ledger_id, member_id, payload.role);
+    check_member_role(payload.role)?;
+    let ledger = load_owned_ledger(db.as_ref(), user_id, &ledger_id).await?;
+    let member_id = ObjectId::parse_str(&member_id)?;
+    if !db.set_member_role(ledger.id, member_id, payload.role).await? {
+        return Err(AppError::not_found("成员不存在"));
+    }
+    Ok(Json(true))
+}
+
+/// 所有者移除成员，或成员自己退出；所有者不能退出自己的账本
+pub async fn remove_member_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthSession { user_id, .. }: AuthSession,
+    AppPath((ledger_id, member_id)): AppPath<(String, String)>,
+) -> Result<Json<bool>, AppError> {
+    let member_id = ObjectId::parse_str(&member_id)?;
+    let (ledger, role) = load_ledger(db.as_ref(), user_id, &ledger_id).await?;
+    if member_id != user_id && role != Role::Owner {
+        return Err(AppError::forbidden("只有账本所有者可以管理成员"));
+    }
+    if ledger.role_of(member_id) == Some(Role::Owner) {
+        return Err(AppError::validation("所有者不能退出自己的账本"));
+    }
+    if !db.remove_member(ledger.id, member_id).await? {
+        return Err(AppError::not_found("成员不存在"));
+    }
+    tracing::info!("[remove_member_handler] ledger_id: {:?}, removed: {:?}", ledger.id, member_id);
+    Ok(Json(true))
+}
+
+pub async fn my_invitations_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthUser(user_id): AuthUser,
+) -> Result<Json<Vec<InvitationOut>>, AppError> {
+    let mut result = Vec::new();
+    for invitation in db.get_invitations_for_user(user_id).await? {
+        result.push(invitation_out(db.as_ref(), &invitation).await?);
+    }
+    Ok(Json(result))
+}
+
+// 被邀请人读取发给自己的邀请
+async fn own_invitation(db: &dyn LedgerStore, user_id: ObjectId, invitation_id: &str) -> Result<Invitation, AppError> {
+    let invitation_id = ObjectId::parse_str(invitation_id)?;
+    db.get_invitation(invitation_id).await?
+        .filter(|i| i.invitee_id == user_id)
+        .ok_or(AppError::not_found("邀请不存在"))
+}
+
+pub async fn accept_invitation_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthSession { user_id, .. }: AuthSession,
+    AppPath(invitation_id): AppPath<String>,
+) -> Result<Json<LedgerOut>, AppError> {
+    let invitation = own_invitation(db.as_ref(), user_id, &invitation_id).await?;
+    // 加入账本成功后才删除邀请，账本已删除或已是成员时邀请保持不变
+    let ledger = db.accept_invitation(invitation.id).await?;
+    tracing::info!("[accept_invitation_handler] user_id: {:?}, ledger_id: {:?}", user_id, ledger.id);
+    Ok(Json(ledger_out(&ledger, invitation.role, false)))
+}
+
+pub async fn decline_invitation_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthSession { user_id, .. }: AuthSession,
+    AppPath(invitation_id): AppPath<String>,
+) -> Result<Json<bool>, AppError> {
+    let invitation = own_invitation(db.as_ref(), user_id, &invitation_id).await?;
+    db.delete_invitation(invitation.id).await?;
+    Ok(Json(true))
+}
+
+pub fn ledger_routes() -> Router<AppState> {
+    Router::new()
+        .route("/ledgers", get(list_ledgers_handler).post(create_ledger_handler))
+        .route("/ledgers/{id}", get(get_ledger_handler).patch(rename_ledger_handler).delete(delete_ledger_handler))
+        .route("/default", put(set_default_ledger_handler))
+        .route("/ledgers/{id}/invitations", get(ledger_invitations_handler).post(invite_handler))
+        .route("/ledgers/{id}/members/{user_id}", put(set_member_role_handler).delete(remove_member_handler))
+        .route("/invitations", get(my_invitations_handler))
+        .route("/invitations/{id}/accept", post(accept_invitation_handler))
+        .route("/invitations/{id}/decline", post(decline_invitation_handler))
+}
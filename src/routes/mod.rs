@@ -5,6 +5,8 @@ pub mod asset;
 pub mod transaction;
 pub mod budget;
 pub mod user;
-pub mod order;
-pub mod order_query;
-pub mod order_delete;
\ No newline at end of file
+pub mod two_factor;
+pub mod orders;
+pub mod rate;
+pub mod ledger;
+pub mod notification;
\ No newline at end of file
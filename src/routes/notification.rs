@@ -0,0 +1,81 @@
+use axum::{Router, Json, routing::{get, post}, extract::State};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use crate::auth::AuthUser;
+use crate::db::LedgerStore;
+use crate::extract::{AppPath, AppQuery};
+use crate::error::AppError;
+use crate::models::notification::{Notification, NotificationKind};
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationQuery {
+    #[serde(default)]
+    pub unread: bool, // 只返回未读通知
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationOut {
+    pub id: String,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub message: String,
+    pub ledger_id: String,
+    pub budget_id: Option<String>,
+    pub read: bool,
+    pub created_at: String,
+}
+
+impl From<&Notification> for NotificationOut {
+    fn from(n: &Notification) -> Self {
+        NotificationOut {
+            id: n.id.to_hex(),
+            kind: n.kind,
+            title: n.title.clone(),
+            message: n.message.clone(),
+            ledger_id: n.ledger_id.to_hex(),
+            budget_id: n.budget_id.map(|id| id.to_hex()),
+            read: n.read,
+            created_at: n.created_at.try_to_rfc3339_string().unwrap_or_default(),
+        }
+    }
+}
+
+async fn list_notifications_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthUser(user_id): AuthUser,
+    AppQuery(query): AppQuery<NotificationQuery>,
+) -> Result<Json<Vec<NotificationOut>>, AppError> {
+    let notifications = db.get_notifications_for_user(user_id, query.unread).await?;
+    tracing::info!("[list_notifications_handler] user_id: {:?}, count: {}", user_id, notifications.len());
+    Ok(Json(notifications.iter().map(NotificationOut::from).collect()))
+}
+
+async fn mark_read_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthUser(user_id): AuthUser,
+    AppPath(notification_id): AppPath<String>,
+) -> Result<Json<NotificationOut>, AppError> {
+    let notification_id = ObjectId::parse_str(&notification_id)?;
+    let notification = db.mark_notification_read(user_id, notification_id).await?
+        .ok_or(AppError::not_found("通知不存在"))?;
+    Ok(Json(NotificationOut::from(&notification)))
+}
+
+// 返回标记为已读的数量
+async fn mark_all_read_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthUser(user_id): AuthUser,
+) -> Result<Json<u64>, AppError> {
+    let count = db.mark_all_notifications_read(user_id).await?;
+    tracing::info!("[mark_all_read_handler] user_id: {:?}, count: {}", user_id, count);
+    Ok(Json(count))
+}
+
+pub fn notification_routes() -> Router<AppState> {
+    Router::new()
+        .route("/notifications", get(list_notifications_handler))
+        .route("/notifications/{id}/read", post(mark_read_handler))
+        .route("/notifications/read_all", post(mark_all_read_handler))
+}
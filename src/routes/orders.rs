@@ -0,0 +1,387 @@
+use axum::{extract::State, Json, Router, routing::{get, post, delete}, http::{header, HeaderValue, StatusCode}, middleware, response::Response};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::state::AppState;
+use crate::db::{LedgerStore, OrderFilter, OrderPage, OrderSort};
+use crate::auth::LedgerMember;
+use crate::models::category::{order_type_for, subtree_ids};
+use crate::models::transaction::{Order, TransferLink};
+use crate::models::money::Money;
+use crate::models::currency::Currency;
+use crate::models::exchange_rate::MissingRate;
+use crate::extract::{AppJson, AppPath, AppQuery};
+use crate::error::AppError;
+use crate::notify::Notifier;
+use crate::services::load_rates;
+use crate::util::parse_date;
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+/// 创建订单的唯一请求体，新旧接口共用
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateOrder {
+    pub account_id: Option<String>,
+    pub category_id: Option<String>,
+    pub name: String,
+    #[serde(alias = "type")]
+    pub order_type: String, // 消费/收入/转账
+    pub amount: f64,
+    pub currency: Currency,
+    pub date: String,       // RFC3339 或 YYYY-MM-DD
+    pub remark: Option<String>,
+}
+
+/// 订单的对外表示
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderOut {
+    pub id: String,
+    pub account_id: Option<String>,
+    pub category_id: Option<String>,
+    pub name: String,
+    pub order_type: String,
+    pub amount: Money,
+    pub date: String,
+    pub remark: Option<String>,
+    pub transfer: Option<TransferLink>,
+}
+
+impl From<&Order> for OrderOut {
+    fn from(o: &Order) -> Self {
+        OrderOut {
+            id: o.id.to_hex(),
+            account_id: o.account_id.map(|id| id.to_hex()),
+            category_id: o.category_id.map(|id| id.to_hex()),
+            name: o.name.clone(),
+            order_type: o.order_type.clone(),
+            amount: o.amount,
+            date: o.date.try_to_rfc3339_string().unwrap_or_default(),
+            remark: o.remark.clone(),
+            transfer: o.transfer.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrderQuery {
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+    pub account_id: Option<String>,
+    pub name: Option<String>,
+    pub order_type: Option<String>,
+    pub category_id: Option<String>,       // 逗号分隔的分类ID，属于其中任一分类即可
+    pub include_children: Option<bool>,    // 是否包含子分类的订单，默认包含
+    pub date_start: Option<String>,
+    pub date_end: Option<String>,
+    pub sort: Option<String>,   // "-date"（默认，最新在前）或 "date"
+    pub cursor: Option<String>, // 上一页返回的 next_cursor，给定时忽略 page
+}
+
+/// 某个分类下某类订单的合计（本位币），`category_id` 为空表示未分类
+#[derive(Debug, Serialize)]
+pub struct CategoryStat {
+    pub category_id: Option<String>,
+    pub name: Option<String>,
+    pub order_type: String,
+    pub amount: Money,
+}
+
+fn parse_id(id: &str) -> Result<ObjectId, AppError> {
+    ObjectId::parse_str(id).map_err(|_| AppError::validation(format!("无效的ID: {}", id)))
+}
+
+// 游标格式：`<日期毫秒>_<订单ID>`
+fn encode_cursor(date: DateTime, id: ObjectId) -> String {
+    format!("{}_{}", date.timestamp_millis(), id.to_hex())
+}
+
+fn decode_cursor(cursor: &str) -> Result<(DateTime, ObjectId), AppError> {
+    let invalid = || AppError::validation("无效的分页游标");
+    let (millis, id) = cursor.split_once('_').ok_or_else(invalid)?;
+    let millis = millis.parse().map_err(|_| invalid())?;
+    let id = ObjectId::parse_str(id).map_err(|_| invalid())?;
+    Ok((DateTime::from_millis(millis), id))
+}
+
+/// 校验并创建订单，所有创建入口都走这里；创建后检查预算提醒
+pub async fn create_order(
+    db: &Arc<dyn LedgerStore>,
+    notifier: &Arc<Notifier>,
+    ledger_id: ObjectId,
+    user_id: ObjectId,
+    payload: CreateOrder,
+) -> Result<Order, AppError> {
+    tracing::info!("[create_order] payload: {:?}", payload);
+    // 校验类型（币种在反序列化时已校验）
+    let allowed_types = ["消费", "收入", "转账"];
+    if !allowed_types.contains(&payload.order_type.as_str()) {
+        return Err(AppError::validation("类型不合法"));
+    }
+    let amount = Money::from_major(payload.amount, payload.currency)?;
+    if amount.minor < 0 {
+        return Err(AppError::validation("金额不合法"));
+    }
+    let date = parse_date(&payload.date)?;
+    let account_id = payload.account_id.as_deref().map(parse_id).transpose()?;
+    if let Some(account_id) = account_id {
+        if payload.order_type == "转账" {
+            return Err(AppError::validation("账户间转账请使用 /transaction/transfers"));
+        }
+        let account = db.get_account(ledger_id, account_id).await?.ok_or(AppError::not_found("账户不存在"))?;
+        if account.balance.currency != payload.currency {
+            return Err(AppError::validation("订单币种与账户币种不一致"));
+        }
+    }
+    let category_id = payload.category_id.as_deref().map(parse_id).transpose()?;
+    if let Some(category_id) = category_id {
+        let category = db.get_category(ledger_id, category_id).await?.ok_or(AppError::not_found("分类不存在"))?;
+        if order_type_for(&category.category_type) != Some(payload.order_type.as_str()) {
+            return Err(AppError::validation(format!(
+                "{}订单不能使用{}分类「{}」", payload.order_type, category.category_type, category.name,
+            )));
+        }
+    }
+    // 可能触发预算提醒的订单带上待检查标记一起写入，检查失败或中途退出时由后台补做
+    let alerts_pending = payload.order_type == "消费" && category_id.is_some();
+    let mut order = db.create_order(Order {
+        id: ObjectId::new(),
+        ledger_id,
+        user_id,
+        account_id,
+        category_id,
+        name: payload.name,
+        order_type: payload.order_type,
+        amount,
+        date,
+        remark: payload.remark,
+        transfer: None,
+        alerts_pending,
+    }).await?;
+    tracing::info!("[create_order] db_order: {:?}", order.id);
+    // 订单已经写入，提醒失败不影响创建结果
+    if order.alerts_pending {
+        match notifier.budget_alerts(db.as_ref(), &order).await {
+            Ok(created) => {
+                order.alerts_pending = false;
+                if created > 0 {
+                    notifier.spawn_flush(db.clone());
+                }
+            }
+            Err(e) => tracing::warn!("[create_order] 预算提醒检查失败，将由后台重试: {}", e),
+        }
+    }
+    Ok(order)
+}
+
+/// 删除订单（转账会连同另一半一起删除），不存在或不在当前账本时返回 404
+pub async fn delete_order(db: &dyn LedgerStore, ledger_id: ObjectId, order_id: &str) -> Result<(), AppError> {
+    let order_id = parse_id(order_id)?;
+    if !db.delete_order(ledger_id, order_id).await? {
+        return Err(AppError::not_found("订单不存在"));
+    }
+    tracing::info!("[delete_order] deleted: {:?}", order_id);
+    Ok(())
+}
+
+pub async fn create_order_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    State(notifier): State<Arc<Notifier>>,
+    LedgerMember { user_id, ledger_id, .. }: LedgerMember,
+    AppJson(payload): AppJson<CreateOrder>,
+) -> Result<(StatusCode, Json<OrderOut>), AppError> {
+    let order = create_order(&db, &notifier, ledger_id, user_id, payload).await?;
+    Ok((StatusCode::CREATED, Json(OrderOut::from(&order))))
+}
+
+pub async fn get_order_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(order_id): AppPath<String>,
+) -> Result<Json<OrderOut>, AppError> {
+    let order = db.get_order(ledger_id, parse_id(&order_id)?).await?.ok_or(AppError::not_found("订单不存在"))?;
+    Ok(Json(OrderOut::from(&order)))
+}
+
+pub async fn delete_order_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(order_id): AppPath<String>,
+) -> Result<Json<bool>, AppError> {
+    delete_order(db.as_ref(), ledger_id, &order_id).await?;
+    Ok(Json(true))
+}
+
+/// 订单列表：筛选、分页（页码或游标）以及按类型、按分类的本位币统计
+pub async fn query_orders_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { user_id, ledger_id, .. }: LedgerMember,
+    AppQuery(query): AppQuery<OrderQuery>,
+) -> Result<Json<HashMap<&'static str, serde_json::Value>>, AppError> {
+    // 筛选
+    let categories = db.get_categories_by_ledger(ledger_id).await?;
+    let category_ids = match query.category_id.as_deref().filter(|s| !s.is_empty()) {
+        Some(ids) => {
+            let mut selected = Vec::new();
+            for id in ids.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+                let id = parse_id(id)?;
+                if !categories.iter().any(|c| c.id == id) {
+                    return Err(AppError::not_found("分类不存在"));
+                }
+                if query.include_children.unwrap_or(true) {
+                    selected.extend(subtree_ids(&categories, id));
+                } else {
+                    selected.push(id);
+                }
+            }
+            Some(selected)
+        }
+        None => None,
+    };
+    let filter = OrderFilter {
+        account_id: query.account_id.as_deref().map(parse_id).transpose()?,
+        category_ids,
+        name: query.name.filter(|n| !n.is_empty()),
+        order_type: query.order_type.filter(|t| !t.is_empty()),
+        date_start: query.date_start.as_deref().filter(|s| !s.is_empty()).map(parse_date).transpose()?,
+        date_end: query.date_end.as_deref().filter(|s| !s.is_empty()).map(parse_date).transpose()?,
+    };
+    // 分页
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(8).clamp(1, 200);
+    let sort = match query.sort.as_deref() {
+        Some("date") => OrderSort::DateAsc,
+        _ => OrderSort::DateDesc,
+    };
+    // 跳过的条数需在数据库接受的 i64 范围内
+    let skip = (page - 1).checked_mul(page_size).filter(|s| i64::try_from(*s).is_ok())
+        .ok_or_else(|| AppError::validation("页码超出范围"))?;
+    let page_req = OrderPage {
+        sort,
+        after: query.cursor.as_deref().map(decode_cursor).transpose()?,
+        skip: skip as u64,
+        limit: page_size as i64,
+    };
+    let total = db.count_orders(ledger_id, &filter).await?;
+    let db_orders = db.find_orders(ledger_id, &filter, &page_req).await?;
+    let next_cursor = match db_orders.last() {
+        Some(last) if db_orders.len() == page_size => Some(encode_cursor(last.date, last.id)),
+        _ => None,
+    };
+    let page_orders = db_orders.iter().map(OrderOut::from).collect::<Vec<_>>();
+    // 分类统计（数据库按类型/币种/日期分组求和，再按当日汇率换算成本位币）
+    let (base, rates) = load_rates(db.as_ref(), user_id).await?;
+    let mut stat: HashMap<String, Money> = HashMap::new();
+    let mut by_category: HashMap<(Option<ObjectId>, String), Money> = HashMap::new();
+    // 缺少汇率的金额不计入统计，在 missing_rates 中列出，不影响订单列表本身
+    let mut missing_rates: Vec<MissingRate> = Vec::new();
+    for t in db.order_totals(ledger_id, &filter, Some(base)).await? {
+        let Some(converted) = rates.convert_or_record(t.amount, base, t.date, &mut missing_rates) else {
+            continue;
+        };
+        stat.entry(t.order_type.clone()).or_insert(Money::zero(base)).accumulate(converted.minor)?;
+        by_category.entry((t.category_id, t.order_type)).or_insert(Money::zero(base)).accumulate(converted.minor)?;
+    }
+    // 按分类的统计，不汇总子分类，金额大的在前
+    let mut category_stat: Vec<CategoryStat> = by_category.into_iter()
+        .map(|((category_id, order_type), amount)| CategoryStat {
+            category_id: category_id.map(|id| id.to_hex()),
+            name: category_id.and_then(|id| categories.iter().find(|c| c.id == id)).map(|c| c.name.clone()),
+            order_type,
+            amount,
+        })
+        .collect();
+    category_stat.sort_by(|a, b| b.amount.minor.cmp(&a.amount.minor)
+        .then_with(|| a.order_type.cmp(&b.order_type))
+        .then_with(|| a.category_id.cmp(&b.category_id)));
+    let mut result = HashMap::new();
+    result.insert("total", serde_json::json!(total));
+    result.insert("orders", serde_json::to_value(page_orders).unwrap());
+    result.insert("stat", serde_json::to_value(stat).unwrap());
+    result.insert("category_stat", serde_json::to_value(category_stat).unwrap());
+    result.insert("missing_rates", serde_json::to_value(missing_rates).unwrap());
+    result.insert("base_currency", serde_json::json!(base));
+    result.insert("next_cursor", serde_json::json!(next_cursor));
+    Ok(Json(result))
+}
+
+pub fn orders_routes() -> Router<AppState> {
+    tracing::info!("[orders_routes] 订单路由已注册 /v1/orders");
+    Router::new()
+        .route("/", post(create_order_handler).get(query_orders_handler))
+        .route("/{id}", get(get_order_handler).delete(delete_order_handler))
+}
+
+// ---- 以下为已废弃的旧接口，保留原有响应格式，内部复用上面的校验和存储逻辑 ----
+
+// 给旧接口的响应加上废弃标记，并指向新接口
+async fn deprecated(mut res: Response) -> Response {
+    let headers = res.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+    headers.insert(header::LINK, HeaderValue::from_static("</api/v1/orders>; rel=\"successor-version\""));
+    res
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteOrderPayload {
+    pub id: String,
+}
+
+// POST /transaction/orders 与 POST /order/
+async fn legacy_create_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    State(notifier): State<Arc<Notifier>>,
+    LedgerMember { user_id, ledger_id, .. }: LedgerMember,
+    AppJson(payload): AppJson<CreateOrder>,
+) -> Result<Json<Order>, AppError> {
+    Ok(Json(create_order(&db, &notifier, ledger_id, user_id, payload).await?))
+}
+
+// GET /transaction/orders：不分页的全部订单
+async fn legacy_list_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+) -> Result<Json<Vec<Order>>, AppError> {
+    Ok(Json(db.get_orders_by_ledger(ledger_id).await?))
+}
+
+// POST /transaction/orders/delete
+async fn legacy_delete_by_body_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppJson(payload): AppJson<DeleteOrderPayload>,
+) -> Result<Json<bool>, AppError> {
+    delete_order(db.as_ref(), ledger_id, &payload.id).await?;
+    Ok(Json(true))
+}
+
+// DELETE /order/{id}：成功时响应体保持 {"success": true}
+async fn legacy_delete_by_path_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { ledger_id, .. }: LedgerMember,
+    AppPath(order_id): AppPath<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    delete_order(db.as_ref(), ledger_id, &order_id).await?;
+    Ok(Json(serde_json::json!({"success": true})))
+}
+
+/// `/transaction` 下的旧订单接口
+pub fn legacy_transaction_routes() -> Router<AppState> {
+    Router::new()
+        .route("/orders", post(legacy_create_handler).get(legacy_list_handler))
+        .route("/orders/delete", post(legacy_delete_by_body_handler))
+        .route_layer(middleware::map_response(deprecated))
+}
+
+/// `/order` 下的旧订单接口
+pub fn legacy_order_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", post(legacy_create_handler))
+        .route("/{id}", delete(legacy_delete_by_path_handler))
+        .route_layer(middleware::map_response(deprecated))
+}
+
+/// `/order_query` 下的旧查询接口，与 `GET /v1/orders` 相同
+pub fn legacy_query_routes() -> Router<AppState> {
+    Router::new()
+        .route("/orders/query", get(query_orders_handler))
+        .route_layer(middleware::map_response(deprecated))
+}
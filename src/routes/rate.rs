@@ -0,0 +1,94 @@
+use axum::{extract::State, Json, Router, routing::post};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use crate::state::AppState;
+use crate::db::LedgerStore;
+use crate::auth::AuthUser;
+use crate::models::currency::Currency;
+use crate::models::exchange_rate::ExchangeRate;
+use crate::extract::AppJson;
+use crate::error::AppError;
+use crate::util::parse_date;
+use mongodb::bson::oid::ObjectId;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRate {
+    pub from: Currency,
+    pub to: Currency,
+    pub rate: f64,
+    pub date: String, // RFC3339 或 YYYY-MM-DD
+}
+
+fn build_rate(user_id: ObjectId, payload: CreateRate) -> Result<ExchangeRate, AppError> {
+    if payload.from == payload.to {
+        return Err(AppError::validation("源币种和目标币种不能相同"));
+    }
+    if !(payload.rate.is_finite() && payload.rate > 0.0) {
+        return Err(AppError::validation("汇率不合法"));
+    }
+    Ok(ExchangeRate {
+        id: ObjectId::new(),
+        user_id,
+        from: payload.from,
+        to: payload.to,
+        rate: payload.rate,
+        date: parse_date(&payload.date)?,
+    })
+}
+
+pub async fn create_rate_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthUser(user_id): AuthUser,
+    AppJson(payload): AppJson<CreateRate>,
+) -> Result<Json<ExchangeRate>, AppError> {
+    tracing::info!("[create_rate_handler] payload: {:?}", payload);
+    let rate = build_rate(user_id, payload)?;
+    let mut rates = db.create_rates(vec![rate]).await?;
+    Ok(Json(rates.remove(0)))
+}
+
+pub async fn get_rates_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthUser(user_id): AuthUser,
+) -> Result<Json<Vec<ExchangeRate>>, AppError> {
+    let mut rates = db.get_rates_by_user(user_id).await?;
+    rates.sort_by_key(|r| r.date);
+    Ok(Json(rates))
+}
+
+// 导入 CSV：每行 `date,from,to,rate`，允许带表头，忽略空行
+pub async fn import_rates_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    AuthUser(user_id): AuthUser,
+    body: String,
+) -> Result<Json<Vec<ExchangeRate>>, AppError> {
+    let mut rates = Vec::new();
+    for (lineno, line) in body.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (lineno == 0 && line.to_ascii_lowercase().starts_with("date")) {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [date, from, to, rate] = fields[..] else {
+            return Err(AppError::validation(format!("第 {} 行格式不合法，应为 date,from,to,rate", lineno + 1)));
+        };
+        let row_err = |e: &dyn std::fmt::Display| AppError::validation(format!("第 {} 行: {}", lineno + 1, e));
+        let payload = CreateRate {
+            from: from.parse().map_err(|e| row_err(&e))?,
+            to: to.parse().map_err(|e| row_err(&e))?,
+            rate: rate.parse().map_err(|e| row_err(&e))?,
+            date: date.to_string(),
+        };
+        rates.push(build_rate(user_id, payload).map_err(|e| row_err(&e))?);
+    }
+    tracing::info!("[import_rates_handler] 导入汇率 {} 条", rates.len());
+    let rates = db.create_rates(rates).await?;
+    Ok(Json(rates))
+}
+
+pub fn rate_routes() -> Router<AppState> {
+    tracing::info!("[rate_routes] 汇率路由已注册 /rates");
+    Router::new()
+        .route("/rates", post(create_rate_handler).get(get_rates_handler))
+        .route("/rates/import", post(import_rates_handler))
+}
@@ -1,74 +1,111 @@
 use axum::{extract::State, Json, Router, routing::post};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use crate::db::MongoDB;
-use crate::auth::AuthUser;
-use crate::models::transaction::Order;
-use crate::routes::account::ApiError;
+use crate::state::AppState;
+use crate::db::LedgerStore;
+use crate::auth::LedgerMember;
+use crate::models::transaction::{Order, TransferDirection, TransferLink};
+use crate::models::money::{Money, MAX_MINOR};
+use mongodb::bson::oid::ObjectId;
+use crate::extract::AppJson;
+use crate::error::AppError;
+use crate::util::parse_date;
 
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct CreateOrder {
-    pub name: String,
-    pub order_type: String, // 消费/收入/转账
-    pub amount: f64,
-    pub currency: String,   // 币种
-    pub date: String,       // 日期字符串
+pub struct CreateTransfer {
+    pub from_account_id: String,
+    pub to_account_id: String,
+    pub amount: f64,                 // 转出金额（转出账户币种）
+    pub exchange_rate: Option<f64>,  // 跨币种时必填：1 单位转出币种 = exchange_rate 单位转入币种
+    pub fee: Option<f64>,            // 手续费（转出账户币种）
+    pub date: String,
     pub remark: Option<String>,
 }
 
-pub async fn create_order_handler(
-    State(db): State<Arc<MongoDB>>,
-    AuthUser(user_id): AuthUser,
-    Json(payload): Json<CreateOrder>,
-) -> Result<Json<Order>, ApiError> {
-    // 校验类型和币种
-    let allowed_types = ["消费", "收入", "转账"];
-    let allowed_currencies = ["人民币", "美元", "欧元"];
-    if !allowed_types.contains(&payload.order_type.as_str()) {
-        return Err(ApiError { message: "类型不合法".to_string() });
-    }
-    if !allowed_currencies.contains(&payload.currency.as_str()) {
-        return Err(ApiError { message: "币种不合法".to_string() });
-    }
-    let date = mongodb::bson::DateTime::parse_rfc3339_str(&payload.date).map_err(|e| ApiError { message: e.to_string() })?;
-    let order = db.create_order(
-        user_id,
-        payload.name,
-        payload.order_type,
-        payload.amount,
-        payload.currency,
-        date,
-        payload.remark,
-    ).await?;
-    Ok(Json(order))
+#[derive(Debug, Serialize)]
+pub struct TransferResponse {
+    pub outgoing: Order,
+    pub incoming: Order,
 }
 
-pub async fn get_orders_handler(
-    State(db): State<Arc<MongoDB>>,
-    AuthUser(user_id): AuthUser,
-) -> Result<Json<Vec<Order>>, ApiError> {
-    let orders = db.get_orders_by_user(user_id).await?;
-    Ok(Json(orders))
-}
+pub async fn create_transfer_handler(
+    State(db): State<Arc<dyn LedgerStore>>,
+    LedgerMember { user_id, ledger_id, .. }: LedgerMember,
+    AppJson(payload): AppJson<CreateTransfer>,
+) -> Result<Json<TransferResponse>, AppError> {
+    tracing::info!("[create_transfer_handler] payload: {:?}", payload);
+    let from_id = ObjectId::parse_str(&payload.from_account_id)?;
+    let to_id = ObjectId::parse_str(&payload.to_account_id)?;
+    if from_id == to_id {
+        return Err(AppError::validation("转出和转入账户不能相同"));
+    }
+    let date = parse_date(&payload.date)?;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct DeleteOrderPayload {
-    pub id: String,
-}
+    let accounts = db.get_accounts_by_ledger(ledger_id).await?;
+    let from = accounts.iter().find(|a| a.id == from_id).ok_or(AppError::not_found("转出账户不存在"))?;
+    let to = accounts.iter().find(|a| a.id == to_id).ok_or(AppError::not_found("转入账户不存在"))?;
+    let same_currency = from.balance.currency == to.balance.currency;
+    let exchange_rate = match payload.exchange_rate {
+        // 同币种转账只能按 1:1，否则转入金额会凭空多出或少掉
+        Some(rate) if same_currency && rate != 1.0 => return Err(AppError::validation("同币种转账的汇率只能是 1")),
+        Some(rate) if rate.is_finite() && rate > 0.0 => rate,
+        Some(_) => return Err(AppError::validation("汇率不合法")),
+        None if same_currency => 1.0,
+        None => return Err(AppError::validation("跨币种转账需提供汇率")),
+    };
 
-pub async fn delete_order_handler(
-    State(db): State<Arc<MongoDB>>,
-    AuthUser(user_id): AuthUser,
-    Json(payload): Json<DeleteOrderPayload>,
-) -> Result<Json<bool>, ApiError> {
-    let order_id = mongodb::bson::oid::ObjectId::parse_str(&payload.id).map_err(|e| ApiError { message: e.to_string() })?;
-    let deleted = db.delete_order(user_id, order_id).await?;
-    Ok(Json(deleted))
+    // 金额和手续费按转出账户币种换算，与订单金额使用相同的范围校验
+    let amount = Money::from_major(payload.amount, from.balance.currency)?;
+    let fee = Money::from_major(payload.fee.unwrap_or(0.0), from.balance.currency)?;
+    if amount.minor <= 0 || fee.minor < 0 {
+        return Err(AppError::validation("金额不合法"));
+    }
+    let received = amount.convert(exchange_rate, to.balance.currency);
+    if received.minor > MAX_MINOR {
+        return Err(AppError::validation("转入金额超出范围"));
+    }
+    if received.minor <= 0 {
+        return Err(AppError::validation("按该汇率换算后的转入金额为零"));
+    }
+    let transfer_id = ObjectId::new();
+    let (out_id, in_id) = (ObjectId::new(), ObjectId::new());
+    let name = format!("{} → {}", from.name, to.name);
+    let outgoing = Order {
+        id: out_id,
+        ledger_id,
+        user_id,
+        account_id: Some(from_id),
+        category_id: None,
+        name: name.clone(),
+        order_type: "转账".to_string(),
+        amount,
+        date,
+        remark: payload.remark.clone(),
+        transfer: Some(TransferLink { transfer_id, peer_order_id: in_id, direction: TransferDirection::Out, exchange_rate, fee }),
+        alerts_pending: false,
+    };
+    let incoming = Order {
+        id: in_id,
+        ledger_id,
+        user_id,
+        account_id: Some(to_id),
+        category_id: None,
+        name,
+        order_type: "转账".to_string(),
+        amount: received,
+        date,
+        remark: payload.remark,
+        transfer: Some(TransferLink { transfer_id, peer_order_id: out_id, direction: TransferDirection::In, exchange_rate, fee: Money::zero(to.balance.currency) }),
+        alerts_pending: false,
+    };
+    let (outgoing, incoming) = db.create_transfer(outgoing, incoming).await?;
+    tracing::info!("[create_transfer_handler] transfer_id: {:?}", transfer_id);
+    Ok(Json(TransferResponse { outgoing, incoming }))
 }
 
-pub fn order_routes() -> Router<Arc<MongoDB>> {
+pub fn order_routes() -> Router<AppState> {
     Router::new()
-        .route("/orders", post(create_order_handler).get(get_orders_handler))
-        .route("/orders/delete", post(delete_order_handler))
+        .merge(crate::routes::orders::legacy_transaction_routes())
+        .route("/transfers", post(create_transfer_handler))
 }
@@ -0,0 +1,172 @@
+use axum::{Router, Json, routing::post, extract::State};
+use mongodb::bson::DateTime;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use crate::auth::{decode_challenge, hash_token, start_session, AuthSession};
+use crate::config::Config;
+use crate::db::LedgerStore;
+use crate::extract::AppJson;
+use crate::error::AppError;
+use crate::models::login_attempt::LoginAttempt;
+use crate::models::user::{TwoFactor, User};
+use crate::routes::user::{check_password, ensure_not_locked, record_failures, TokenResponse};
+use crate::state::AppState;
+use crate::totp;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+#[derive(Serialize)]
+pub struct Enrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Deserialize)]
+pub struct CodePayload {
+    pub code: String,
+}
+
+#[derive(Serialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>, // 明文只在启用时返回一次
+}
+
+#[derive(Deserialize)]
+pub struct DisablePayload {
+    pub password: String,
+    pub code: String, // 验证码或恢复码
+}
+
+#[derive(Deserialize)]
+pub struct VerifyPayload {
+    pub challenge_token: String,
+    pub code: String, // 验证码或恢复码
+}
+
+pub fn two_factor_routes() -> Router<AppState> {
+    Router::new()
+        .route("/enroll", post(enroll))
+        .route("/confirm", post(confirm))
+        .route("/disable", post(disable))
+        .route("/verify", post(verify))
+}
+
+fn now_secs() -> i64 {
+    DateTime::now().timestamp_millis() / 1000
+}
+
+// 恢复码格式 `xxxx-xxxx`，比较前去掉空白和连字符并转小写
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_lowercase()
+}
+
+fn new_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 4];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            let code = hex::encode(bytes);
+            format!("{}-{}", &code[..4], &code[4..])
+        })
+        .collect()
+}
+
+async fn load_user(db: &dyn LedgerStore, user_id: mongodb::bson::oid::ObjectId) -> Result<User, AppError> {
+    db.get_user(user_id).await?.ok_or(AppError::not_found("用户不存在"))
+}
+
+/// 校验已启用的第二因素：6 位验证码（不可重放）或一次性恢复码
+async fn check_second_factor(db: &dyn LedgerStore, user: &User, code: &str) -> Result<bool, AppError> {
+    let Some(two_factor) = user.two_factor.as_ref().filter(|t| t.enabled) else {
+        return Ok(false);
+    };
+    if let Some(step) = totp::verify(&two_factor.secret, code, now_secs()) {
+        return Ok(db.use_totp_step(user.id, step).await?);
+    }
+    Ok(db.use_recovery_code(user.id, &hash_token(&normalize_recovery_code(code))).await?)
+}
+
+// 生成新密钥，确认前不生效；重复调用会替换尚未确认的密钥
+async fn enroll(
+    State(db): State<Arc<dyn LedgerStore>>,
+    State(config): State<Arc<Config>>,
+    session: AuthSession,
+) -> Result<Json<Enrollment>, AppError> {
+    let user = load_user(db.as_ref(), session.user_id).await?;
+    if user.two_factor_enabled() {
+        return Err(AppError::conflict("两步验证已开启，请先关闭"));
+    }
+    let secret = totp::generate_secret();
+    db.set_two_factor(user.id, Some(TwoFactor {
+        secret: secret.clone(),
+        enabled: false,
+        recovery_hashes: Vec::new(),
+        last_step: 0,
+    })).await?;
+    let otpauth_uri = totp::otpauth_uri(&config.auth.two_factor_issuer, &user.username, &secret);
+    Ok(Json(Enrollment { secret, otpauth_uri }))
+}
+
+// 用验证器上的验证码确认后正式启用，并返回恢复码
+async fn confirm(
+    State(db): State<Arc<dyn LedgerStore>>,
+    session: AuthSession,
+    AppJson(payload): AppJson<CodePayload>,
+) -> Result<Json<RecoveryCodes>, AppError> {
+    let user = load_user(db.as_ref(), session.user_id).await?;
+    let Some(pending) = user.two_factor.filter(|t| !t.enabled) else {
+        return Err(AppError::validation("请先调用 /user/2fa/enroll 生成密钥"));
+    };
+    let Some(step) = totp::verify(&pending.secret, &payload.code, now_secs()) else {
+        return Err(AppError::validation("验证码错误"));
+    };
+    let recovery_codes = new_recovery_codes();
+    db.set_two_factor(user.id, Some(TwoFactor {
+        secret: pending.secret,
+        enabled: true,
+        recovery_hashes: recovery_codes.iter().map(|c| hash_token(&normalize_recovery_code(c))).collect(),
+        last_step: step,
+    })).await?;
+    tracing::info!("[2fa_confirm] user_id: {:?}", user.id);
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
+
+async fn disable(
+    State(db): State<Arc<dyn LedgerStore>>,
+    session: AuthSession,
+    AppJson(payload): AppJson<DisablePayload>,
+) -> Result<Json<bool>, AppError> {
+    let user = check_password(db.as_ref(), session.user_id, &payload.password).await?;
+    if !check_second_factor(db.as_ref(), &user, &payload.code).await? {
+        return Err(AppError::unauthorized("验证码错误"));
+    }
+    db.set_two_factor(user.id, None).await?;
+    tracing::info!("[2fa_disable] user_id: {:?}", user.id);
+    Ok(Json(true))
+}
+
+// 用登录返回的挑战令牌加验证码换取正式令牌，每个挑战令牌只能换取一次；验证码错误与密码错误共用失败计数
+async fn verify(
+    State(db): State<Arc<dyn LedgerStore>>,
+    State(config): State<Arc<Config>>,
+    AppJson(payload): AppJson<VerifyPayload>,
+) -> Result<Json<TokenResponse>, AppError> {
+    let challenge = decode_challenge(&config.auth, &payload.challenge_token)
+        .ok_or(AppError::unauthorized("挑战令牌无效或已过期，请重新登录"))?;
+    let user = load_user(db.as_ref(), challenge.user_id).await?;
+    let limit = &config.auth.login_limit;
+    let keys = [LoginAttempt::username_key(&user.username)];
+    ensure_not_locked(db.as_ref(), limit, &keys).await?;
+    if !check_second_factor(db.as_ref(), &user, &payload.code).await? {
+        record_failures(db.as_ref(), limit, &keys).await?;
+        return Err(AppError::unauthorized("验证码错误"));
+    }
+    // 验证成功才消耗挑战令牌，输错验证码可以重试
+    if !db.use_challenge(&challenge.jti, challenge.expires_at).await? {
+        return Err(AppError::unauthorized("挑战令牌已使用，请重新登录"));
+    }
+    db.clear_login_attempts(&keys[0]).await?;
+    let tokens = start_session(db.as_ref(), &config.auth, user.id).await?;
+    Ok(Json(tokens.into()))
+}
@@ -1,9 +1,21 @@
-use axum::{Router, Json, routing::post, extract::State, response::IntoResponse, http::StatusCode};
+use axum::{Router, Json, routing::{delete, get, post, put}, extract::{ConnectInfo, State}, http::HeaderMap};
 use serde::{Deserialize, Serialize};
-use mongodb::{bson::doc, Collection};
-use crate::models::user::User;
-use crate::auth::{create_jwt};
+use crate::state::AppState;
+use crate::routes::two_factor;
+use crate::models::user::{check_username, normalize_username, User, UserOut};
+use crate::models::currency::Currency;
+use crate::models::api_key::{ApiKey, ApiKeyScope};
+use crate::models::login_attempt::LoginAttempt;
+use crate::models::ledger::Ledger;
+use crate::util::{double_option, parse_date};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use crate::config::{Config, LoginLimit, PasswordPolicy};
+use crate::auth::{create_challenge, hash_token, new_api_key, refresh_session, start_session, AuthSession, AuthUser, Tokens};
+use crate::extract::{AppJson, AppPath};
+use crate::error::AppError;
 use bcrypt::{hash, verify, DEFAULT_COST};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::LazyLock;
 
 #[derive(Deserialize)]
 pub struct RegisterPayload {
@@ -19,72 +31,388 @@ pub struct LoginPayload {
 
 #[derive(Serialize)]
 pub struct TokenResponse {
-    pub token: String,
+    pub token: String,         // 访问令牌
+    pub refresh_token: String,
+    pub expires_in: i64,       // 访问令牌有效秒数
+}
+
+impl From<Tokens> for TokenResponse {
+    fn from(t: Tokens) -> Self {
+        TokenResponse { token: t.access_token, refresh_token: t.refresh_token, expires_in: t.expires_in }
+    }
+}
+
+/// 登录结果：直接签发令牌，或要求继续完成两步验证
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Tokens(TokenResponse),
+    Challenge {
+        two_factor_required: bool,
+        challenge_token: String,
+        expires_in: i64,
+    },
+}
+
+#[derive(Deserialize)]
+pub struct RefreshPayload {
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ChangePassword {
+    pub old_password: String,
+    pub new_password: String,
+}
+
+#[derive(Deserialize)]
+pub struct ChangeUsername {
+    pub username: String,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteMe {
+    pub password: String, // 注销前再次确认密码
+}
+
+#[derive(Deserialize)]
+pub struct CreateApiKey {
+    pub name: String,
+    #[serde(default)]
+    pub scope: ApiKeyScope,         // 默认只读
+    pub expires_at: Option<String>, // 不填表示永不过期
 }
 
 #[derive(Serialize)]
-pub struct ErrorMsg {
-    pub message: String,
+pub struct ApiKeyOut {
+    pub id: String,
+    pub name: String,
+    pub scope: ApiKeyScope,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>, // 明文密钥，只在创建时返回一次
+}
+
+impl From<&ApiKey> for ApiKeyOut {
+    fn from(k: &ApiKey) -> Self {
+        ApiKeyOut {
+            id: k.id.to_hex(),
+            name: k.name.clone(),
+            scope: k.scope,
+            created_at: k.created_at.try_to_rfc3339_string().unwrap_or_default(),
+            expires_at: k.expires_at.and_then(|t| t.try_to_rfc3339_string().ok()),
+            key: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Settings {
+    pub base_currency: Currency,
+    pub email: Option<String>, // 接收邮件通知的地址
+}
+
+/// 修改设置；未提供 email 时保持不变，为 null 时清空
+#[derive(Deserialize)]
+pub struct UpdateSettings {
+    pub base_currency: Currency,
+    #[serde(default, deserialize_with = "double_option")]
+    pub email: Option<Option<String>>,
 }
 
 use std::sync::Arc;
-use crate::db::MongoDB;
+use crate::db::LedgerStore;
 
-pub fn user_routes() -> Router<Arc<MongoDB>> {
+pub fn user_routes() -> Router<AppState> {
     Router::new()
         .route("/register", post(register))
         .route("/login", post(login))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout))
+        .route("/logout_all", post(logout_all))
+        .route("/settings", get(get_settings).put(update_settings))
+        .route("/me", get(me).delete(delete_me))
+        .route("/password", put(change_password))
+        .route("/username", put(change_username))
+        .route("/api_keys", get(list_api_keys).post(create_api_key))
+        .route("/api_keys/{id}", delete(delete_api_key))
+        .nest("/2fa", two_factor::two_factor_routes())
+}
+
+// 规范化并校验用户名
+fn valid_username(raw: &str) -> Result<String, AppError> {
+    let username = normalize_username(raw);
+    check_username(&username).map_err(AppError::validation)?;
+    Ok(username)
+}
+
+fn check_strength(policy: &PasswordPolicy, password: &str) -> Result<(), AppError> {
+    policy.check(password).map_err(AppError::validation)
+}
+
+async fn me(State(db): State<Arc<dyn LedgerStore>>, AuthUser(user_id): AuthUser) -> Result<Json<UserOut>, AppError> {
+    let user = db.get_user(user_id).await?.ok_or(AppError::not_found("用户不存在"))?;
+    Ok(Json(UserOut::from(&user)))
+}
+
+// 校验当前密码，账户敏感操作前调用
+pub(crate) async fn check_password(db: &dyn LedgerStore, user_id: ObjectId, password: &str) -> Result<User, AppError> {
+    let user = db.get_user(user_id).await?.ok_or(AppError::not_found("用户不存在"))?;
+    if !verify(password, &user.password)? {
+        return Err(AppError::unauthorized("密码错误"));
+    }
+    Ok(user)
+}
+
+// 修改密码后吊销所有旧会话，并为当前客户端签发新令牌
+async fn change_password(
+    State(db): State<Arc<dyn LedgerStore>>,
+    State(config): State<Arc<Config>>,
+    session: AuthSession,
+    AppJson(payload): AppJson<ChangePassword>,
+) -> Result<Json<TokenResponse>, AppError> {
+    check_strength(&config.auth.password, &payload.new_password)?;
+    check_password(db.as_ref(), session.user_id, &payload.old_password).await?;
+    db.set_password(session.user_id, hash(&payload.new_password, DEFAULT_COST)?).await?;
+    db.revoke_all_sessions(session.user_id).await?;
+    tracing::info!("[change_password] user_id: {:?}", session.user_id);
+    let tokens = start_session(db.as_ref(), &config.auth, session.user_id).await?;
+    Ok(Json(tokens.into()))
 }
 
-async fn register(State(db): State<Arc<MongoDB>>, Json(payload): Json<RegisterPayload>) -> (StatusCode, axum::Json<TokenResponse>) {
-    let users: Collection<User> = db.users_collection();
-    if users.find_one(doc! {"username": &payload.username}).await.unwrap().is_some() {
-        return (StatusCode::BAD_REQUEST, Json(TokenResponse { token: "用户名已存在".to_string() }));
+async fn change_username(
+    State(db): State<Arc<dyn LedgerStore>>,
+    session: AuthSession,
+    AppJson(payload): AppJson<ChangeUsername>,
+) -> Result<Json<UserOut>, AppError> {
+    let username = valid_username(&payload.username)?;
+    if !db.rename_user(session.user_id, username.clone()).await? {
+        return Err(AppError::not_found("用户不存在"));
     }
-    let hashed = hash(&payload.password, DEFAULT_COST).unwrap();
-    let user = User {
-        id: mongodb::bson::oid::ObjectId::new(),
-        username: payload.username,
+    tracing::info!("[change_username] user_id: {:?}, username: {}", session.user_id, username);
+    me(State(db), AuthUser(session.user_id)).await
+}
+
+// 注销账号：删除用户及其全部数据
+async fn delete_me(
+    State(db): State<Arc<dyn LedgerStore>>,
+    session: AuthSession,
+    AppJson(payload): AppJson<DeleteMe>,
+) -> Result<Json<bool>, AppError> {
+    check_password(db.as_ref(), session.user_id, &payload.password).await?;
+    db.delete_user(session.user_id).await?;
+    tracing::info!("[delete_me] user_id: {:?}", session.user_id);
+    Ok(Json(true))
+}
+
+async fn get_settings(State(db): State<Arc<dyn LedgerStore>>, AuthUser(user_id): AuthUser) -> Result<Json<Settings>, AppError> {
+    let user = db.get_user(user_id).await?.ok_or(AppError::not_found("用户不存在"))?;
+    Ok(Json(Settings { base_currency: user.base_currency, email: user.email }))
+}
+
+// 修改本位币和通知邮箱属于账号设置，只能通过登录会话，API 密钥不可调用
+async fn update_settings(
+    State(db): State<Arc<dyn LedgerStore>>,
+    session: AuthSession,
+    AppJson(payload): AppJson<UpdateSettings>,
+) -> Result<Json<Settings>, AppError> {
+    let user_id = session.user_id;
+    let email = payload.email.map(|email| email.map(|e| e.trim().to_string()));
+    if let Some(Some(email)) = &email && email.parse::<lettre::Address>().is_err() {
+        return Err(AppError::validation("邮箱格式不正确"));
+    }
+    if !db.set_base_currency(user_id, payload.base_currency).await? {
+        return Err(AppError::not_found("用户不存在"));
+    }
+    if let Some(email) = email {
+        db.set_email(user_id, email).await?;
+    }
+    tracing::info!("[update_settings] user_id: {:?}, base_currency: {}", user_id, payload.base_currency);
+    let user = db.get_user(user_id).await?.ok_or(AppError::not_found("用户不存在"))?;
+    Ok(Json(Settings { base_currency: user.base_currency, email: user.email }))
+}
+
+async fn register(
+    State(db): State<Arc<dyn LedgerStore>>,
+    State(config): State<Arc<Config>>,
+    AppJson(payload): AppJson<RegisterPayload>,
+) -> Result<Json<TokenResponse>, AppError> {
+    let username = valid_username(&payload.username)?;
+    check_strength(&config.auth.password, &payload.password)?;
+    let hashed = hash(&payload.password, DEFAULT_COST)?;
+    // 用户名是否重复由存储层（唯一索引）判断，重复时返回 409
+    let user = db.create_user(User {
+        id: ObjectId::new(),
+        username,
         password: hashed,
-        created_at: mongodb::bson::DateTime::now(),
-    };
-    let res = users.insert_one(&user).await;
-    match res {
-        Ok(_) => {
-            let token = create_jwt(&user.id);
-            (StatusCode::OK, Json(TokenResponse { token }))
-        },
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, Json(TokenResponse { token: "注册失败".to_string() })),
+        created_at: DateTime::now(),
+        base_currency: Currency::default(),
+        two_factor: None,
+        default_ledger_id: None,
+        email: None,
+    }).await?;
+    // 每个用户注册时都有一个只属于自己的账本
+    let ledger = db.create_ledger(Ledger::personal(user.id)).await?;
+    db.set_default_ledger(user.id, ledger.id).await?;
+    let tokens = start_session(db.as_ref(), &config.auth, user.id).await?;
+    Ok(Json(tokens.into()))
+}
+
+// 用户不存在时用来执行一次等价的 bcrypt 校验，避免通过响应时间判断用户名是否存在
+static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| hash("dummy-password", DEFAULT_COST).unwrap());
+
+// 客户端 IP；只有配置信任代理时才读取 X-Forwarded-For
+fn client_ip(config: &Config, headers: &HeaderMap, addr: SocketAddr) -> IpAddr {
+    if config.server.trust_forwarded_for
+        && let Some(ip) = headers.get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .and_then(|v| v.trim().parse().ok())
+    {
+        return ip;
     }
+    addr.ip()
+}
+
+// 距离解除锁定还有多少秒
+fn remaining_lock(limit: &LoginLimit, attempt: &LoginAttempt) -> i64 {
+    let until = attempt.last_failure.timestamp_millis() + limit.lock_secs(attempt.failures) * 1000;
+    (until - DateTime::now().timestamp_millis() + 999) / 1000
 }
 
-async fn login(State(db): State<Arc<MongoDB>>, Json(payload): Json<LoginPayload>) -> (StatusCode, axum::Json<TokenResponse>) {
-    let users: Collection<User> = db.users_collection();
-    match users.find_one(doc! {"username": &payload.username}).await {
-        Ok(user_opt) => {
-            if let Some(user) = user_opt {
-                match verify(&payload.password, &user.password) {
-                    Ok(valid) => {
-                        if valid {
-                            let token = create_jwt(&user.id);
-                            return (StatusCode::OK, Json(TokenResponse { token }));
-                        } else {
-                            println!("[登录] 密码错误: {}", &payload.username);
-                        }
-                    },
-                    Err(e) => {
-                        println!("[登录] 密码校验异常: {}", e);
-                        return (StatusCode::INTERNAL_SERVER_ERROR, Json(TokenResponse { token: "密码校验异常".to_string() }));
-                    }
-                }
-            } else {
-                println!("[登录] 用户不存在: {}", &payload.username);
+/// 任一计数键处于锁定期时返回 429
+pub(crate) async fn ensure_not_locked(db: &dyn LedgerStore, limit: &LoginLimit, keys: &[String]) -> Result<(), AppError> {
+    for key in keys {
+        if let Some(attempt) = db.get_login_attempt(key).await? {
+            let remaining = remaining_lock(limit, &attempt);
+            if remaining > 0 {
+                return Err(AppError::too_many_requests(
+                    format!("登录失败次数过多，请 {} 秒后再试", remaining), remaining as u64,
+                ));
             }
-            (StatusCode::UNAUTHORIZED, Json(TokenResponse { token: "用户名或密码错误".to_string() }))
-        },
-        Err(e) => {
-            println!("[登录] 数据库查询异常: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(TokenResponse { token: "数据库查询异常".to_string() }))
         }
     }
+    Ok(())
+}
+
+/// 给每个计数键记一次失败
+pub(crate) async fn record_failures(db: &dyn LedgerStore, limit: &LoginLimit, keys: &[String]) -> Result<(), AppError> {
+    let window_start = DateTime::from_millis(DateTime::now().timestamp_millis() - limit.window_secs * 1000);
+    for key in keys {
+        let attempt = db.record_login_failure(key, window_start).await?;
+        let lock = limit.lock_secs(attempt.failures);
+        if lock > 0 {
+            tracing::warn!("[record_failures] {} 连续失败 {} 次，锁定 {} 秒", key, attempt.failures, lock);
+        }
+    }
+    Ok(())
+}
+
+// 用户名和 IP 分别计数，任一被锁定都拒绝登录；无论用户名是否存在，响应都相同
+async fn login(
+    State(db): State<Arc<dyn LedgerStore>>,
+    State(config): State<Arc<Config>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    AppJson(payload): AppJson<LoginPayload>,
+) -> Result<Json<LoginResponse>, AppError> {
+    let limit = &config.auth.login_limit;
+    let username = normalize_username(&payload.username);
+    let keys = [
+        LoginAttempt::username_key(&username),
+        LoginAttempt::ip_key(&client_ip(&config, &headers, addr)),
+    ];
+    ensure_not_locked(db.as_ref(), limit, &keys).await?;
+
+    let user = db.get_user_by_username(&username).await?;
+    let password_hash = user.as_ref().map_or(DUMMY_HASH.as_str(), |u| u.password.as_str());
+    let verified = verify(&payload.password, password_hash)?;
+    let Some(user) = user.filter(|_| verified) else {
+        record_failures(db.as_ref(), limit, &keys).await?;
+        return Err(AppError::unauthorized("用户名或密码错误"));
+    };
+    // 开启两步验证时先返回挑战令牌，用户名计数等验证码通过后再清零
+    if user.two_factor_enabled() {
+        return Ok(Json(LoginResponse::Challenge {
+            two_factor_required: true,
+            challenge_token: create_challenge(&config.auth, &user.id),
+            expires_in: config.auth.challenge_ttl_secs,
+        }));
+    }
+    // IP 计数不因登录成功而清零，否则攻击者可以用自己的账号重置计数
+    db.clear_login_attempts(&keys[0]).await?;
+    let tokens = start_session(db.as_ref(), &config.auth, user.id).await?;
+    Ok(Json(LoginResponse::Tokens(tokens.into())))
+}
+
+// 用刷新令牌换取新的令牌对（刷新令牌同时轮换）
+async fn refresh(
+    State(db): State<Arc<dyn LedgerStore>>,
+    State(config): State<Arc<Config>>,
+    AppJson(payload): AppJson<RefreshPayload>,
+) -> Result<Json<TokenResponse>, AppError> {
+    let tokens = refresh_session(db.as_ref(), &config.auth, &payload.refresh_token).await?;
+    Ok(Json(tokens.into()))
+}
+
+// 退出当前会话
+async fn logout(State(db): State<Arc<dyn LedgerStore>>, session: AuthSession) -> Result<Json<bool>, AppError> {
+    db.revoke_session(session.user_id, session.session_id).await?;
+    tracing::info!("[logout] user_id: {:?}, session_id: {:?}", session.user_id, session.session_id);
+    Ok(Json(true))
+}
+
+// 退出所有设备；与密钥管理一样只接受登录会话
+async fn logout_all(State(db): State<Arc<dyn LedgerStore>>, session: AuthSession) -> Result<Json<u64>, AppError> {
+    let revoked = db.revoke_all_sessions(session.user_id).await?;
+    tracing::info!("[logout_all] user_id: {:?}, revoked: {}", session.user_id, revoked);
+    Ok(Json(revoked))
+}
+
+// API 密钥只能在登录会话中管理，不能用密钥再创建密钥
+async fn create_api_key(
+    State(db): State<Arc<dyn LedgerStore>>,
+    session: AuthSession,
+    AppJson(payload): AppJson<CreateApiKey>,
+) -> Result<Json<ApiKeyOut>, AppError> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::validation("密钥名称不能为空"));
+    }
+    let expires_at = payload.expires_at.as_deref().map(parse_date).transpose()?;
+    if expires_at.is_some_and(|t| t <= DateTime::now()) {
+        return Err(AppError::validation("过期时间必须晚于当前时间"));
+    }
+    let id = ObjectId::new();
+    let key = new_api_key(&id);
+    let api_key = db.create_api_key(ApiKey {
+        id,
+        user_id: session.user_id,
+        name: name.to_string(),
+        key_hash: hash_token(&key),
+        scope: payload.scope,
+        created_at: DateTime::now(),
+        expires_at,
+    }).await?;
+    tracing::info!("[create_api_key] user_id: {:?}, key_id: {:?}, scope: {:?}", session.user_id, id, api_key.scope);
+    Ok(Json(ApiKeyOut { key: Some(key), ..ApiKeyOut::from(&api_key) }))
+}
+
+async fn list_api_keys(State(db): State<Arc<dyn LedgerStore>>, session: AuthSession) -> Result<Json<Vec<ApiKeyOut>>, AppError> {
+    let keys = db.get_api_keys_by_user(session.user_id).await?;
+    Ok(Json(keys.iter().map(ApiKeyOut::from).collect()))
+}
+
+async fn delete_api_key(
+    State(db): State<Arc<dyn LedgerStore>>,
+    session: AuthSession,
+    AppPath(id): AppPath<String>,
+) -> Result<Json<bool>, AppError> {
+    let key_id = ObjectId::parse_str(&id)?;
+    if !db.delete_api_key(session.user_id, key_id).await? {
+        return Err(AppError::not_found("API密钥不存在"));
+    }
+    tracing::info!("[delete_api_key] user_id: {:?}, key_id: {:?}", session.user_id, key_id);
+    Ok(Json(true))
 }
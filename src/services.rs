@@ -0,0 +1,54 @@
+//! 路由和后台通知共用的业务计算：汇率表加载、预算周期执行结果统计。
+
+use mongodb::bson::{oid::ObjectId, DateTime};
+use crate::db::{LedgerStore, OrderFilter};
+use crate::error::AppError;
+use crate::models::budget::{Budget, PeriodOutcome};
+use crate::models::category::{subtree_ids, Category};
+use crate::models::currency::Currency;
+use crate::models::exchange_rate::RateTable;
+use crate::models::money::Money;
+
+/// 读取用户本位币及其汇率表，供各统计接口换算使用。
+pub async fn load_rates(db: &dyn LedgerStore, user_id: ObjectId) -> Result<(Currency, RateTable), AppError> {
+    let user = db.get_user(user_id).await?.ok_or(AppError::not_found("用户不存在"))?;
+    let rates = db.get_rates_by_user(user_id).await?;
+    Ok((user.base_currency, RateTable::new(rates)))
+}
+
+/// 截至 `now` 已开始的各周期的执行结果（含结转）。统计预算分类及其全部子分类的消费，
+/// 按订单日期的汇率换算成预算币种；缺少汇率的消费不计入，在各周期的 `missing_rates` 中列出
+pub async fn budget_outcomes(
+    db: &dyn LedgerStore,
+    budget: &Budget,
+    categories: &[Category],
+    rates: &RateTable,
+    now: DateTime,
+) -> Result<Vec<PeriodOutcome>, AppError> {
+    let windows = budget.windows_until(now);
+    let (Some(first), Some(last)) = (windows.first(), windows.last()) else {
+        return Ok(Vec::new());
+    };
+    let currency = budget.amount.currency;
+    let filter = OrderFilter {
+        category_ids: Some(subtree_ids(categories, budget.category_id)),
+        order_type: Some("消费".to_string()),
+        date_start: Some(first.start),
+        date_end: Some(now.min(last.end)),
+        ..Default::default()
+    };
+    let mut spent = vec![Money::zero(currency); windows.len()];
+    let mut missing = vec![Vec::new(); windows.len()];
+    // 一次查询按订单的确切时间分到各周期，周期不从零点开始时同一天的订单也能正确拆分
+    let starts: Vec<DateTime> = windows.iter().map(|w| w.start).collect();
+    for t in db.order_totals_by_period(budget.ledger_id, &filter, Some(currency), &starts).await? {
+        if let Some(converted) = rates.convert_or_record(t.amount, currency, t.date, &mut missing[t.period]) {
+            spent[t.period].accumulate(converted.minor)?;
+        }
+    }
+    let mut outcomes = budget.outcomes(&windows, &spent)?;
+    for (outcome, missing) in outcomes.iter_mut().zip(missing) {
+        outcome.missing_rates = missing;
+    }
+    Ok(outcomes)
+}
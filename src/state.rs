@@ -0,0 +1,38 @@
+use axum::extract::FromRef;
+use std::sync::Arc;
+use crate::config::Config;
+use crate::db::LedgerStore;
+use crate::notify::Notifier;
+
+/// 路由共享状态；处理函数可以直接提取其中的 `State<Arc<dyn LedgerStore>>`、`State<Arc<Config>>` 或 `State<Arc<Notifier>>`
+#[derive(Clone)]
+pub struct AppState {
+    pub db: Arc<dyn LedgerStore>,
+    pub config: Arc<Config>,
+    pub notifier: Arc<Notifier>,
+}
+
+impl AppState {
+    pub fn new(db: Arc<dyn LedgerStore>, config: Config) -> Self {
+        let notifier = Arc::new(Notifier::new(&config.notifications));
+        AppState { db, config: Arc::new(config), notifier }
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn LedgerStore> {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Config> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Notifier> {
+    fn from_ref(state: &AppState) -> Self {
+        state.notifier.clone()
+    }
+}
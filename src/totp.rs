@@ -0,0 +1,58 @@
+//! RFC 6238 TOTP（HMAC-SHA1、6 位、30 秒步长），兼容常见的身份验证器 App。
+
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+
+const STEP_SECS: i64 = 30;
+const DIGITS: u32 = 6;
+/// 允许前后各一个步长的时钟误差
+const SKEW_STEPS: i64 = 1;
+
+fn alphabet() -> base32::Alphabet {
+    base32::Alphabet::Rfc4648 { padding: false }
+}
+
+/// 生成 160 位随机密钥，Base32 编码
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base32::encode(alphabet(), &bytes)
+}
+
+/// 供身份验证器扫码的 `otpauth://` 地址
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    let issuer = urlencoding::encode(issuer);
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer, urlencoding::encode(account), secret, issuer, DIGITS, STEP_SECS,
+    )
+}
+
+fn hotp(key: &[u8], counter: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC 接受任意长度的密钥");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([hash[offset], hash[offset + 1], hash[offset + 2], hash[offset + 3]]) & 0x7fff_ffff;
+    value % 10u32.pow(DIGITS)
+}
+
+/// 计算 `unix_secs` 时刻的验证码；密钥不是合法 Base32 时返回 `None`
+pub fn code_at(secret: &str, unix_secs: i64) -> Option<String> {
+    let key = base32::decode(alphabet(), secret)?;
+    Some(format!("{:0width$}", hotp(&key, unix_secs / STEP_SECS), width = DIGITS as usize))
+}
+
+/// 校验验证码，成功时返回匹配的步长序号，用于拒绝同一验证码被重复使用
+pub fn verify(secret: &str, code: &str, unix_secs: i64) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let key = base32::decode(alphabet(), secret)?;
+    let current = unix_secs / STEP_SECS;
+    (current - SKEW_STEPS..=current + SKEW_STEPS).find(|&step| {
+        format!("{:0width$}", hotp(&key, step), width = DIGITS as usize) == code
+    })
+}
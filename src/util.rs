@@ -0,0 +1,32 @@
+//! 多个模块共用的日期解析、反序列化等辅助函数。
+
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Deserializer};
+use crate::error::AppError;
+
+/// 解析 RFC3339 或 YYYY-MM-DD（按 UTC 零点）格式的日期
+pub fn parse_date(s: &str) -> Result<DateTime, AppError> {
+    if let Ok(dt) = DateTime::parse_rfc3339_str(s) {
+        return Ok(dt);
+    }
+    let naive = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| AppError::validation(format!("日期格式不合法: {}", s)))?;
+    Ok(DateTime::from_millis(naive.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp_millis()))
+}
+
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+/// `date` 所在 UTC 自然日的零点
+pub fn day_start(date: DateTime) -> DateTime {
+    let millis = date.timestamp_millis();
+    DateTime::from_millis(millis - millis.rem_euclid(DAY_MILLIS))
+}
+
+/// 区分“字段缺省”（None）与“显式置空”（Some(None)），用于 PATCH 中可清空的字段
+pub fn double_option<'de, T, D>(de: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(de).map(Some)
+}
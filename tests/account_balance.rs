@@ -0,0 +1,172 @@
+mod common;
+
+use common::{delete, get, post, register, spawn_app};
+use serde_json::json;
+
+async fn create_account(base: &str, token: &str, balance: f64) -> String {
+    create_account_in(base, token, balance, "人民币").await
+}
+
+async fn create_account_in(base: &str, token: &str, balance: f64, currency: &str) -> String {
+    let (status, account) = post(base, token, "/account/accounts", json!({
+        "name": "现金",
+        "account_type": "现金",
+        "balance": balance,
+        "currency": currency,
+    })).await;
+    assert_eq!(status, 200);
+    account["id"]["$oid"].as_str().unwrap().to_string()
+}
+
+async fn balance_of(base: &str, token: &str, account_id: &str) -> i64 {
+    let (_, accounts) = get(base, token, "/account/accounts").await;
+    accounts.as_array().unwrap().iter()
+        .find(|a| a["id"]["$oid"] == account_id)
+        .unwrap()["balance"]["minor"]
+        .as_i64()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn orders_adjust_account_balance() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let account_id = create_account(&base, &token, 100.0).await;
+
+    let (status, expense) = post(&base, &token, "/transaction/orders", json!({
+        "account_id": account_id,
+        "name": "午饭",
+        "order_type": "消费",
+        "amount": 30.0,
+        "currency": "人民币",
+        "date": "2025-01-15T12:00:00Z",
+    })).await;
+    assert_eq!(status, 200);
+    let (status, _) = post(&base, &token, "/transaction/orders", json!({
+        "account_id": account_id,
+        "name": "红包",
+        "order_type": "收入",
+        "amount": 50.0,
+        "currency": "人民币",
+        "date": "2025-01-16T12:00:00Z",
+    })).await;
+    assert_eq!(status, 200);
+    assert_eq!(balance_of(&base, &token, &account_id).await, 12000);
+
+    let expense_id = expense["id"]["$oid"].as_str().unwrap();
+    delete(&base, &token, &format!("/order/{}", expense_id)).await;
+    assert_eq!(balance_of(&base, &token, &account_id).await, 15000);
+
+    let (status, account) = post(&base, &token, &format!("/account/accounts/{}/recompute", account_id), json!({})).await;
+    assert_eq!(status, 200);
+    assert_eq!(account["balance"]["minor"], 15000);
+}
+
+#[tokio::test]
+async fn order_on_other_users_account_is_rejected() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let account_id = create_account(&base, &alice, 100.0).await;
+
+    let (status, _) = post(&base, &bob, "/transaction/orders", json!({
+        "account_id": account_id,
+        "name": "午饭",
+        "order_type": "消费",
+        "amount": 30.0,
+        "currency": "人民币",
+        "date": "2025-01-15T12:00:00Z",
+    })).await;
+    assert_ne!(status, 200);
+    assert_eq!(balance_of(&base, &alice, &account_id).await, 10000);
+}
+
+#[tokio::test]
+async fn transfer_moves_money_and_deletes_as_pair() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let usd = create_account_in(&base, &token, 100.0, "美元").await;
+    let cny = create_account_in(&base, &token, 0.0, "人民币").await;
+
+    let (status, _) = post(&base, &token, "/transaction/transfers", json!({
+        "from_account_id": usd,
+        "to_account_id": cny,
+        "amount": 10.0,
+        "date": "2025-01-15T12:00:00Z",
+    })).await;
+    assert_ne!(status, 200, "跨币种转账缺少汇率应被拒绝");
+
+    let (status, transfer) = post(&base, &token, "/transaction/transfers", json!({
+        "from_account_id": usd,
+        "to_account_id": cny,
+        "amount": 10.0,
+        "exchange_rate": 7.1,
+        "fee": 1.0,
+        "date": "2025-01-15T12:00:00Z",
+    })).await;
+    assert_eq!(status, 200);
+    assert_eq!(transfer["incoming"]["amount"]["minor"], 7100);
+    assert_eq!(balance_of(&base, &token, &usd).await, 8900);
+    assert_eq!(balance_of(&base, &token, &cny).await, 7100);
+
+    let incoming_id = transfer["incoming"]["id"]["$oid"].as_str().unwrap();
+    delete(&base, &token, &format!("/order/{}", incoming_id)).await;
+    assert_eq!(balance_of(&base, &token, &usd).await, 10000);
+    assert_eq!(balance_of(&base, &token, &cny).await, 0);
+    let (_, body) = get(&base, &token, "/order_query/orders/query").await;
+    assert_eq!(body["total"], 0);
+}
+
+#[tokio::test]
+async fn transfer_amount_and_fee_are_range_checked() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let from = create_account(&base, &token, 100.0).await;
+    let to = create_account(&base, &token, 0.0).await;
+
+    for (amount, fee) in [(1e300, 0.0), (0.0, 0.0), (10.0, -1.0), (10.0, 1e300)] {
+        let (status, body) = post(&base, &token, "/transaction/transfers", json!({
+            "from_account_id": from,
+            "to_account_id": to,
+            "amount": amount,
+            "fee": fee,
+            "date": "2025-01-15T12:00:00Z",
+        })).await;
+        assert_eq!(status, 400, "amount {} fee {}", amount, fee);
+        assert_eq!(body["code"], "validation_error");
+    }
+    assert_eq!(balance_of(&base, &token, &from).await, 10000);
+}
+
+#[tokio::test]
+async fn transfer_rate_cannot_create_or_lose_money() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let cny = create_account(&base, &token, 100.0).await;
+    let other = create_account(&base, &token, 0.0).await;
+    let jpy = create_account_in(&base, &token, 0.0, "JPY").await;
+
+    // 同币种转账的汇率只能是 1；换算后转入金额为零的汇率同样拒绝
+    for (to, rate) in [(&other, 2.0), (&other, 0.5), (&jpy, 0.0001)] {
+        let (status, body) = post(&base, &token, "/transaction/transfers", json!({
+            "from_account_id": cny,
+            "to_account_id": to,
+            "amount": 1.0,
+            "exchange_rate": rate,
+            "date": "2025-01-15T12:00:00Z",
+        })).await;
+        assert_eq!(status, 400, "rate {}", rate);
+        assert_eq!(body["code"], "validation_error");
+    }
+    assert_eq!(balance_of(&base, &token, &cny).await, 10000);
+
+    let (status, transfer) = post(&base, &token, "/transaction/transfers", json!({
+        "from_account_id": cny,
+        "to_account_id": other,
+        "amount": 10.0,
+        "exchange_rate": 1.0,
+        "date": "2025-01-15T12:00:00Z",
+    })).await;
+    assert_eq!(status, 200);
+    assert_eq!(transfer["incoming"]["amount"]["minor"], 1000);
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::{delete, get, post, register, spawn_app};
+use serde_json::json;
+
+#[tokio::test]
+async fn api_keys_authenticate_with_scope() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+
+    let (status, reader) = post(&base, &token, "/user/api_keys", json!({"name": "对账脚本"})).await;
+    assert_eq!(status, 200);
+    assert_eq!(reader["scope"], "read_only");
+    let reader_key = reader["key"].as_str().unwrap().to_string();
+    let (_, writer) = post(&base, &token, "/user/api_keys", json!({
+        "name": "银行导入", "scope": "read_write", "expires_at": "2999-01-01",
+    })).await;
+    let writer_key = writer["key"].as_str().unwrap().to_string();
+
+    // 列表不返回明文密钥
+    let (status, keys) = get(&base, &token, "/user/api_keys").await;
+    assert_eq!(status, 200);
+    assert_eq!(keys.as_array().unwrap().len(), 2);
+    assert!(keys[0].get("key").is_none());
+
+    let account = json!({"name": "现金", "account_type": "现金", "balance": 0.0, "currency": "CNY"});
+    assert_eq!(get(&base, &reader_key, "/account/accounts").await.0, 200);
+    let (status, body) = post(&base, &reader_key, "/account/accounts", account.clone()).await;
+    assert_eq!(status, 403);
+    assert_eq!(body["code"], "forbidden");
+    assert_eq!(post(&base, &writer_key, "/account/accounts", account).await.0, 200);
+
+    // X-Api-Key 请求头同样可用
+    let res = reqwest::Client::new()
+        .get(format!("{}/account/accounts", base))
+        .header("X-Api-Key", &reader_key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 200);
+
+    // 密钥不能用来管理密钥
+    assert_eq!(get(&base, &writer_key, "/user/api_keys").await.0, 401);
+    // 也不能用来注销登录会话
+    assert_eq!(post(&base, &writer_key, "/user/logout_all", json!({})).await.0, 401);
+    // 也不能修改账号设置（邮箱、本位币）
+    let res = reqwest::Client::new()
+        .put(format!("{}/user/settings", base))
+        .header("X-Api-Key", &writer_key)
+        .json(&json!({"base_currency": "USD", "email": "attacker@example.com"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+    let (_, settings) = get(&base, &token, "/user/settings").await;
+    assert_eq!(settings["base_currency"], "CNY");
+    assert!(settings["email"].is_null());
+    assert_eq!(get(&base, &token, "/account/accounts").await.0, 200);
+
+    let (status, _) = delete(&base, &token, &format!("/user/api_keys/{}", reader["id"].as_str().unwrap())).await;
+    assert_eq!(status, 200);
+    assert_eq!(get(&base, &reader_key, "/account/accounts").await.0, 401);
+    let last = if writer_key.ends_with('0') { "1" } else { "0" };
+    let tampered = format!("{}{}", &writer_key[..writer_key.len() - 1], last);
+    assert_eq!(get(&base, &tampered, "/account/accounts").await.0, 401);
+}
+
+#[tokio::test]
+async fn api_keys_are_private_and_validated() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+
+    let (status, _) = post(&base, &alice, "/user/api_keys", json!({"name": " "})).await;
+    assert_eq!(status, 400);
+    let (status, _) = post(&base, &alice, "/user/api_keys", json!({"name": "x", "expires_at": "2000-01-01"})).await;
+    assert_eq!(status, 400);
+
+    let (_, key) = post(&base, &alice, "/user/api_keys", json!({"name": "x"})).await;
+    let id = key["id"].as_str().unwrap();
+    assert_eq!(delete(&base, &bob, &format!("/user/api_keys/{}", id)).await.0, 404);
+    let (_, keys) = get(&base, &bob, "/user/api_keys").await;
+    assert!(keys.as_array().unwrap().is_empty());
+    assert_eq!(get(&base, key["key"].as_str().unwrap(), "/account/accounts").await.0, 200);
+}
@@ -0,0 +1,170 @@
+mod common;
+
+use common::{delete, get, id_of, patch, post, put, register, spawn_app};
+use serde_json::{json, Value};
+
+async fn create_book(base: &str, token: &str, name: &str) -> String {
+    let (status, book) = post(base, token, "/ledger/ledgers", json!({"name": name})).await;
+    assert_eq!(status, 200, "{}", book);
+    assert_eq!(book["role"], "owner");
+    assert_eq!(book["is_default"], false);
+    id_of(&book)
+}
+
+fn order_body(amount: f64) -> Value {
+    json!({
+        "name": "进货", "order_type": "消费", "amount": amount, "currency": "CNY",
+        "date": "2025-01-15T12:00:00Z",
+    })
+}
+
+#[tokio::test]
+async fn books_keep_their_data_separate() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let business = create_book(&base, &token, "副业").await;
+
+    let (status, _) = post(&base, &token, "/v1/orders", order_body(10.0)).await;
+    assert_eq!(status, 201);
+    let (status, _) = post(&base, &token, &format!("/books/{}/v1/orders", business), order_body(99.0)).await;
+    assert_eq!(status, 201);
+
+    // 默认账本与副业账本各自统计
+    let (_, personal) = get(&base, &token, "/v1/orders").await;
+    assert_eq!(personal["total"], 1);
+    assert_eq!(personal["stat"]["消费"]["minor"], 1000);
+    let (_, side) = get(&base, &token, &format!("/books/{}/v1/orders?order_type=消费", business)).await;
+    assert_eq!(side["total"], 1);
+    assert_eq!(side["stat"]["消费"]["minor"], 9900);
+
+    // 不同账本的资源互相不可见
+    let order_id = side["orders"][0]["id"].as_str().unwrap();
+    let (status, _) = get(&base, &token, &format!("/v1/orders/{}", order_id)).await;
+    assert_eq!(status, 404);
+    let (status, _) = get(&base, &token, &format!("/books/{}/v1/orders/{}", business, order_id)).await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+async fn path_segment_selects_book_for_every_resource() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let business = create_book(&base, &token, "副业").await;
+    let prefix = format!("/books/{}", business);
+
+    let (status, account) = post(&base, &token, &format!("{}/account/accounts", prefix), json!({
+        "name": "对公账户", "account_type": "银行卡", "balance": 0.0, "currency": "CNY",
+    })).await;
+    assert_eq!(status, 200);
+    let (status, category) = post(&base, &token, &format!("{}/category/categories", prefix), json!({
+        "name": "进货", "category_type": "支出",
+    })).await;
+    assert_eq!(status, 200);
+    let (status, _) = post(&base, &token, &format!("{}/asset/assets", prefix), json!({
+        "name": "存货", "asset_type": "其他", "value": 10.0, "currency": "CNY",
+        "account_id": account["id"]["$oid"],
+    })).await;
+    assert_eq!(status, 200);
+    let (status, _) = post(&base, &token, &format!("{}/budget/budgets", prefix), json!({
+        "category_id": category["id"]["$oid"], "amount": 500.0, "currency": "CNY", "period": "月",
+        "start_date": "2025-01-01T00:00:00Z", "end_date": "2025-01-31T23:59:59Z",
+    })).await;
+    assert_eq!(status, 200);
+
+    for path in ["/account/accounts", "/category/categories", "/asset/assets", "/budget/budgets"] {
+        let (_, in_book) = get(&base, &token, &format!("{}{}", prefix, path)).await;
+        assert_eq!(in_book.as_array().unwrap().len(), 1, "{}", path);
+        let (_, default) = get(&base, &token, path).await;
+        assert_eq!(default.as_array().unwrap().len(), 0, "{}", path);
+    }
+
+    // 跨账本引用视为不存在
+    let (status, _) = post(&base, &token, "/asset/assets", json!({
+        "name": "x", "asset_type": "其他", "value": 1.0, "currency": "CNY",
+        "account_id": account["id"]["$oid"],
+    })).await;
+    assert_eq!(status, 404);
+
+    let (status, _) = get(&base, &token, "/books/not-an-id/account/accounts").await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn default_book_can_be_switched() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let business = create_book(&base, &token, "副业").await;
+
+    let (status, book) = put(&base, &token, "/ledger/default", json!({"ledger_id": business})).await;
+    assert_eq!(status, 200);
+    assert_eq!(book["is_default"], true);
+    let (status, _) = post(&base, &token, "/v1/orders", order_body(5.0)).await;
+    assert_eq!(status, 201);
+    let (_, side) = get(&base, &token, &format!("/books/{}/v1/orders", business)).await;
+    assert_eq!(side["total"], 1);
+
+    let (_, books) = get(&base, &token, "/ledger/ledgers").await;
+    let defaults: Vec<_> = books.as_array().unwrap().iter().filter(|b| b["is_default"] == true).collect();
+    assert_eq!(defaults.len(), 1);
+    assert_eq!(defaults[0]["id"], business.as_str());
+
+    // 不是成员的账本不能设为默认
+    let bob = register(&base, "bob").await;
+    let (status, _) = put(&base, &bob, "/ledger/default", json!({"ledger_id": business})).await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn books_can_be_renamed_and_deleted() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let business = create_book(&base, &token, "副业").await;
+    let path = format!("/ledger/ledgers/{}", business);
+
+    let (status, book) = patch(&base, &token, &path, json!({"name": "  工作室  "})).await;
+    assert_eq!(status, 200);
+    assert_eq!(book["name"], "工作室");
+    let (status, _) = patch(&base, &token, &path, json!({"name": "   "})).await;
+    assert_eq!(status, 400);
+    let (status, _) = post(&base, &token, "/ledger/ledgers", json!({"name": "x".repeat(33)})).await;
+    assert_eq!(status, 400);
+
+    let (status, _) = post(&base, &token, &format!("/books/{}/v1/orders", business), order_body(1.0)).await;
+    assert_eq!(status, 201);
+    let (status, _) = delete(&base, &token, &path).await;
+    assert_eq!(status, 200);
+    let (status, _) = get(&base, &token, &format!("/books/{}/v1/orders", business)).await;
+    assert_eq!(status, 404);
+    let (_, books) = get(&base, &token, "/ledger/ledgers").await;
+    assert_eq!(books.as_array().unwrap().len(), 1);
+
+    // 默认账本不能删除
+    let default = id_of(&books[0]);
+    let (status, body) = delete(&base, &token, &format!("/ledger/ledgers/{}", default)).await;
+    assert_eq!(status, 409);
+    assert_eq!(body["code"], "conflict");
+}
+
+#[tokio::test]
+async fn falls_back_when_default_book_becomes_inaccessible() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let shared = create_book(&base, &alice, "家庭").await;
+    let (_, invitation) = post(&base, &alice, &format!("/ledger/ledgers/{}/invitations", shared), json!({
+        "username": "bob", "role": "editor",
+    })).await;
+    let (status, _) = post(&base, &bob, &format!("/ledger/invitations/{}/accept", id_of(&invitation)), json!({})).await;
+    assert_eq!(status, 200);
+    let (status, _) = put(&base, &bob, "/ledger/default", json!({"ledger_id": shared})).await;
+    assert_eq!(status, 200);
+
+    // 所有者删除共享账本后，bob 回到自己的个人账本
+    let (status, _) = delete(&base, &alice, &format!("/ledger/ledgers/{}", shared)).await;
+    assert_eq!(status, 200);
+    let (status, _) = post(&base, &bob, "/v1/orders", order_body(3.0)).await;
+    assert_eq!(status, 201);
+    let (_, books) = get(&base, &bob, "/ledger/ledgers").await;
+    assert_eq!(books.as_array().unwrap().len(), 1);
+    assert_eq!(books[0]["is_default"], true);
+}
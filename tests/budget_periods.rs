@@ -0,0 +1,218 @@
+mod common;
+
+use common::{create_category, get, id_of, patch, post, put, register, spawn_app, spend};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde_json::{json, Value};
+use todo_list::models::budget::{Budget, BudgetPeriod};
+use todo_list::models::currency::Currency;
+use todo_list::models::money::Money;
+
+async fn budget(base: &str, token: &str, body: Value) -> String {
+    let (status, budget) = post(base, token, "/budget/budgets", body).await;
+    assert_eq!(status, 200, "{}", budget);
+    id_of(&budget)
+}
+
+#[tokio::test]
+async fn monthly_periods_clamp_to_the_end_of_shorter_months() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let id = budget(&base, &token, json!({
+        "category_id": food, "amount": 100.0, "currency": "CNY", "period": "monthly",
+        "start_date": "2025-01-31T00:00:00Z",
+    })).await;
+
+    let (status, periods) = get(&base, &token, &format!("/budget/budgets/{}/periods?date=2025-04-15", id)).await;
+    assert_eq!(status, 200, "{}", periods);
+    let starts: Vec<_> = periods.as_array().unwrap().iter().map(|p| p["start_date"].as_str().unwrap()).collect();
+    assert_eq!(starts, ["2025-01-31T00:00:00Z", "2025-02-28T00:00:00Z", "2025-03-31T00:00:00Z"]);
+    assert!(periods[0]["end_date"].as_str().unwrap().starts_with("2025-02-27T23:59:59"));
+
+    // 没有结束日期的循环预算一直有效
+    let (_, summary) = get(&base, &token, "/budget/summary?date=2030-06-01").await;
+    assert_eq!(summary["budgets"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn weekly_progress_only_counts_the_current_week() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let id = budget(&base, &token, json!({
+        "category_id": food, "amount": 200.0, "currency": "CNY", "period": "weekly",
+        "start_date": "2025-01-06T00:00:00Z",
+    })).await;
+    spend(&base, &token, &food, 100.0, "CNY", "2025-01-07T12:00:00Z").await;
+    spend(&base, &token, &food, 50.0, "CNY", "2025-01-14T12:00:00Z").await;
+
+    let (status, progress) = get(&base, &token, &format!("/budget/budgets/{}/progress?date=2025-01-15", id)).await;
+    assert_eq!(status, 200);
+    assert_eq!(progress["start_date"], "2025-01-13T00:00:00Z");
+    assert_eq!(progress["spent"]["minor"], 5000);
+    assert_eq!(progress["amount"]["minor"], 20000);
+    assert_eq!(progress["carried_over"]["minor"], 0);
+}
+
+#[tokio::test]
+async fn periods_starting_mid_day_split_orders_at_the_exact_time() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let id = budget(&base, &token, json!({
+        "category_id": food, "amount": 200.0, "currency": "CNY", "period": "weekly",
+        "start_date": "2025-01-06T12:00:00Z",
+    })).await;
+    spend(&base, &token, &food, 30.0, "CNY", "2025-01-13T10:00:00Z").await;
+    spend(&base, &token, &food, 50.0, "CNY", "2025-01-13T14:00:00Z").await;
+
+    let (_, periods) = get(&base, &token, &format!("/budget/budgets/{}/periods?date=2025-01-15", id)).await;
+    let spent: Vec<_> = periods.as_array().unwrap().iter().map(|p| p["spent"]["minor"].as_i64().unwrap()).collect();
+    assert_eq!(spent, [3000, 5000]);
+}
+
+#[tokio::test]
+async fn rollover_carries_surplus_and_overspend_forward() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let body = |rollover: bool| json!({
+        "category_id": food, "amount": 1000.0, "currency": "CNY", "period": "monthly",
+        "start_date": "2025-01-01T00:00:00Z", "rollover": rollover,
+    });
+    let rolling = budget(&base, &token, body(true)).await;
+    let fixed = budget(&base, &token, body(false)).await;
+    spend(&base, &token, &food, 300.0, "CNY", "2025-01-10T12:00:00Z").await;
+    spend(&base, &token, &food, 1900.0, "CNY", "2025-02-10T12:00:00Z").await;
+
+    // 1 月结余 700 转入 2 月；2 月超支 200 从 3 月扣除
+    let (_, periods) = get(&base, &token, &format!("/budget/budgets/{}/periods?date=2025-03-10", rolling)).await;
+    let periods = periods.as_array().unwrap();
+    assert_eq!(periods.len(), 3);
+    let carried: Vec<_> = periods.iter().map(|p| p["carried_over"]["minor"].as_i64().unwrap()).collect();
+    assert_eq!(carried, [0, 70000, -20000]);
+    let amounts: Vec<_> = periods.iter().map(|p| p["amount"]["minor"].as_i64().unwrap()).collect();
+    assert_eq!(amounts, [100000, 170000, 80000]);
+    assert_eq!(periods[1]["remaining"]["minor"], -20000);
+
+    let (_, progress) = get(&base, &token, &format!("/budget/budgets/{}/progress?date=2025-03-10", fixed)).await;
+    assert_eq!(progress["amount"]["minor"], 100000);
+    assert_eq!(progress["carried_over"]["minor"], 0);
+
+    let (_, summary) = get(&base, &token, "/budget/summary?date=2025-03-10").await;
+    assert_eq!(summary["total_amount"]["minor"], 180000);
+    assert_eq!(summary["total_spent"]["minor"], 0);
+}
+
+#[test]
+fn rollover_of_a_large_amount_reports_overflow_instead_of_wrapping() {
+    let budget = Budget {
+        id: ObjectId::new(),
+        ledger_id: ObjectId::new(),
+        user_id: ObjectId::new(),
+        category_id: ObjectId::new(),
+        amount: Money::new(i64::MAX / 3, Currency::CNY),
+        period: BudgetPeriod::Monthly,
+        start_date: DateTime::from_millis(1735689600000), // 2025-01-01T00:00:00Z
+        end_date: None,
+        rollover: true,
+    };
+    let windows: Vec<_> = (0..4).map(|n| budget.window(n).unwrap()).collect();
+    let unspent = vec![Money::zero(Currency::CNY); windows.len()];
+
+    // 前三个周期的结转还能表示，再结转一个周期就溢出
+    let mut outcomes = budget.outcomes(&windows[..3], &unspent[..3]).unwrap();
+    assert_eq!(outcomes[2].carried_over.minor, i64::MAX / 3 * 2);
+    assert_eq!(budget.progress(&outcomes[2], windows[2].end).unwrap().amount.minor, i64::MAX / 3 * 3);
+    assert_eq!(budget.outcomes(&windows, &unspent).unwrap_err().status(), 400);
+
+    // 可用金额（本期预算加结转）溢出
+    let mut last = outcomes.pop().unwrap();
+    last.carried_over = Money::new(i64::MAX / 3 * 3, Currency::CNY);
+    assert_eq!(budget.progress(&last, windows[2].end).unwrap_err().status(), 400);
+
+    // 持续超支同样不能回绕
+    let overspent = vec![Money::new(i64::MAX / 3 * 2, Currency::CNY); windows.len()];
+    assert_eq!(budget.outcomes(&windows, &overspent).unwrap_err().status(), 400);
+}
+
+#[test]
+fn projection_right_after_the_period_starts_reports_overflow() {
+    let budget = Budget {
+        id: ObjectId::new(),
+        ledger_id: ObjectId::new(),
+        user_id: ObjectId::new(),
+        category_id: ObjectId::new(),
+        amount: Money::new(100_000, Currency::CNY),
+        period: BudgetPeriod::Yearly,
+        start_date: DateTime::from_millis(1735689600000), // 2025-01-01T00:00:00Z
+        end_date: None,
+        rollover: false,
+    };
+    let window = budget.window(0).unwrap();
+    let outcome = |minor: i64| budget.outcomes(&[window], &[Money::new(minor, Currency::CNY)]).unwrap().pop().unwrap();
+    let just_started = DateTime::from_millis(window.start.timestamp_millis() + 1);
+
+    // 开始 1 毫秒后按约 3e10 倍推算，小额支出仍可表示，大额支出溢出而不是回绕成负数
+    let progress = budget.progress(&outcome(1), just_started).unwrap();
+    assert_eq!(progress.projected.minor, window.end.timestamp_millis() - window.start.timestamp_millis());
+    assert_eq!(budget.progress(&outcome(1_000_000_000_000), just_started).unwrap_err().status(), 400);
+}
+
+#[tokio::test]
+async fn periods_are_validated_and_legacy_names_accepted() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+
+    let (status, _) = post(&base, &token, "/budget/budgets", json!({
+        "category_id": food, "amount": 1.0, "currency": "CNY", "period": "custom",
+        "start_date": "2025-01-01T00:00:00Z",
+    })).await;
+    assert_eq!(status, 400);
+    let (status, _) = post(&base, &token, "/budget/budgets", json!({
+        "category_id": food, "amount": 1.0, "currency": "CNY", "period": "yearly",
+        "start_date": "2025-01-01T00:00:00Z", "end_date": "2024-12-31T00:00:00Z",
+    })).await;
+    assert_eq!(status, 400);
+    let (status, _) = post(&base, &token, "/budget/budgets", json!({
+        "category_id": food, "amount": 1.0, "currency": "CNY", "period": "fortnightly",
+        "start_date": "2025-01-01T00:00:00Z",
+    })).await;
+    assert_eq!(status, 400);
+
+    // 旧的中文写法仍然可用，返回规范名称
+    let (status, legacy) = post(&base, &token, "/budget/budgets", json!({
+        "category_id": food, "amount": 1.0, "currency": "CNY", "period": "月",
+        "start_date": "2025-01-01T00:00:00Z", "end_date": "2025-06-30T00:00:00Z",
+    })).await;
+    assert_eq!(status, 200);
+    assert_eq!(legacy["period"], "monthly");
+    assert_eq!(legacy["rollover"], false);
+
+    // 与订单、汇率一样接受 YYYY-MM-DD
+    let (status, plain) = post(&base, &token, "/budget/budgets", json!({
+        "category_id": food, "amount": 1.0, "currency": "CNY", "period": "custom",
+        "start_date": "2025-01-01", "end_date": "2025-03-31",
+    })).await;
+    assert_eq!(status, 200, "{}", plain);
+    assert_eq!(plain["start_date"]["$date"]["$numberLong"], "1735689600000"); // 2025-01-01T00:00:00Z
+    let plain_path = format!("/budget/budgets/{}", id_of(&plain));
+    let (status, _) = put(&base, &token, &plain_path, json!({
+        "category_id": food, "amount": 2.0, "currency": "CNY", "period": "monthly",
+        "start_date": "2025-02-01", "end_date": "2025-12-31",
+    })).await;
+    assert_eq!(status, 200);
+    let (status, updated) = patch(&base, &token, &plain_path, json!({"start_date": "2025-03-01"})).await;
+    assert_eq!(status, 200);
+    assert_eq!(updated["start_date"]["$date"]["$numberLong"], "1740787200000"); // 2025-03-01T00:00:00Z
+
+    // 清空结束日期后一直循环；自定义周期不能没有结束日期
+    let path = format!("/budget/budgets/{}", id_of(&legacy));
+    let (status, updated) = patch(&base, &token, &path, json!({"end_date": null, "rollover": true})).await;
+    assert_eq!(status, 200);
+    assert_eq!(updated["end_date"], Value::Null);
+    assert_eq!(updated["rollover"], true);
+    let (status, _) = patch(&base, &token, &path, json!({"period": "custom"})).await;
+    assert_eq!(status, 400);
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use common::{create_category, get, id_of, post, register, spawn_app, spend};
+use serde_json::json;
+
+async fn january_budget(base: &str, token: &str, category: &str, amount: f64) -> String {
+    let (status, body) = post(base, token, "/budget/budgets", json!({
+        "category_id": category, "amount": amount, "currency": "CNY", "period": "月",
+        "start_date": "2025-01-01T00:00:00Z", "end_date": "2025-01-31T00:00:00Z",
+    })).await;
+    assert_eq!(status, 200);
+    id_of(&body)
+}
+
+#[tokio::test]
+async fn progress_counts_spending_in_child_categories_within_the_period() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let lunch = create_category(&base, &token, "午餐", "支出", Some(&food)).await;
+    let snack = create_category(&base, &token, "零食", "支出", Some(&lunch)).await;
+    let travel = create_category(&base, &token, "交通", "支出", None).await;
+    let budget = january_budget(&base, &token, &food, 1000.0).await;
+
+    spend(&base, &token, &food, 100.0, "CNY", "2025-01-02T12:00:00Z").await;
+    spend(&base, &token, &lunch, 50.0, "CNY", "2025-01-05T12:00:00Z").await;
+    spend(&base, &token, &snack, 50.0, "CNY", "2025-01-08T12:00:00Z").await;
+    // 不计入：其他分类、周期之外、截至日期之后；收入订单不能使用支出分类
+    spend(&base, &token, &travel, 500.0, "CNY", "2025-01-05T12:00:00Z").await;
+    spend(&base, &token, &food, 500.0, "CNY", "2024-12-31T12:00:00Z").await;
+    spend(&base, &token, &food, 500.0, "CNY", "2025-01-20T12:00:00Z").await;
+    let (status, _) = post(&base, &token, "/v1/orders", json!({
+        "name": "退款", "order_type": "收入", "amount": 30.0, "currency": "CNY",
+        "date": "2025-01-03T12:00:00Z", "category_id": food,
+    })).await;
+    assert_eq!(status, 400);
+
+    // 截至 1 月 11 日：周期共 30 天，已过 10 天
+    let (status, progress) = get(&base, &token, &format!("/budget/budgets/{}/progress?date=2025-01-11", budget)).await;
+    assert_eq!(status, 200, "{}", progress);
+    assert_eq!(progress["spent"], json!({"minor": 20000, "currency": "CNY"}));
+    assert_eq!(progress["remaining"]["minor"], 80000);
+    assert_eq!(progress["percent"], 20.0);
+    assert_eq!(progress["projected"]["minor"], 60000);
+
+    // 周期结束后推算值等于实际支出，超支时剩余为负
+    let (_, progress) = get(&base, &token, &format!("/budget/budgets/{}/progress?date=2025-02-10", budget)).await;
+    assert_eq!(progress["spent"]["minor"], 70000);
+    assert_eq!(progress["projected"]["minor"], 70000);
+    let small = january_budget(&base, &token, &lunch, 80.0).await;
+    let (_, progress) = get(&base, &token, &format!("/budget/budgets/{}/progress?date=2025-02-10", small)).await;
+    assert_eq!(progress["spent"]["minor"], 10000);
+    assert_eq!(progress["remaining"]["minor"], -2000);
+    assert_eq!(progress["percent"], 125.0);
+
+    // 周期开始前没有支出
+    let (_, progress) = get(&base, &token, &format!("/budget/budgets/{}/progress?date=2024-12-31", budget)).await;
+    assert_eq!(progress["spent"]["minor"], 0);
+    assert_eq!(progress["percent"], 0.0);
+}
+
+#[tokio::test]
+async fn progress_converts_foreign_currency_orders() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let (status, _) = post(&base, &token, "/rate/rates", json!({
+        "from": "USD", "to": "CNY", "rate": 7.0, "date": "2025-01-01",
+    })).await;
+    assert_eq!(status, 200);
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let budget = january_budget(&base, &token, &food, 1000.0).await;
+    spend(&base, &token, &food, 10.0, "USD", "2025-01-02T12:00:00Z").await;
+
+    let (_, progress) = get(&base, &token, &format!("/budget/budgets/{}/progress?date=2025-01-31", budget)).await;
+    assert_eq!(progress["spent"], json!({"minor": 7000, "currency": "CNY"}));
+}
+
+#[tokio::test]
+async fn progress_lists_spending_without_a_rate_instead_of_failing() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let budget = january_budget(&base, &token, &food, 1000.0).await;
+    spend(&base, &token, &food, 30.0, "CNY", "2025-01-02T12:00:00Z").await;
+    spend(&base, &token, &food, 10.0, "EUR", "2025-01-03T12:00:00Z").await;
+
+    let (status, progress) = get(&base, &token, &format!("/budget/budgets/{}/progress?date=2025-01-31", budget)).await;
+    assert_eq!(status, 200, "{}", progress);
+    assert_eq!(progress["spent"], json!({"minor": 3000, "currency": "CNY"}));
+    let missing = progress["missing_rates"].as_array().unwrap();
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0]["from"], "EUR");
+
+    let (status, periods) = get(&base, &token, &format!("/budget/budgets/{}/periods?date=2025-01-31", budget)).await;
+    assert_eq!(status, 200, "{}", periods);
+}
+
+#[tokio::test]
+async fn summary_lists_active_budgets_with_totals() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let travel = create_category(&base, &token, "交通", "支出", None).await;
+    january_budget(&base, &token, &food, 1000.0).await;
+    january_budget(&base, &token, &travel, 500.0).await;
+    let (status, _) = post(&base, &token, "/budget/budgets", json!({
+        "category_id": food, "amount": 2000.0, "currency": "CNY", "period": "月",
+        "start_date": "2025-02-01T00:00:00Z", "end_date": "2025-02-28T00:00:00Z",
+    })).await;
+    assert_eq!(status, 200);
+    spend(&base, &token, &food, 300.0, "CNY", "2025-01-10T12:00:00Z").await;
+    spend(&base, &token, &travel, 100.0, "CNY", "2025-01-10T12:00:00Z").await;
+
+    let (status, summary) = get(&base, &token, "/budget/summary?date=2025-01-15").await;
+    assert_eq!(status, 200);
+    assert_eq!(summary["budgets"].as_array().unwrap().len(), 2);
+    assert_eq!(summary["base_currency"], "CNY");
+    assert_eq!(summary["total_amount"]["minor"], 150000);
+    assert_eq!(summary["total_spent"]["minor"], 40000);
+    assert_eq!(summary["total_remaining"]["minor"], 110000);
+
+    let (_, summary) = get(&base, &token, "/budget/summary?date=2025-03-15").await;
+    assert_eq!(summary["budgets"].as_array().unwrap().len(), 0);
+    assert_eq!(summary["total_amount"]["minor"], 0);
+}
+
+#[tokio::test]
+async fn summary_leaves_budgets_without_a_rate_out_of_the_totals() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let travel = create_category(&base, &token, "交通", "支出", None).await;
+    january_budget(&base, &token, &food, 1000.0).await;
+    let (status, _) = post(&base, &token, "/budget/budgets", json!({
+        "category_id": travel, "amount": 500.0, "currency": "EUR", "period": "月",
+        "start_date": "2025-01-01T00:00:00Z", "end_date": "2025-01-31T00:00:00Z",
+    })).await;
+    assert_eq!(status, 200);
+
+    let (status, summary) = get(&base, &token, "/budget/summary?date=2025-01-15").await;
+    assert_eq!(status, 200, "{}", summary);
+    assert_eq!(summary["budgets"].as_array().unwrap().len(), 2);
+    assert_eq!(summary["total_amount"]["minor"], 100000);
+    assert_eq!(summary["missing_rates"][0]["from"], "EUR");
+}
+
+#[tokio::test]
+async fn negative_budget_amount_is_rejected() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let (status, body) = post(&base, &token, "/budget/budgets", json!({
+        "category_id": food, "amount": -1.0, "currency": "CNY", "period": "月",
+        "start_date": "2025-01-01T00:00:00Z", "end_date": "2025-01-31T00:00:00Z",
+    })).await;
+    assert_eq!(status, 400, "{}", body);
+}
+
+#[tokio::test]
+async fn progress_of_unknown_budget_is_404() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let food = create_category(&base, &alice, "餐饮", "支出", None).await;
+    let budget = january_budget(&base, &alice, &food, 1000.0).await;
+
+    let (status, _) = get(&base, &bob, &format!("/budget/budgets/{}/progress", budget)).await;
+    assert_eq!(status, 404);
+    let (status, _) = get(&base, &alice, &format!("/budget/budgets/{}/progress?date=bad", budget)).await;
+    assert_eq!(status, 400);
+    // 订单引用其他账本的分类视为不存在
+    let (status, _) = post(&base, &bob, "/v1/orders", json!({
+        "name": "x", "order_type": "消费", "amount": 1.0, "currency": "CNY",
+        "date": "2025-01-02T12:00:00Z", "category_id": food,
+    })).await;
+    assert_eq!(status, 404);
+}
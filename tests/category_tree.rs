@@ -0,0 +1,228 @@
+mod common;
+
+use common::{create_category, delete, get, patch, post, register, spawn_app, spend};
+use mongodb::bson::oid::ObjectId;
+use serde_json::{json, Value};
+use todo_list::db::{LedgerStore, MemoryStore, StoreError};
+use todo_list::models::category::{subtree_ids, Category};
+
+async fn move_to(base: &str, token: &str, id: &str, parent: Option<&str>) -> (u16, Value) {
+    post(base, token, &format!("/category/categories/{}/move", id), json!({"parent_id": parent})).await
+}
+
+fn names(nodes: &Value) -> Vec<&str> {
+    nodes.as_array().unwrap().iter().map(|n| n["name"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn tree_nests_children_under_their_parents() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let lunch = create_category(&base, &token, "午餐", "支出", Some(&food)).await;
+    create_category(&base, &token, "零食", "支出", Some(&lunch)).await;
+    create_category(&base, &token, "晚餐", "支出", Some(&food)).await;
+    create_category(&base, &token, "交通", "支出", None).await;
+
+    let (status, tree) = get(&base, &token, "/category/tree").await;
+    assert_eq!(status, 200);
+    assert_eq!(names(&tree), ["餐饮", "交通"]);
+    assert_eq!(tree[0]["id"], food.as_str());
+    assert_eq!(names(&tree[0]["children"]), ["午餐", "晚餐"]);
+    assert_eq!(tree[0]["children"][0]["parent_id"], food.as_str());
+    assert_eq!(names(&tree[0]["children"][0]["children"]), ["零食"]);
+    assert!(tree[0].get("total").is_none());
+
+    // 平铺列表保持不变
+    let (_, flat) = get(&base, &token, "/category/categories").await;
+    assert_eq!(flat.as_array().unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn parent_must_be_a_valid_id_and_not_form_a_cycle() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let (status, body) = post(&base, &token, "/category/categories", json!({
+        "name": "午餐", "category_type": "支出", "parent_id": "not-an-id",
+    })).await;
+    assert_eq!(status, 400);
+    assert!(body["message"].as_str().unwrap().contains("not-an-id"));
+
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let lunch = create_category(&base, &token, "午餐", "支出", Some(&food)).await;
+    let snack = create_category(&base, &token, "零食", "支出", Some(&lunch)).await;
+    let (status, _) = move_to(&base, &token, &food, Some(&snack)).await;
+    assert_eq!(status, 400);
+    let (status, _) = patch(&base, &token, &format!("/category/categories/{}", food), json!({"parent_id": lunch})).await;
+    assert_eq!(status, 400);
+    let (status, _) = move_to(&base, &token, &food, Some(&food)).await;
+    assert_eq!(status, 400);
+    let (status, _) = move_to(&base, &token, &food, Some("000000000000000000000000")).await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn depth_is_limited_including_moved_subtrees() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let mut chain = vec![create_category(&base, &token, "第1层", "支出", None).await];
+    for level in 2..=5 {
+        let parent = chain.last().unwrap().clone();
+        chain.push(create_category(&base, &token, &format!("第{}层", level), "支出", Some(&parent)).await);
+    }
+    let (status, body) = post(&base, &token, "/category/categories", json!({
+        "name": "第6层", "category_type": "支出", "parent_id": chain[4],
+    })).await;
+    assert_eq!(status, 400);
+    assert!(body["message"].as_str().unwrap().contains("5"));
+
+    // 两层的子树放到第 4 层下会超过上限，放到第 3 层下正好
+    let other = create_category(&base, &token, "其他", "支出", None).await;
+    create_category(&base, &token, "其他子类", "支出", Some(&other)).await;
+    let (status, _) = move_to(&base, &token, &other, Some(&chain[3])).await;
+    assert_eq!(status, 400);
+    let (status, _) = move_to(&base, &token, &other, Some(&chain[2])).await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+async fn deleting_with_reassign_keeps_the_depth_limit() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let mut chain = vec![create_category(&base, &token, "第1层", "支出", None).await];
+    for level in 2..=4 {
+        let parent = chain.last().unwrap().clone();
+        chain.push(create_category(&base, &token, &format!("第{}层", level), "支出", Some(&parent)).await);
+    }
+    // 删除“其他”后，两层的子分类转到第 4 层下会超过上限，转到第 3 层下正好
+    let other = create_category(&base, &token, "其他", "支出", None).await;
+    let child = create_category(&base, &token, "其他子类", "支出", Some(&other)).await;
+    create_category(&base, &token, "其他孙类", "支出", Some(&child)).await;
+    let (status, body) = delete(&base, &token, &format!("/category/categories/{}?reassign_to={}", other, chain[3])).await;
+    assert_eq!(status, 409, "{}", body);
+    let (_, tree) = get(&base, &token, "/category/tree").await;
+    assert_eq!(tree.as_array().unwrap().len(), 2);
+    let (status, _) = delete(&base, &token, &format!("/category/categories/{}?reassign_to={}", other, chain[2])).await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+async fn move_carries_the_whole_subtree() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let lunch = create_category(&base, &token, "午餐", "支出", Some(&food)).await;
+    create_category(&base, &token, "零食", "支出", Some(&lunch)).await;
+    let daily = create_category(&base, &token, "日常", "支出", None).await;
+
+    let (status, moved) = move_to(&base, &token, &lunch, Some(&daily)).await;
+    assert_eq!(status, 200);
+    assert_eq!(moved["parent_id"]["$oid"], daily.as_str());
+    assert_eq!(moved["name"], "午餐");
+    let (_, tree) = get(&base, &token, "/category/tree").await;
+    assert!(tree[0]["children"].as_array().unwrap().is_empty());
+    assert_eq!(names(&tree[1]["children"]), ["午餐"]);
+    assert_eq!(names(&tree[1]["children"][0]["children"]), ["零食"]);
+
+    // 移到顶级
+    let (status, _) = move_to(&base, &token, &lunch, None).await;
+    assert_eq!(status, 200);
+    let (_, tree) = get(&base, &token, "/category/tree").await;
+    assert_eq!(names(&tree), ["餐饮", "午餐", "日常"]);
+}
+
+#[tokio::test]
+async fn stats_roll_child_spending_up_to_parents() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let (status, _) = post(&base, &token, "/rate/rates", json!({
+        "from": "USD", "to": "CNY", "rate": 7.0, "date": "2025-01-01",
+    })).await;
+    assert_eq!(status, 200);
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let lunch = create_category(&base, &token, "午餐", "支出", Some(&food)).await;
+    let snack = create_category(&base, &token, "零食", "支出", Some(&lunch)).await;
+    create_category(&base, &token, "交通", "支出", None).await;
+    for (category, amount, currency, date) in [
+        (Some(&food), 100.0, "CNY", "2025-01-02"),
+        (Some(&lunch), 50.0, "CNY", "2025-01-03"),
+        (Some(&snack), 10.0, "USD", "2025-01-04"),
+        (None, 30.0, "CNY", "2025-01-05"),
+        (Some(&food), 999.0, "CNY", "2025-02-01"),
+    ] {
+        let (status, body) = post(&base, &token, "/v1/orders", json!({
+            "name": "消费", "order_type": "消费", "amount": amount, "currency": currency,
+            "date": date, "category_id": category,
+        })).await;
+        assert_eq!(status, 201, "{}", body);
+    }
+
+    let (status, stats) = get(&base, &token, "/category/stats?date_start=2025-01-01&date_end=2025-01-31").await;
+    assert_eq!(status, 200, "{}", stats);
+    assert_eq!(stats["order_type"], "消费");
+    assert_eq!(stats["total"], json!({"minor": 25000, "currency": "CNY"}));
+    assert_eq!(stats["uncategorized"]["minor"], 3000);
+    let food_node = &stats["categories"][0];
+    assert_eq!(food_node["amount"]["minor"], 10000);
+    assert_eq!(food_node["total"]["minor"], 22000);
+    assert_eq!(food_node["children"][0]["amount"]["minor"], 5000);
+    assert_eq!(food_node["children"][0]["total"]["minor"], 12000);
+    assert_eq!(food_node["children"][0]["children"][0]["total"]["minor"], 7000);
+    assert_eq!(stats["categories"][1]["total"]["minor"], 0);
+
+    let (_, income) = get(&base, &token, "/category/stats?order_type=收入").await;
+    assert_eq!(income["total"]["minor"], 0);
+}
+
+#[tokio::test]
+async fn stats_skip_orders_without_a_rate() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    spend(&base, &token, &food, 100.0, "CNY", "2025-01-02T12:00:00Z").await;
+    spend(&base, &token, &food, 10.0, "EUR", "2025-01-03T12:00:00Z").await;
+
+    let (status, stats) = get(&base, &token, "/category/stats").await;
+    assert_eq!(status, 200, "{}", stats);
+    assert_eq!(stats["total"]["minor"], 10000);
+    assert_eq!(stats["categories"][0]["total"]["minor"], 10000);
+    assert_eq!(stats["missing_rates"][0]["from"], "EUR");
+}
+
+#[tokio::test]
+async fn store_rechecks_the_parent_when_writing() {
+    let store = MemoryStore::new();
+    let ledger_id = ObjectId::new();
+    let category = |name: &str| Category {
+        id: ObjectId::new(),
+        ledger_id,
+        user_id: ObjectId::new(),
+        name: name.to_string(),
+        parent_id: None,
+        category_type: "支出".to_string(),
+    };
+    let a = store.create_category(category("A")).await.unwrap();
+    let b = store.create_category(category("B")).await.unwrap();
+
+    // 两次移动各自按旧数据校验都能通过，后写入的一次必须在写入时被拒绝
+    assert!(store.update_category(Category { parent_id: Some(b.id), ..a.clone() }).await.unwrap());
+    let err = store.update_category(Category { parent_id: Some(a.id), ..b.clone() }).await.unwrap_err();
+    assert!(matches!(err, StoreError::Conflict(_)));
+    assert_eq!(store.get_category(ledger_id, b.id).await.unwrap().unwrap().parent_id, None);
+}
+
+#[test]
+fn subtree_of_a_cycle_terminates() {
+    let ledger_id = ObjectId::new();
+    let (a, b) = (ObjectId::new(), ObjectId::new());
+    let category = |id: ObjectId, parent_id: ObjectId| Category {
+        id,
+        ledger_id,
+        user_id: ObjectId::new(),
+        name: String::new(),
+        parent_id: Some(parent_id),
+        category_type: "支出".to_string(),
+    };
+    let categories = [category(a, b), category(b, a)];
+    assert_eq!(subtree_ids(&categories, a), [a, b]);
+}
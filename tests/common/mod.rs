@@ -0,0 +1,149 @@
+#![allow(dead_code)]
+
+use axum::{middleware, Router, ServiceExt};
+use axum::extract::Request;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use todo_list::config::Config;
+use todo_list::db::{LedgerStore, MemoryStore};
+use todo_list::routes;
+use todo_list::state::AppState;
+use tower::Layer;
+
+/// 测试使用的 dev 配置
+pub fn test_config() -> Config {
+    Config { profile: "dev".to_string(), ..Config::default() }
+}
+
+/// 基于内存存储启动一个完整的 API 服务，返回其基地址（含 `/api` 前缀）。
+pub async fn spawn_app() -> String {
+    spawn_app_with(Arc::new(MemoryStore::new()), test_config()).await
+}
+
+/// 用指定的存储和配置启动服务；多个服务共用同一存储可模拟重启
+pub async fn spawn_app_with(db: Arc<dyn LedgerStore>, config: Config) -> String {
+    let app = Router::new()
+        .nest("/api", routes::api::api_routes())
+        .with_state(AppState::new(db, config));
+    let app = middleware::map_request(routes::api::select_book_by_path).layer(app);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, ServiceExt::<Request>::into_make_service_with_connect_info::<SocketAddr>(app)).await.unwrap();
+    });
+    format!("http://{}/api", addr)
+}
+
+/// 注册一个新用户并返回其 token。
+pub async fn register(base: &str, username: &str) -> String {
+    let res = reqwest::Client::new()
+        .post(format!("{}/user/register", base))
+        .json(&json!({"username": username, "password": "password123"}))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success(), "注册失败: {}", res.status());
+    let body: Value = res.json().await.unwrap();
+    body["token"].as_str().unwrap().to_string()
+}
+
+/// 用用户名和密码登录，返回状态码和响应体。
+pub async fn login(base: &str, username: &str, password: &str) -> (u16, Value) {
+    post(base, "", "/user/login", json!({"username": username, "password": password})).await
+}
+
+pub async fn get(base: &str, token: &str, path: &str) -> (u16, Value) {
+    let res = reqwest::Client::new()
+        .get(format!("{}{}", base, path))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap();
+    let status = res.status().as_u16();
+    (status, res.json().await.unwrap_or(Value::Null))
+}
+
+pub async fn post(base: &str, token: &str, path: &str, body: Value) -> (u16, Value) {
+    let res = reqwest::Client::new()
+        .post(format!("{}{}", base, path))
+        .bearer_auth(token)
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    let status = res.status().as_u16();
+    (status, res.json().await.unwrap_or(Value::Null))
+}
+
+pub async fn delete(base: &str, token: &str, path: &str) -> (u16, Value) {
+    let res = reqwest::Client::new()
+        .delete(format!("{}{}", base, path))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap();
+    let status = res.status().as_u16();
+    (status, res.json().await.unwrap_or(Value::Null))
+}
+
+pub async fn put(base: &str, token: &str, path: &str, body: Value) -> (u16, Value) {
+    let res = reqwest::Client::new()
+        .put(format!("{}{}", base, path))
+        .bearer_auth(token)
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    let status = res.status().as_u16();
+    (status, res.json().await.unwrap_or(Value::Null))
+}
+
+pub async fn patch(base: &str, token: &str, path: &str, body: Value) -> (u16, Value) {
+    let res = reqwest::Client::new()
+        .patch(format!("{}{}", base, path))
+        .bearer_auth(token)
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    let status = res.status().as_u16();
+    (status, res.json().await.unwrap_or(Value::Null))
+}
+
+/// 带 JSON 请求体的 DELETE
+pub async fn delete_json(base: &str, token: &str, path: &str, body: Value) -> (u16, Value) {
+    let res = reqwest::Client::new()
+        .delete(format!("{}{}", base, path))
+        .bearer_auth(token)
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    let status = res.status().as_u16();
+    (status, res.json().await.unwrap_or(Value::Null))
+}
+
+/// 响应体中的 `id`，兼容 `{"$oid": "..."}` 和十六进制字符串两种格式
+pub fn id_of(value: &Value) -> String {
+    let id = &value["id"];
+    id["$oid"].as_str().or(id.as_str()).unwrap().to_string()
+}
+
+/// 创建分类并返回其 ID
+pub async fn create_category(base: &str, token: &str, name: &str, category_type: &str, parent: Option<&str>) -> String {
+    let (status, body) = post(base, token, "/category/categories", json!({
+        "name": name, "category_type": category_type, "parent_id": parent,
+    })).await;
+    assert_eq!(status, 200, "{}", body);
+    id_of(&body)
+}
+
+/// 在分类下记一笔消费
+pub async fn spend(base: &str, token: &str, category: &str, amount: f64, currency: &str, date: &str) {
+    let (status, body) = post(base, token, "/v1/orders", json!({
+        "name": "消费", "order_type": "消费", "amount": amount, "currency": currency,
+        "date": date, "category_id": category,
+    })).await;
+    assert_eq!(status, 201, "{}", body);
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use todo_list::config::{Config, ConfigError, DEFAULT_JWT_SECRET};
+
+fn env(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let map: HashMap<String, String> = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    move |key| map.get(key).cloned()
+}
+
+#[test]
+fn toml_file_is_merged_with_defaults_and_env_overrides() {
+    let mut config = Config::from_toml(r#"
+        profile = "dev"
+        [database]
+        name = "ledger"
+        [cors]
+        allowed_origins = ["http://localhost:5173"]
+    "#).unwrap();
+    assert_eq!(config.database.name, "ledger");
+    assert_eq!(config.database.uri, "mongodb://localhost:27017");
+    assert_eq!(config.server.bind, "0.0.0.0:3000");
+    assert_eq!(config.auth.access_token_ttl_secs, 60 * 15);
+
+    config.apply_env(env(&[
+        ("APP_DATABASE_URI", "mongodb://db:27017"),
+        ("APP_BIND", "127.0.0.1:8080"),
+        ("APP_ACCESS_TOKEN_TTL_SECS", "900"),
+        ("APP_CORS_ORIGINS", "https://a.example, https://b.example"),
+        ("APP_LOG_LEVEL", "debug"),
+    ])).unwrap();
+    assert_eq!(config.database.uri, "mongodb://db:27017");
+    assert_eq!(config.database.name, "ledger");
+    assert_eq!(config.server.bind, "127.0.0.1:8080");
+    assert_eq!(config.auth.access_token_ttl_secs, 900);
+    assert_eq!(config.cors.allowed_origins, vec!["https://a.example", "https://b.example"]);
+    assert_eq!(config.log.level, "debug");
+    config.validate().unwrap();
+
+    let err = config.apply_env(env(&[("APP_ACCESS_TOKEN_TTL_SECS", "一周")])).unwrap_err();
+    assert!(matches!(err, ConfigError::Env { key: "APP_ACCESS_TOKEN_TTL_SECS", .. }));
+}
+
+#[test]
+fn default_secret_is_only_allowed_in_dev() {
+    let mut config = Config::default();
+    assert_eq!(config.auth.jwt_secret, DEFAULT_JWT_SECRET);
+    assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+
+    config.profile = "dev".to_string();
+    config.validate().unwrap();
+
+    config.profile = "prod".to_string();
+    config.apply_env(env(&[("APP_JWT_SECRET", "0123456789abcdef0123456789abcdef")])).unwrap();
+    config.validate().unwrap();
+
+    config.log.level = "loud".to_string();
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn password_policy_is_configurable() {
+    let mut config = Config::from_toml(r#"
+        profile = "dev"
+        [auth.password]
+        require_symbol = true
+    "#).unwrap();
+    let policy = &config.auth.password;
+    assert_eq!(policy.min_length, 8);
+    assert!(policy.check("password123").is_err());
+    assert!(policy.check("password-123").is_ok());
+
+    config.apply_env(env(&[("APP_PASSWORD_MIN_LENGTH", "0")])).unwrap();
+    assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+    config.apply_env(env(&[("APP_PASSWORD_MIN_LENGTH", "12")])).unwrap();
+    config.validate().unwrap();
+    assert!(config.auth.password.check("password-123").is_ok());
+    assert!(config.auth.password.check("passwo-123").is_err());
+}
+
+#[test]
+fn login_lock_doubles_up_to_the_limit() {
+    let limit = Config::default().auth.login_limit;
+    assert_eq!(limit.lock_secs(limit.max_failures - 1), 0);
+    assert_eq!(limit.lock_secs(limit.max_failures), limit.base_lock_secs);
+    assert_eq!(limit.lock_secs(limit.max_failures + 2), limit.base_lock_secs * 4);
+    assert_eq!(limit.lock_secs(u32::MAX), limit.max_lock_secs);
+}
+
+#[test]
+fn notification_settings_are_validated() {
+    let mut config = Config::from_toml(r#"
+        profile = "dev"
+        [notifications.smtp]
+        host = "localhost"
+        port = 2525
+        from = "账本 <noreply@example.com>"
+    "#).unwrap();
+    assert_eq!(config.notifications.budget_thresholds, vec![80, 100]);
+    config.validate().unwrap();
+
+    config.apply_env(env(&[("APP_BUDGET_ALERT_THRESHOLDS", "50, 90"), ("APP_WEBHOOK_URL", "http://hooks.local/budget")])).unwrap();
+    assert_eq!(config.notifications.budget_thresholds, vec![50, 90]);
+    config.validate().unwrap();
+
+    config.apply_env(env(&[("APP_BUDGET_ALERT_THRESHOLDS", "0")])).unwrap();
+    assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+    let err = config.apply_env(env(&[("APP_BUDGET_ALERT_THRESHOLDS", "八十")])).unwrap_err();
+    assert!(matches!(err, ConfigError::Env { key: "APP_BUDGET_ALERT_THRESHOLDS", .. }));
+
+    config.apply_env(env(&[("APP_BUDGET_ALERT_THRESHOLDS", "80"), ("APP_WEBHOOK_URL", "ftp://hooks.local")])).unwrap();
+    assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use common::{create_category, delete, get, id_of, patch, post, put, register, spawn_app};
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn account_update_adjusts_balance_and_delete_checks_references() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let (_, account) = post(&base, &token, "/account/accounts", json!({
+        "name": "现金", "account_type": "现金", "balance": 100.0, "currency": "CNY",
+    })).await;
+    let account_id = id_of(&account);
+    post(&base, &token, "/transaction/orders", json!({
+        "account_id": account_id, "name": "午饭", "order_type": "消费",
+        "amount": 30.0, "currency": "CNY", "date": "2025-01-15T12:00:00Z",
+    })).await;
+
+    // 期初余额 100 => 200，当前余额随之 70 => 170
+    let (status, account) = patch(&base, &token, &format!("/account/accounts/{}", account_id), json!({
+        "balance": 200.0, "remark": "钱包",
+    })).await;
+    assert_eq!(status, 200);
+    assert_eq!(account["balance"]["minor"], 17000);
+    assert_eq!(account["initial_balance"]["minor"], 20000);
+    assert_eq!(account["remark"], "钱包");
+    assert_eq!(account["name"], "现金");
+
+    // PATCH 中显式 null 清空备注
+    let (_, account) = patch(&base, &token, &format!("/account/accounts/{}", account_id), json!({"remark": null})).await;
+    assert_eq!(account["remark"], Value::Null);
+
+    // 已有订单的账户不能更换币种
+    let (status, _) = put(&base, &token, &format!("/account/accounts/{}", account_id), json!({
+        "name": "现金", "account_type": "现金", "balance": 200.0, "currency": "USD",
+    })).await;
+    assert_ne!(status, 200);
+
+    // 仍有订单引用时不能删除
+    let (status, body) = delete(&base, &token, &format!("/account/accounts/{}", account_id)).await;
+    assert_ne!(status, 200);
+    assert!(body["message"].as_str().unwrap().contains("1 条订单"));
+
+    let (_, orders) = get(&base, &token, "/transaction/orders").await;
+    let order_id = id_of(&orders[0]);
+    delete(&base, &token, &format!("/order/{}", order_id)).await;
+    let (status, _) = delete(&base, &token, &format!("/account/accounts/{}", account_id)).await;
+    assert_eq!(status, 200);
+    let (status, _) = get(&base, &token, &format!("/account/accounts/{}", account_id)).await;
+    assert_ne!(status, 200);
+}
+
+#[tokio::test]
+async fn category_delete_requires_reassign_when_referenced() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let lunch = create_category(&base, &token, "午饭", "支出", Some(&food)).await;
+    let other = create_category(&base, &token, "其他", "支出", None).await;
+    let (status, budget) = post(&base, &token, "/budget/budgets", json!({
+        "category_id": food, "amount": 1000.0, "currency": "CNY", "period": "月",
+        "start_date": "2025-01-01T00:00:00Z", "end_date": "2025-01-31T23:59:59Z",
+    })).await;
+    assert_eq!(status, 200);
+    let budget_id = id_of(&budget);
+
+    let (status, body) = delete(&base, &token, &format!("/category/categories/{}", food)).await;
+    assert_ne!(status, 200);
+    assert!(body["message"].as_str().unwrap().contains("1 个预算"));
+
+    // 不能转移到自身的子分类
+    let (status, _) = delete(&base, &token, &format!("/category/categories/{}?reassign_to={}", food, lunch)).await;
+    assert_ne!(status, 200);
+
+    let (status, _) = delete(&base, &token, &format!("/category/categories/{}?reassign_to={}", food, other)).await;
+    assert_eq!(status, 200);
+    let (_, budget) = get(&base, &token, &format!("/budget/budgets/{}", budget_id)).await;
+    assert_eq!(budget["category_id"]["$oid"], other);
+    let (_, child) = get(&base, &token, &format!("/category/categories/{}", lunch)).await;
+    assert_eq!(child["parent_id"]["$oid"], other);
+}
+
+#[tokio::test]
+async fn category_parent_must_exist_and_not_be_self() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+
+    let (status, _) = patch(&base, &token, &format!("/category/categories/{}", food), json!({"parent_id": food})).await;
+    assert_ne!(status, 200);
+    let missing = "000000000000000000000000";
+    let (status, _) = post(&base, &token, "/category/categories", json!({
+        "name": "午饭", "parent_id": missing, "category_type": "支出",
+    })).await;
+    assert_ne!(status, 200);
+
+    let (status, category) = patch(&base, &token, &format!("/category/categories/{}", food), json!({"name": "吃饭"})).await;
+    assert_eq!(status, 200);
+    assert_eq!(category["name"], "吃饭");
+    assert_eq!(category["category_type"], "支出");
+}
+
+#[tokio::test]
+async fn asset_and_budget_round_trip() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let (_, account) = post(&base, &token, "/account/accounts", json!({
+        "name": "证券", "account_type": "投资", "balance": 0.0, "currency": "CNY",
+    })).await;
+    let account_id = id_of(&account);
+    let (status, asset) = post(&base, &token, "/asset/assets", json!({
+        "name": "基金", "asset_type": "基金", "value": 5000.0, "currency": "CNY", "account_id": account_id,
+    })).await;
+    assert_eq!(status, 200);
+    let asset_id = id_of(&asset);
+
+    let (status, asset) = patch(&base, &token, &format!("/asset/assets/{}", asset_id), json!({"value": 5200.5})).await;
+    assert_eq!(status, 200);
+    assert_eq!(asset["value"]["minor"], 520050);
+    assert_eq!(asset["name"], "基金");
+
+    // 只改名称时金额原样保留；只改币种不给金额会按新币种的小数位误读原金额，因此拒绝
+    let (status, asset) = patch(&base, &token, &format!("/asset/assets/{}", asset_id), json!({"name": "指数基金"})).await;
+    assert_eq!(status, 200);
+    assert_eq!(asset["value"], json!({"minor": 520050, "currency": "CNY"}));
+    let (status, _) = patch(&base, &token, &format!("/asset/assets/{}", asset_id), json!({"currency": "JPY"})).await;
+    assert_eq!(status, 400);
+    let (status, asset) = patch(&base, &token, &format!("/asset/assets/{}", asset_id), json!({
+        "value": 100000.0, "currency": "JPY",
+    })).await;
+    assert_eq!(status, 200);
+    assert_eq!(asset["value"], json!({"minor": 100000, "currency": "JPY"}));
+
+    // 资产引用的账户不能删除
+    let (status, _) = delete(&base, &token, &format!("/account/accounts/{}", account_id)).await;
+    assert_ne!(status, 200);
+    let (status, _) = delete(&base, &token, &format!("/asset/assets/{}", asset_id)).await;
+    assert_eq!(status, 200);
+    let (status, _) = get(&base, &token, &format!("/asset/assets/{}", asset_id)).await;
+    assert_ne!(status, 200);
+
+    let category = create_category(&base, &token, "餐饮", "支出", None).await;
+    let (_, budget) = post(&base, &token, "/budget/budgets", json!({
+        "category_id": category, "amount": 1000.0, "currency": "CNY", "period": "月",
+        "start_date": "2025-01-01T00:00:00Z", "end_date": "2025-01-31T23:59:59Z",
+    })).await;
+    let budget_id = id_of(&budget);
+    let (status, budget) = patch(&base, &token, &format!("/budget/budgets/{}", budget_id), json!({"amount": 1500.0})).await;
+    assert_eq!(status, 200);
+    assert_eq!(budget["amount"]["minor"], 150000);
+    let (status, _) = patch(&base, &token, &format!("/budget/budgets/{}", budget_id), json!({
+        "end_date": "2024-12-01T00:00:00Z",
+    })).await;
+    assert_ne!(status, 200);
+    let (status, _) = delete(&base, &token, &format!("/budget/budgets/{}", budget_id)).await;
+    assert_eq!(status, 200);
+    let (_, budgets) = get(&base, &token, "/budget/budgets").await;
+    assert_eq!(budgets.as_array().unwrap().len(), 0);
+}
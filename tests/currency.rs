@@ -0,0 +1,98 @@
+mod common;
+
+use common::{create_category, post, register, spawn_app};
+use serde_json::json;
+
+#[tokio::test]
+async fn chinese_alias_is_stored_as_iso_code() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+
+    let (status, account) = post(&base, &token, "/account/accounts", json!({
+        "name": "日元现金",
+        "account_type": "现金",
+        "balance": 1500.4,
+        "currency": "日元",
+    })).await;
+    assert_eq!(status, 200);
+    assert_eq!(account["balance"]["currency"], "JPY");
+    assert_eq!(account["balance"]["minor"], 1500);
+
+    let (status, order) = post(&base, &token, "/transaction/orders", json!({
+        "name": "咖啡",
+        "order_type": "消费",
+        "amount": 3.5,
+        "currency": "usd",
+        "date": "2025-01-15T12:00:00Z",
+    })).await;
+    assert_eq!(status, 200);
+    assert_eq!(order["amount"]["currency"], "USD");
+    assert_eq!(order["amount"]["minor"], 350);
+}
+
+#[tokio::test]
+async fn unknown_currency_is_rejected() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+
+    let (status, _) = post(&base, &token, "/account/accounts", json!({
+        "name": "现金",
+        "account_type": "现金",
+        "balance": 10.0,
+        "currency": "人民b",
+    })).await;
+    assert_eq!(status, 400);
+
+    // 各类请求体中的未知币种都返回指明该币种的参数错误
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let requests = [
+        ("/account/accounts", json!({"name": "现金", "account_type": "现金", "balance": 10.0, "currency": "XYZ"})),
+        ("/asset/assets", json!({"name": "基金", "asset_type": "基金", "value": 10.0, "currency": "XYZ"})),
+        ("/v1/orders", json!({"name": "午饭", "order_type": "消费", "amount": 10.0, "currency": "XYZ", "date": "2025-01-15"})),
+        ("/budget/budgets", json!({
+            "category_id": food, "amount": 10.0, "currency": "XYZ", "period": "monthly", "start_date": "2025-01-01",
+        })),
+        ("/rate/rates", json!({"from": "XYZ", "to": "CNY", "rate": 1.0, "date": "2025-01-01"})),
+    ];
+    for (path, body) in requests {
+        let (status, body) = post(&base, &token, path, body).await;
+        assert_eq!(status, 400, "{}", path);
+        assert_eq!(body["code"], "validation_error");
+        assert!(body["message"].as_str().unwrap().contains("不支持的币种: XYZ"), "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn out_of_range_amounts_are_rejected() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+
+    let (status, body) = post(&base, &token, "/account/accounts", json!({
+        "name": "现金",
+        "account_type": "现金",
+        "balance": 1e300,
+        "currency": "CNY",
+    })).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "validation_error");
+
+    let (status, _) = post(&base, &token, "/v1/orders", json!({
+        "name": "咖啡",
+        "order_type": "消费",
+        "amount": 1e17,
+        "currency": "CNY",
+        "date": "2025-01-15",
+    })).await;
+    assert_eq!(status, 400);
+
+    // 上限以内的大额仍然可以记录
+    let (status, order) = post(&base, &token, "/v1/orders", json!({
+        "name": "房产",
+        "order_type": "消费",
+        "amount": 1e12,
+        "currency": "CNY",
+        "date": "2025-01-15",
+    })).await;
+    assert_eq!(status, 201);
+    assert_eq!(order["amount"]["minor"], 100_000_000_000_000i64);
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use axum::response::IntoResponse;
+use common::{delete, get, post, register, spawn_app};
+use serde_json::{json, Value};
+use todo_list::db::StoreError;
+use todo_list::error::AppError;
+
+#[tokio::test]
+async fn errors_map_to_status_and_code() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+
+    // 401：缺少 token
+    let res = reqwest::Client::new().get(format!("{}/account/accounts", base)).send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+    let body: Value = res.json().await.unwrap();
+    assert_eq!(body["code"], "unauthorized");
+
+    // 400：参数不合法
+    let (status, body) = post(&base, &token, "/rate/rates", json!({
+        "from": "CNY", "to": "CNY", "rate": 1.0, "date": "2025-01-01",
+    })).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "validation_error");
+    assert_eq!(body["message"], "源币种和目标币种不能相同");
+    let (status, body) = get(&base, &token, "/account/accounts/not-an-id").await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "validation_error");
+
+    // 404：不存在
+    let (status, body) = get(&base, &token, "/account/accounts/000000000000000000000000").await;
+    assert_eq!(status, 404);
+    assert_eq!(body["code"], "not_found");
+    assert_eq!(body["message"], "账户不存在");
+
+    // 409：仍被引用
+    let (_, account) = post(&base, &token, "/account/accounts", json!({
+        "name": "现金", "account_type": "现金", "balance": 0.0, "currency": "CNY",
+    })).await;
+    let account_id = account["id"]["$oid"].as_str().unwrap();
+    post(&base, &token, "/v1/orders", json!({
+        "account_id": account_id, "name": "红包", "order_type": "收入",
+        "amount": 10.0, "currency": "CNY", "date": "2025-01-15",
+    })).await;
+    let (status, body) = delete(&base, &token, &format!("/account/accounts/{}", account_id)).await;
+    assert_eq!(status, 409);
+    assert_eq!(body["code"], "conflict");
+}
+
+#[tokio::test]
+async fn register_and_login_report_errors_in_error_body() {
+    let base = spawn_app().await;
+    register(&base, "alice").await;
+
+    let client = reqwest::Client::new();
+    let res = client.post(format!("{}/user/register", base))
+        .json(&json!({"username": "alice", "password": "password123"}))
+        .send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 409);
+    let body: Value = res.json().await.unwrap();
+    assert_eq!(body["code"], "conflict");
+    assert!(body.get("token").is_none());
+
+    let res = client.post(format!("{}/user/login", base))
+        .json(&json!({"username": "alice", "password": "wrong"}))
+        .send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+    let body: Value = res.json().await.unwrap();
+    assert_eq!(body["code"], "unauthorized");
+    assert!(body.get("token").is_none());
+}
+
+#[tokio::test]
+async fn malformed_requests_get_the_error_body() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let client = reqwest::Client::new();
+
+    // 语法错误的 JSON 在进入处理函数之前就被拒绝，同样返回统一的错误体
+    let res = client.post(format!("{}/account/accounts", base))
+        .bearer_auth(&token)
+        .header("content-type", "application/json")
+        .body(r#"{"name": "现金", "balance": "#)
+        .send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 400);
+    let body: Value = res.json().await.unwrap();
+    assert_eq!(body["code"], "validation_error");
+    assert!(body["message"].as_str().unwrap().starts_with("请求体不合法"));
+
+    // 缺少字段、查询参数类型不对
+    let (status, body) = post(&base, &token, "/account/accounts", json!({"name": "现金"})).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "validation_error");
+    let (status, body) = get(&base, &token, "/v1/orders?page=abc").await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "validation_error");
+}
+
+#[tokio::test]
+async fn server_errors_hide_internal_details() {
+    let res = AppError::Storage(StoreError::Migration("connection refused at 10.0.0.1".to_string())).into_response();
+    assert_eq!(res.status().as_u16(), 500);
+    let body: Value = serde_json::from_slice(&axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap()).unwrap();
+    assert_eq!(body, json!({"code": "storage_error", "message": "服务器内部错误"}));
+
+    let res = AppError::Internal("bcrypt: invalid cost".to_string()).into_response();
+    let body: Value = serde_json::from_slice(&axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap()).unwrap();
+    assert_eq!(body["message"], "服务器内部错误");
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use common::{get, post, put, register, spawn_app};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde_json::json;
+use todo_list::models::currency::Currency;
+use todo_list::models::exchange_rate::{ExchangeRate, RateTable};
+
+async fn spend(base: &str, token: &str, amount: f64, currency: &str, date: &str) {
+    let (status, _) = post(base, token, "/transaction/orders", json!({
+        "name": "消费",
+        "order_type": "消费",
+        "amount": amount,
+        "currency": currency,
+        "date": date,
+    })).await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+async fn order_stats_use_rate_effective_on_order_date() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+
+    let csv = "date,from,to,rate\n2025-01-01,USD,CNY,7.0\n2025-02-01,USD,CNY,7.2\n";
+    let res = reqwest::Client::new()
+        .post(format!("{}/rate/rates/import", base))
+        .bearer_auth(&token)
+        .body(csv)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 200);
+
+    spend(&base, &token, 10.0, "USD", "2025-01-15T00:00:00Z").await;
+    spend(&base, &token, 10.0, "USD", "2025-02-15T00:00:00Z").await;
+    spend(&base, &token, 5.0, "人民币", "2025-02-15T00:00:00Z").await;
+
+    let (status, body) = get(&base, &token, "/order_query/orders/query").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["base_currency"], "CNY");
+    assert_eq!(body["stat"]["消费"]["minor"], 7000 + 7200 + 500);
+    assert!(body["missing_rates"].as_array().unwrap().is_empty());
+
+    // 切换本位币后按反向汇率换算
+    let (status, _) = put(&base, &token, "/user/settings", json!({"base_currency": "USD"})).await;
+    assert_eq!(status, 200);
+    let (_, body) = get(&base, &token, "/order_query/orders/query?order_type=消费&date_start=2025-01-01T00:00:00Z&date_end=2025-01-31T00:00:00Z").await;
+    assert_eq!(body["stat"]["消费"], json!({"minor": 1000, "currency": "USD"}));
+}
+
+#[tokio::test]
+async fn rate_recorded_during_the_day_applies_from_midnight() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let rates = [(6.0, "2025-01-01T00:00:00Z"), (7.0, "2025-01-10T18:00:00Z")];
+    for (rate, date) in rates {
+        let (status, _) = post(&base, &token, "/rate/rates", json!({
+            "from": "USD", "to": "CNY", "rate": rate, "date": date,
+        })).await;
+        assert_eq!(status, 200);
+    }
+    spend(&base, &token, 10.0, "USD", "2025-01-09T20:00:00Z").await;
+    spend(&base, &token, 10.0, "USD", "2025-01-10T08:00:00Z").await;
+    spend(&base, &token, 10.0, "USD", "2025-01-10T20:00:00Z").await;
+
+    // 按自然日分组的合计：当天登记的汇率对整天生效
+    let (_, body) = get(&base, &token, "/order_query/orders/query").await;
+    assert_eq!(body["stat"]["消费"]["minor"], 6000 + 7000 + 7000);
+
+    // 按订单时间逐笔查询得到相同的汇率
+    let table = RateTable::new(rates.iter().map(|&(rate, date)| ExchangeRate {
+        id: ObjectId::new(),
+        user_id: ObjectId::new(),
+        from: Currency::USD,
+        to: Currency::CNY,
+        rate,
+        date: DateTime::parse_rfc3339_str(date).unwrap(),
+    }).collect());
+    for (date, expected) in [("2025-01-09T20:00:00Z", 6.0), ("2025-01-10T08:00:00Z", 7.0), ("2025-01-10T20:00:00Z", 7.0)] {
+        let date = DateTime::parse_rfc3339_str(date).unwrap();
+        assert_eq!(table.rate(Currency::USD, Currency::CNY, date), Some(expected));
+    }
+}
+
+#[tokio::test]
+async fn missing_rate_is_reported() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    spend(&base, &token, 10.0, "EUR", "2025-01-15T00:00:00Z").await;
+    spend(&base, &token, 20.0, "CNY", "2025-01-15T00:00:00Z").await;
+
+    // 订单照常返回，缺少汇率的金额不计入统计并单独列出
+    let (status, body) = get(&base, &token, "/order_query/orders/query").await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["orders"].as_array().unwrap().len(), 2);
+    assert_eq!(body["stat"]["消费"]["minor"], 2000);
+    assert_eq!(body["missing_rates"].as_array().unwrap().len(), 1);
+    assert_eq!(body["missing_rates"][0]["from"], "EUR");
+    assert_eq!(body["missing_rates"][0]["to"], "CNY");
+}
+
+#[tokio::test]
+async fn net_worth_sums_accounts_and_assets_in_base_currency() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    post(&base, &token, "/rate/rates", json!({"from": "USD", "to": "CNY", "rate": 7.0, "date": "2025-01-01"})).await;
+
+    let (_, account) = post(&base, &token, "/account/accounts", json!({
+        "name": "美元卡", "account_type": "银行卡", "balance": 100.0, "currency": "USD",
+    })).await;
+    post(&base, &token, "/account/accounts", json!({
+        "name": "现金", "account_type": "现金", "balance": 50.0, "currency": "CNY",
+    })).await;
+    let (status, _) = post(&base, &token, "/asset/assets", json!({
+        "name": "基金", "asset_type": "基金", "value": 1000.0, "currency": "CNY",
+        "account_id": account["id"]["$oid"],
+    })).await;
+    assert_eq!(status, 200);
+
+    let (status, body) = get(&base, &token, "/account/net_worth").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["accounts"]["minor"], 70000 + 5000);
+    assert_eq!(body["assets"]["minor"], 100000);
+    assert_eq!(body["total"], json!({"minor": 175000, "currency": "CNY"}));
+    assert_eq!(body["missing_rates"], json!([]));
+}
+
+#[tokio::test]
+async fn net_worth_lists_accounts_without_a_rate_instead_of_failing() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    post(&base, &token, "/account/accounts", json!({
+        "name": "欧元卡", "account_type": "银行卡", "balance": 100.0, "currency": "EUR",
+    })).await;
+    post(&base, &token, "/account/accounts", json!({
+        "name": "现金", "account_type": "现金", "balance": 50.0, "currency": "CNY",
+    })).await;
+
+    let (status, body) = get(&base, &token, "/account/net_worth").await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["total"]["minor"], 5000);
+    assert_eq!(body["missing_rates"].as_array().unwrap().len(), 1);
+    assert_eq!(body["missing_rates"][0]["from"], "EUR");
+}
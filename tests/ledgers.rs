@@ -0,0 +1,250 @@
+mod common;
+
+use common::{delete, get, id_of, post, put, register, spawn_app};
+use serde_json::{json, Value};
+
+/// 用户的默认（个人）账本 ID
+async fn personal_ledger(base: &str, token: &str) -> String {
+    let (status, ledgers) = get(base, token, "/ledger/ledgers").await;
+    assert_eq!(status, 200);
+    let ledgers = ledgers.as_array().unwrap();
+    assert_eq!(ledgers.len(), 1);
+    assert_eq!(ledgers[0]["role"], "owner");
+    ledgers[0]["id"].as_str().unwrap().to_string()
+}
+
+/// owner 邀请 invitee 以指定角色加入账本，invitee 接受
+async fn invite_and_accept(base: &str, owner: &str, ledger: &str, invitee: &str, username: &str, role: &str) {
+    let (status, invitation) = post(base, owner, &format!("/ledger/ledgers/{}/invitations", ledger), json!({
+        "username": username, "role": role,
+    })).await;
+    assert_eq!(status, 200, "{}", invitation);
+    let (status, pending) = get(base, invitee, "/ledger/invitations").await;
+    assert_eq!(status, 200);
+    assert_eq!(pending[0]["ledger_id"], ledger);
+    let id = invitation["id"].as_str().unwrap();
+    let (status, accepted) = post(base, invitee, &format!("/ledger/invitations/{}/accept", id), json!({})).await;
+    assert_eq!(status, 200);
+    assert_eq!(accepted["role"], role);
+}
+
+/// 带 X-Ledger-Id 头的请求
+async fn send(method: reqwest::Method, base: &str, token: &str, ledger: &str, path: &str, body: Option<Value>) -> (u16, Value) {
+    let mut req = reqwest::Client::new()
+        .request(method, format!("{}{}", base, path))
+        .bearer_auth(token)
+        .header("X-Ledger-Id", ledger);
+    if let Some(body) = body {
+        req = req.json(&body);
+    }
+    let res = req.send().await.unwrap();
+    let status = res.status().as_u16();
+    (status, res.json().await.unwrap_or(Value::Null))
+}
+
+fn account_body() -> Value {
+    json!({"name": "家庭卡", "account_type": "银行卡", "balance": 100.0, "currency": "CNY"})
+}
+
+#[tokio::test]
+async fn registration_creates_a_personal_ledger_used_by_default() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let ledger = personal_ledger(&base, &token).await;
+
+    let (status, _) = post(&base, &token, "/account/accounts", account_body()).await;
+    assert_eq!(status, 200);
+    // 不带请求头与显式指定默认账本看到的是同一份数据
+    let (_, implicit) = get(&base, &token, "/account/accounts").await;
+    let (_, explicit) = send(reqwest::Method::GET, &base, &token, &ledger, "/account/accounts", None).await;
+    assert_eq!(implicit.as_array().unwrap().len(), 1);
+    assert_eq!(implicit, explicit);
+}
+
+#[tokio::test]
+async fn editor_can_write_and_sees_shared_data() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let ledger = personal_ledger(&base, &alice).await;
+    invite_and_accept(&base, &alice, &ledger, &bob, "Bob", "editor").await;
+
+    let (status, account) = send(reqwest::Method::POST, &base, &bob, &ledger, "/account/accounts", Some(account_body())).await;
+    assert_eq!(status, 200);
+    let (_, accounts) = get(&base, &alice, "/account/accounts").await;
+    assert_eq!(accounts[0]["id"]["$oid"], id_of(&account));
+
+    // bob 自己的个人账本不受影响
+    let (_, own) = get(&base, &bob, "/account/accounts").await;
+    assert_eq!(own.as_array().unwrap().len(), 0);
+    let (_, ledgers) = get(&base, &bob, "/ledger/ledgers").await;
+    assert_eq!(ledgers.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn viewer_can_read_but_not_write() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let carol = register(&base, "carol").await;
+    let ledger = personal_ledger(&base, &alice).await;
+    let (_, account) = post(&base, &alice, "/account/accounts", account_body()).await;
+    invite_and_accept(&base, &alice, &ledger, &carol, "carol", "viewer").await;
+
+    let path = format!("/account/accounts/{}", id_of(&account));
+    let (status, _) = send(reqwest::Method::GET, &base, &carol, &ledger, &path, None).await;
+    assert_eq!(status, 200);
+    let (status, body) = send(reqwest::Method::POST, &base, &carol, &ledger, "/account/accounts", Some(account_body())).await;
+    assert_eq!(status, 403);
+    assert_eq!(body["code"], "forbidden");
+    let (status, _) = send(reqwest::Method::DELETE, &base, &carol, &ledger, &path, None).await;
+    assert_eq!(status, 403);
+}
+
+#[tokio::test]
+async fn non_members_get_404_for_the_ledger() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let mallory = register(&base, "mallory").await;
+    let ledger = personal_ledger(&base, &alice).await;
+
+    let (status, _) = send(reqwest::Method::GET, &base, &mallory, &ledger, "/account/accounts", None).await;
+    assert_eq!(status, 404);
+    let (status, _) = get(&base, &mallory, &format!("/ledger/ledgers/{}", ledger)).await;
+    assert_eq!(status, 404);
+    let (status, _) = send(reqwest::Method::GET, &base, &mallory, "not-an-id", "/account/accounts", None).await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn invitations_are_validated() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let ledger = personal_ledger(&base, &alice).await;
+    let path = format!("/ledger/ledgers/{}/invitations", ledger);
+
+    let (status, _) = post(&base, &alice, &path, json!({"username": "nobody", "role": "editor"})).await;
+    assert_eq!(status, 404);
+    let (status, _) = post(&base, &alice, &path, json!({"username": "bob", "role": "owner"})).await;
+    assert_eq!(status, 400);
+    let (status, _) = post(&base, &alice, &path, json!({"username": "alice", "role": "editor"})).await;
+    assert_eq!(status, 409);
+    let (status, _) = post(&base, &alice, &path, json!({"username": "bob", "role": "editor"})).await;
+    assert_eq!(status, 200);
+    let (status, _) = post(&base, &alice, &path, json!({"username": "bob", "role": "viewer"})).await;
+    assert_eq!(status, 409);
+    // 非所有者不能邀请，也看不到待处理的邀请
+    let (status, _) = get(&base, &bob, &path).await;
+    assert_eq!(status, 404);
+
+    let (_, pending) = get(&base, &bob, "/ledger/invitations").await;
+    let id = pending[0]["id"].as_str().unwrap();
+    assert_eq!(pending[0]["inviter"], "alice");
+    // 只有被邀请人能处理邀请
+    let (status, _) = post(&base, &alice, &format!("/ledger/invitations/{}/accept", id), json!({})).await;
+    assert_eq!(status, 404);
+    let (status, _) = post(&base, &bob, &format!("/ledger/invitations/{}/decline", id), json!({})).await;
+    assert_eq!(status, 200);
+    let (_, pending) = get(&base, &bob, "/ledger/invitations").await;
+    assert_eq!(pending.as_array().unwrap().len(), 0);
+    let (status, _) = send(reqwest::Method::GET, &base, &bob, &ledger, "/account/accounts", None).await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn only_the_owner_manages_members() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let carol = register(&base, "carol").await;
+    let ledger = personal_ledger(&base, &alice).await;
+    invite_and_accept(&base, &alice, &ledger, &bob, "bob", "editor").await;
+    invite_and_accept(&base, &alice, &ledger, &carol, "carol", "viewer").await;
+
+    let (_, detail) = get(&base, &alice, &format!("/ledger/ledgers/{}", ledger)).await;
+    let members = detail["members"].as_array().unwrap();
+    assert_eq!(members.len(), 3);
+    let id_of_member = |name: &str| {
+        members.iter().find(|m| m["username"] == name).unwrap()["user_id"].as_str().unwrap().to_string()
+    };
+    let (alice_id, bob_id, carol_id) = (id_of_member("alice"), id_of_member("bob"), id_of_member("carol"));
+    let member_path = |id: &str| format!("/ledger/ledgers/{}/members/{}", ledger, id);
+
+    // 编辑者不能改角色或移除他人
+    let (status, _) = put(&base, &bob, &member_path(&carol_id), json!({"role": "editor"})).await;
+    assert_eq!(status, 403);
+    let (status, _) = delete(&base, &bob, &member_path(&carol_id)).await;
+    assert_eq!(status, 403);
+
+    // 所有者把 carol 升为编辑者后她可以记账
+    let (status, _) = put(&base, &alice, &member_path(&carol_id), json!({"role": "editor"})).await;
+    assert_eq!(status, 200);
+    let (status, _) = send(reqwest::Method::POST, &base, &carol, &ledger, "/account/accounts", Some(account_body())).await;
+    assert_eq!(status, 200);
+
+    // 所有者不能被降级，也不能退出
+    let (status, _) = put(&base, &alice, &member_path(&alice_id), json!({"role": "viewer"})).await;
+    assert_eq!(status, 404);
+    let (status, _) = delete(&base, &alice, &member_path(&alice_id)).await;
+    assert_eq!(status, 400);
+
+    // 成员可以自己退出，所有者可以移除成员
+    let (status, _) = delete(&base, &bob, &member_path(&bob_id)).await;
+    assert_eq!(status, 200);
+    let (status, _) = delete(&base, &alice, &member_path(&carol_id)).await;
+    assert_eq!(status, 200);
+    let (status, _) = send(reqwest::Method::GET, &base, &carol, &ledger, "/account/accounts", None).await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn membership_changes_require_a_login_session() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    register(&base, "bob").await;
+    let ledger = personal_ledger(&base, &alice).await;
+    let (_, key) = post(&base, &alice, "/user/api_keys", json!({"name": "脚本", "scope": "read_write"})).await;
+    let key = key["key"].as_str().unwrap();
+
+    // 读写密钥可以记账，但不能邀请成员或删除账本
+    let (status, _) = send(reqwest::Method::POST, &base, key, &ledger, "/account/accounts", Some(account_body())).await;
+    assert_eq!(status, 200);
+    let (status, _) = post(&base, key, &format!("/ledger/ledgers/{}/invitations", ledger), json!({
+        "username": "bob", "role": "editor",
+    })).await;
+    assert_eq!(status, 401);
+    let (_, other) = post(&base, &alice, "/ledger/ledgers", json!({"name": "旅行"})).await;
+    let (status, _) = delete(&base, key, &format!("/ledger/ledgers/{}", id_of(&other))).await;
+    assert_eq!(status, 401);
+}
+
+#[tokio::test]
+async fn deleting_the_owner_hands_shared_ledgers_to_a_member() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let carol = register(&base, "carol").await;
+    let ledger = personal_ledger(&base, &alice).await;
+    invite_and_accept(&base, &alice, &ledger, &carol, "carol", "viewer").await;
+    invite_and_accept(&base, &alice, &ledger, &bob, "bob", "editor").await;
+    let (status, _) = send(reqwest::Method::POST, &base, &bob, &ledger, "/account/accounts", Some(account_body())).await;
+    assert_eq!(status, 200);
+
+    let (status, _) = common::delete_json(&base, &alice, "/user/me", json!({"password": "password123"})).await;
+    assert_eq!(status, 200);
+
+    // 共享账本由编辑者接手，数据和其他成员保留
+    let (status, detail) = get(&base, &bob, &format!("/ledger/ledgers/{}", ledger)).await;
+    assert_eq!(status, 200);
+    assert_eq!(detail["role"], "owner");
+    let roles: Vec<_> = detail["members"].as_array().unwrap().iter()
+        .map(|m| (m["username"].as_str().unwrap(), m["role"].as_str().unwrap()))
+        .collect();
+    assert_eq!(roles, [("carol", "viewer"), ("bob", "owner")]);
+    let (_, accounts) = send(reqwest::Method::GET, &base, &bob, &ledger, "/account/accounts", None).await;
+    assert_eq!(accounts.as_array().unwrap().len(), 1);
+    let (status, _) = send(reqwest::Method::GET, &base, &carol, &ledger, "/account/accounts", None).await;
+    assert_eq!(status, 200);
+    let (_, ledgers) = get(&base, &bob, "/ledger/ledgers").await;
+    assert_eq!(ledgers.as_array().unwrap().len(), 2);
+}
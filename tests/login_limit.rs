@@ -0,0 +1,115 @@
+mod common;
+
+use common::{login, register, spawn_app_with, test_config};
+use serde_json::json;
+use std::sync::Arc;
+use todo_list::config::Config;
+use todo_list::db::{LedgerStore, MemoryStore};
+
+fn limited_config() -> Config {
+    let mut config = test_config();
+    config.auth.login_limit.max_failures = 3;
+    config.auth.login_limit.base_lock_secs = 60;
+    config
+}
+
+#[tokio::test]
+async fn repeated_failures_lock_username_and_survive_restart() {
+    let db: Arc<dyn LedgerStore> = Arc::new(MemoryStore::new());
+    let base = spawn_app_with(db.clone(), limited_config()).await;
+    register(&base, "alice").await;
+
+    for _ in 0..3 {
+        let (status, body) = login(&base, "alice", "wrong-password1").await;
+        assert_eq!(status, 401);
+        assert_eq!(body["message"], "用户名或密码错误");
+    }
+    // 锁定后即使密码正确也拒绝，且不区分大小写
+    let (status, body) = login(&base, "ALICE", "password123").await;
+    assert_eq!(status, 429);
+    assert_eq!(body["code"], "too_many_requests");
+
+    // 同一存储上重新启动的服务依然锁定
+    let restarted = spawn_app_with(db, limited_config()).await;
+    let res = reqwest::Client::new()
+        .post(format!("{}/user/login", restarted))
+        .json(&json!({"username": "alice", "password": "password123"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 429);
+    let retry_after: i64 = res.headers()["retry-after"].to_str().unwrap().parse().unwrap();
+    assert!(retry_after > 0 && retry_after <= 60);
+}
+
+#[tokio::test]
+async fn unknown_usernames_get_the_same_responses() {
+    let mut config = limited_config();
+    config.auth.login_limit.max_failures = 2;
+    config.server.trust_forwarded_for = true;
+    let base = spawn_app_with(Arc::new(MemoryStore::new()), config).await;
+    register(&base, "alice").await;
+
+    let attempt = |username: &'static str, ip: &'static str| {
+        let base = base.clone();
+        async move {
+            let res = reqwest::Client::new()
+                .post(format!("{}/user/login", base))
+                .header("X-Forwarded-For", ip)
+                .json(&json!({"username": username, "password": "wrong-password1"}))
+                .send()
+                .await
+                .unwrap();
+            (res.status().as_u16(), res.json::<serde_json::Value>().await.unwrap())
+        }
+    };
+    let (existing, missing) = (attempt("alice", "10.0.0.1").await, attempt("ghost", "10.0.0.2").await);
+    assert_eq!(existing, missing);
+    attempt("alice", "10.0.0.1").await;
+    attempt("ghost", "10.0.0.2").await;
+    let (existing, missing) = (attempt("alice", "10.0.0.3").await, attempt("ghost", "10.0.0.4").await);
+    assert_eq!(existing.0, 429);
+    assert_eq!(existing.0, missing.0);
+    assert_eq!(existing.1["code"], missing.1["code"]);
+}
+
+#[tokio::test]
+async fn failures_from_one_ip_across_usernames_lock_the_ip() {
+    let base = spawn_app_with(Arc::new(MemoryStore::new()), limited_config()).await;
+    register(&base, "alice").await;
+    for username in ["bob", "carol", "dave"] {
+        assert_eq!(login(&base, username, "wrong-password1").await.0, 401);
+    }
+    assert_eq!(login(&base, "alice", "password123").await.0, 429);
+}
+
+#[tokio::test]
+async fn successful_login_resets_the_username_counter() {
+    let mut config = limited_config();
+    config.server.trust_forwarded_for = true;
+    let base = spawn_app_with(Arc::new(MemoryStore::new()), config).await;
+    register(&base, "alice").await;
+
+    // 每次换一个 IP，只观察用户名计数
+    let attempt = |password: &'static str, ip: String| {
+        let base = base.clone();
+        async move {
+            reqwest::Client::new()
+                .post(format!("{}/user/login", base))
+                .header("X-Forwarded-For", ip)
+                .json(&json!({"username": "alice", "password": password}))
+                .send()
+                .await
+                .unwrap()
+                .status()
+                .as_u16()
+        }
+    };
+    let mut ip = 0;
+    let mut next_ip = || { ip += 1; format!("10.0.1.{}", ip) };
+    for _ in 0..2 {
+        assert_eq!(attempt("wrong-password1", next_ip()).await, 401);
+        assert_eq!(attempt("wrong-password1", next_ip()).await, 401);
+        assert_eq!(attempt("password123", next_ip()).await, 200);
+    }
+}
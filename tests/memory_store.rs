@@ -0,0 +1,261 @@
+mod common;
+
+use common::{delete, get, post, register, spawn_app, spawn_app_with, test_config};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde_json::json;
+use std::sync::Arc;
+use todo_list::db::{LedgerStore, MemoryStore, OrderFilter, StoreError};
+use todo_list::models::account::Account;
+use todo_list::models::currency::Currency;
+use todo_list::models::ledger::{Invitation, Ledger, Role};
+use todo_list::models::money::Money;
+use todo_list::models::notification::{Notification, NotificationKind};
+
+#[tokio::test]
+async fn register_and_login() {
+    let base = spawn_app().await;
+    register(&base, "alice").await;
+    let res = reqwest::Client::new()
+        .post(format!("{}/user/login", base))
+        .json(&json!({"username": "alice", "password": "password123"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 200);
+
+    let res = reqwest::Client::new()
+        .post(format!("{}/user/login", base))
+        .json(&json!({"username": "alice", "password": "wrong"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn accounts_are_listed_per_user() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+
+    let (status, account) = post(&base, &alice, "/account/accounts", json!({
+        "name": "工资卡",
+        "account_type": "银行卡",
+        "balance": 100.0,
+        "currency": "人民币",
+    })).await;
+    assert_eq!(status, 200);
+    assert_eq!(account["name"], "工资卡");
+
+    let (_, list) = get(&base, &alice, "/account/accounts").await;
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    let (_, list) = get(&base, &bob, "/account/accounts").await;
+    assert!(list.as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn create_query_and_delete_orders() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+
+    for (name, order_type, amount) in [("午饭", "消费", 30.0), ("工资", "收入", 5000.0), ("晚饭", "消费", 45.5)] {
+        let (status, _) = post(&base, &token, "/transaction/orders", json!({
+            "name": name,
+            "order_type": order_type,
+            "amount": amount,
+            "currency": "人民币",
+            "date": "2025-01-15T12:00:00Z",
+        })).await;
+        assert_eq!(status, 200);
+    }
+
+    let (status, body) = get(&base, &token, "/order_query/orders/query?order_type=消费").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["total"], 2);
+    assert_eq!(body["stat"]["消费"]["minor"], 7550);
+
+    let id = body["orders"][0]["id"].as_str().unwrap().to_string();
+    let (status, body) = delete(&base, &token, &format!("/order/{}", id)).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["success"], true);
+
+    let (_, body) = get(&base, &token, "/order_query/orders/query").await;
+    assert_eq!(body["total"], 2);
+}
+
+#[tokio::test]
+async fn order_query_paginates_by_page_and_cursor() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+
+    for day in 1..=5 {
+        post(&base, &token, "/transaction/orders", json!({
+            "name": format!("早餐(第{}天)", day),
+            "order_type": "消费",
+            "amount": 10.0,
+            "currency": "CNY",
+            "date": format!("2025-03-0{}T08:00:00Z", day),
+        })).await;
+    }
+
+    let (_, page2) = get(&base, &token, "/order_query/orders/query?page=2&page_size=2").await;
+    assert_eq!(page2["total"], 5);
+    let dates: Vec<&str> = page2["orders"].as_array().unwrap().iter().map(|o| o["date"].as_str().unwrap()).collect();
+    assert!(dates[0].starts_with("2025-03-03") && dates[1].starts_with("2025-03-02"));
+
+    // 页码过大时跳过的条数溢出，返回参数错误而不是崩溃或回绕
+    for path in ["/order_query/orders/query?page=18446744073709551615", "/order_query/orders/query?page=18446744073709551615&page_size=1"] {
+        let (status, body) = get(&base, &token, path).await;
+        assert_eq!(status, 400, "{}", body);
+        assert_eq!(body["code"], "validation_error");
+    }
+
+    let mut seen = Vec::new();
+    let mut path = "/order_query/orders/query?page_size=2&sort=date".to_string();
+    loop {
+        let (status, body) = get(&base, &token, &path).await;
+        assert_eq!(status, 200);
+        for o in body["orders"].as_array().unwrap() {
+            seen.push(o["name"].as_str().unwrap().to_string());
+        }
+        match body["next_cursor"].as_str() {
+            Some(cursor) => path = format!("/order_query/orders/query?page_size=2&sort=date&cursor={}", cursor),
+            None => break,
+        }
+    }
+    let expected: Vec<String> = (1..=5).map(|d| format!("早餐(第{}天)", d)).collect();
+    assert_eq!(seen, expected);
+
+    let (_, body) = get(&base, &token, "/order_query/orders/query?name=(第3天)").await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["stat"]["消费"]["minor"], 1000);
+}
+
+#[tokio::test]
+async fn order_totals_group_by_day_and_merge_base_currency() {
+    let store = Arc::new(MemoryStore::new());
+    let base = spawn_app_with(store.clone(), test_config()).await;
+    let token = register(&base, "alice").await;
+    for (amount, currency, date) in [
+        (1.0, "USD", "2025-03-01T08:00:00Z"),
+        (2.0, "USD", "2025-03-01T20:00:00Z"),
+        (4.0, "USD", "2025-03-02T08:00:00Z"),
+        (8.0, "CNY", "2025-03-01T08:00:00Z"),
+        (16.0, "CNY", "2025-03-05T08:00:00Z"),
+    ] {
+        post(&base, &token, "/transaction/orders", json!({
+            "name": "消费", "order_type": "消费", "amount": amount, "currency": currency, "date": date,
+        })).await;
+    }
+    let (_, ledgers) = get(&base, &token, "/ledger/ledgers").await;
+    let ledger_id = ObjectId::parse_str(ledgers[0]["id"].as_str().unwrap()).unwrap();
+
+    // 外币按自然日分组，本位币不分日期
+    let mut totals: Vec<_> = store.order_totals(ledger_id, &OrderFilter::default(), Some(Currency::CNY)).await.unwrap()
+        .into_iter()
+        .map(|t| (t.amount.currency.code(), t.date.try_to_rfc3339_string().unwrap(), t.amount.minor))
+        .collect();
+    totals.sort();
+    assert_eq!(totals, [
+        ("CNY", "2025-03-01T00:00:00Z".to_string(), 2400),
+        ("USD", "2025-03-01T00:00:00Z".to_string(), 300),
+        ("USD", "2025-03-02T00:00:00Z".to_string(), 400),
+    ]);
+    let totals = store.order_totals(ledger_id, &OrderFilter::default(), None).await.unwrap();
+    assert_eq!(totals.len(), 4);
+
+    // 按周期分组时同一天的订单按确切时间拆开，早于第一个周期的不计入
+    let starts = [DateTime::parse_rfc3339_str("2025-03-01T12:00:00Z").unwrap(), DateTime::parse_rfc3339_str("2025-03-02T00:00:00Z").unwrap()];
+    let mut totals: Vec<_> = store.order_totals_by_period(ledger_id, &OrderFilter::default(), Some(Currency::CNY), &starts).await.unwrap()
+        .into_iter()
+        .map(|t| (t.period, t.amount.currency.code(), t.amount.minor))
+        .collect();
+    totals.sort();
+    assert_eq!(totals, [(0, "USD", 200), (1, "CNY", 1600), (1, "USD", 400)]);
+}
+
+#[tokio::test]
+async fn balance_overflow_is_rejected_without_writing_the_order() {
+    let store = Arc::new(MemoryStore::new());
+    let base = spawn_app_with(store.clone(), test_config()).await;
+    let token = register(&base, "alice").await;
+    let user = store.get_user_by_username("alice").await.unwrap().unwrap();
+    let (_, ledgers) = get(&base, &token, "/ledger/ledgers").await;
+    let ledger_id = ObjectId::parse_str(ledgers[0]["id"].as_str().unwrap()).unwrap();
+    let full = Money::new(i64::MAX - 100, Currency::CNY);
+    let account = store.create_account(Account {
+        id: ObjectId::new(),
+        ledger_id,
+        user_id: user.id,
+        name: "满额账户".to_string(),
+        account_type: "现金".to_string(),
+        balance: full,
+        initial_balance: full,
+        remark: None,
+    }).await.unwrap();
+
+    let (status, _) = post(&base, &token, "/v1/orders", json!({
+        "name": "工资", "order_type": "收入", "amount": 10.0, "currency": "CNY",
+        "date": "2025-01-10", "account_id": account.id.to_hex(),
+    })).await;
+    assert_eq!(status, 409);
+    let account = store.get_account(ledger_id, account.id).await.unwrap().unwrap();
+    assert_eq!(account.balance, full);
+    assert!(store.get_orders_by_ledger(ledger_id).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn invitation_is_kept_when_joining_the_ledger_fails() {
+    let store = MemoryStore::new();
+    let (owner, invitee) = (ObjectId::new(), ObjectId::new());
+    let ledger = store.create_ledger(Ledger::personal(owner)).await.unwrap();
+    let invitation = |ledger_id: ObjectId, invitee_id: ObjectId| Invitation {
+        id: ObjectId::new(),
+        ledger_id,
+        inviter_id: owner,
+        invitee_id,
+        role: Role::Editor,
+        created_at: DateTime::now(),
+    };
+
+    // 账本不存在、已是成员时都不消耗邀请
+    let orphan = store.create_invitation(invitation(ObjectId::new(), invitee)).await.unwrap();
+    assert!(matches!(store.accept_invitation(orphan.id).await, Err(StoreError::NotFound(_))));
+    assert!(store.get_invitation(orphan.id).await.unwrap().is_some());
+    let own = store.create_invitation(invitation(ledger.id, owner)).await.unwrap();
+    assert!(matches!(store.accept_invitation(own.id).await, Err(StoreError::Conflict(_))));
+    assert!(store.get_invitation(own.id).await.unwrap().is_some());
+
+    let valid = store.create_invitation(invitation(ledger.id, invitee)).await.unwrap();
+    let joined = store.accept_invitation(valid.id).await.unwrap();
+    assert_eq!(joined.role_of(invitee), Some(Role::Editor));
+    assert!(store.get_invitation(valid.id).await.unwrap().is_none());
+    assert!(matches!(store.accept_invitation(valid.id).await, Err(StoreError::NotFound(_))));
+}
+
+#[tokio::test]
+async fn pending_notifications_come_out_oldest_first() {
+    let store = MemoryStore::new();
+    let notification = |key: &str, created_at: i64| Notification {
+        id: ObjectId::new(),
+        user_id: ObjectId::new(),
+        ledger_id: ObjectId::new(),
+        kind: NotificationKind::BudgetThreshold,
+        key: key.to_string(),
+        title: String::new(),
+        message: String::new(),
+        budget_id: None,
+        read: false,
+        created_at: DateTime::from_millis(created_at),
+        delivered_at: None,
+        attempts: 0,
+        last_error: None,
+    };
+    // 写入顺序与创建时间不一致时，与 MongoDB 一样按创建时间投递
+    for (key, created_at) in [("b", 2000), ("c", 3000), ("a", 1000)] {
+        assert!(store.create_notification(notification(key, created_at)).await.unwrap());
+    }
+    let pending = store.get_pending_notifications(5).await.unwrap();
+    let keys: Vec<_> = pending.iter().map(|n| n.key.as_str()).collect();
+    assert_eq!(keys, ["a", "b", "c"]);
+}
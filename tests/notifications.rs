@@ -0,0 +1,323 @@
+mod common;
+
+use axum::{extract::State, http::{HeaderMap, StatusCode}, routing::post as route_post, Router};
+use common::{create_category, get, id_of, post, put, register, spawn_app, spawn_app_with, spend, test_config};
+use hmac::{Hmac, Mac};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use todo_list::config::{Config, SmtpConfig, WebhookConfig};
+use todo_list::db::{LedgerStore, MemoryStore};
+use todo_list::models::currency::Currency;
+use todo_list::models::money::Money;
+use todo_list::models::transaction::Order;
+use todo_list::notify::Notifier;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+/// 本地 webhook 接收端，记录收到的请求头和请求体；`failing` 为 true 时返回 500
+#[derive(Clone, Default)]
+struct Hook {
+    received: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+    failing: Arc<AtomicBool>,
+}
+
+async fn receive(State(hook): State<Hook>, headers: HeaderMap, body: String) -> StatusCode {
+    if hook.failing.load(Ordering::SeqCst) {
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+    hook.received.lock().unwrap().push((headers, body));
+    StatusCode::OK
+}
+
+async fn spawn_webhook(hook: Hook) -> String {
+    let app = Router::new().route("/hook", route_post(receive)).with_state(hook);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}/hook", addr)
+}
+
+/// 只实现收信所需命令的本地 SMTP 服务，记录每封邮件的原文
+async fn spawn_smtp(mails: Arc<Mutex<Vec<String>>>) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mails = mails.clone();
+            tokio::spawn(async move {
+                let (read, mut write) = stream.into_split();
+                let mut lines = BufReader::new(read).lines();
+                write.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+                let mut data: Option<String> = None;
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if let Some(mail) = data.as_mut() {
+                        if line == "." {
+                            mails.lock().unwrap().push(data.take().unwrap());
+                            write.write_all(b"250 OK\r\n").await.unwrap();
+                        } else {
+                            mail.push_str(&line);
+                            mail.push('\n');
+                        }
+                        continue;
+                    }
+                    let command = line.get(..4).unwrap_or_default().to_ascii_uppercase();
+                    let reply: &[u8] = match command.as_str() {
+                        "EHLO" | "HELO" => b"250 localhost\r\n",
+                        "DATA" => {
+                            data = Some(String::new());
+                            b"354 End data with <CR><LF>.<CR><LF>\r\n"
+                        }
+                        "QUIT" => {
+                            write.write_all(b"221 Bye\r\n").await.unwrap();
+                            break;
+                        }
+                        _ => b"250 OK\r\n",
+                    };
+                    write.write_all(reply).await.unwrap();
+                }
+            });
+        }
+    });
+    port
+}
+
+/// 等待后台投递完成
+async fn wait_until(mut done: impl FnMut() -> bool) {
+    for _ in 0..100 {
+        if done() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("等待超时");
+}
+
+async fn monthly_budget(base: &str, token: &str, category: &str, amount: f64) {
+    let (status, body) = post(base, token, "/budget/budgets", json!({
+        "category_id": category, "amount": amount, "currency": "CNY", "period": "monthly",
+        "start_date": "2025-01-01T00:00:00Z",
+    })).await;
+    assert_eq!(status, 200, "{}", body);
+}
+
+async fn notifications(base: &str, token: &str, query: &str) -> Vec<Value> {
+    let (status, body) = get(base, token, &format!("/notification/notifications{}", query)).await;
+    assert_eq!(status, 200);
+    body.as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn crossing_thresholds_notifies_every_member_once() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let (_, ledgers) = get(&base, &alice, "/ledger/ledgers").await;
+    let ledger = ledgers[0]["id"].as_str().unwrap().to_string();
+    let (_, invitation) = post(&base, &alice, &format!("/ledger/ledgers/{}/invitations", ledger), json!({
+        "username": "bob", "role": "viewer",
+    })).await;
+    let (status, _) = post(&base, &bob, &format!("/ledger/invitations/{}/accept", invitation["id"].as_str().unwrap()), json!({})).await;
+    assert_eq!(status, 200);
+
+    let food = create_category(&base, &alice, "餐饮", "支出", None).await;
+    monthly_budget(&base, &alice, &food, 1000.0).await;
+    spend(&base, &alice, &food, 500.0, "CNY", "2025-01-10T12:00:00Z").await;
+    assert!(notifications(&base, &alice, "").await.is_empty());
+
+    // 达到 80%，之后同一周期内不再重复提醒
+    spend(&base, &alice, &food, 350.0, "CNY", "2025-01-10T12:00:00Z").await;
+    spend(&base, &alice, &food, 10.0, "CNY", "2025-01-10T12:00:00Z").await;
+    let list = notifications(&base, &alice, "").await;
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0]["kind"], "budget_threshold");
+    assert_eq!(list[0]["title"], "预算即将用完");
+    assert_eq!(list[0]["ledger_id"], ledger.as_str());
+
+    spend(&base, &alice, &food, 200.0, "CNY", "2025-01-10T12:00:00Z").await;
+    let list = notifications(&base, &alice, "").await;
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0]["title"], "预算已超支");
+    assert_eq!(notifications(&base, &bob, "").await.len(), 2);
+
+    // 收入订单不能记到支出分类，也就不会触发提醒
+    let (status, _) = post(&base, &alice, "/v1/orders", json!({
+        "name": "退款", "order_type": "收入", "amount": 5000.0, "currency": "CNY",
+        "date": "2025-01-10T12:00:00Z", "category_id": food,
+    })).await;
+    assert_eq!(status, 400);
+    assert_eq!(notifications(&base, &alice, "").await.len(), 2);
+}
+
+#[tokio::test]
+async fn each_member_is_alerted_with_their_own_rates() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let (_, ledgers) = get(&base, &alice, "/ledger/ledgers").await;
+    let ledger = ledgers[0]["id"].as_str().unwrap().to_string();
+    let (_, invitation) = post(&base, &alice, &format!("/ledger/ledgers/{}/invitations", ledger), json!({
+        "username": "bob", "role": "viewer",
+    })).await;
+    post(&base, &bob, &format!("/ledger/invitations/{}/accept", invitation["id"].as_str().unwrap()), json!({})).await;
+    post(&base, &alice, "/rate/rates", json!({"from": "USD", "to": "CNY", "rate": 7.0, "date": "2025-01-01"})).await;
+    post(&base, &bob, "/rate/rates", json!({"from": "USD", "to": "CNY", "rate": 1.0, "date": "2025-01-01"})).await;
+
+    let food = create_category(&base, &alice, "餐饮", "支出", None).await;
+    monthly_budget(&base, &alice, &food, 1000.0).await;
+    spend(&base, &alice, &food, 120.0, "USD", "2025-01-10T12:00:00Z").await;
+
+    // 按 alice 的汇率已用 84%，按 bob 的汇率只用了 12%，与各自查看的进度一致
+    let list = notifications(&base, &alice, "").await;
+    assert_eq!(list.len(), 1);
+    assert!(list[0]["message"].as_str().unwrap().contains("84%"), "{}", list[0]);
+    assert!(notifications(&base, &bob, "").await.is_empty());
+    let (_, budgets) = get(&base, &bob, &format!("/books/{}/budget/budgets", ledger)).await;
+    let path = format!("/books/{}/budget/budgets/{}/progress?date=2025-01-11", ledger, id_of(&budgets[0]));
+    let (_, progress) = get(&base, &bob, &path).await;
+    assert_eq!(progress["percent"], 12.0);
+}
+
+#[tokio::test]
+async fn notifications_can_be_marked_read() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let food = create_category(&base, &alice, "餐饮", "支出", None).await;
+    monthly_budget(&base, &alice, &food, 100.0).await;
+    spend(&base, &alice, &food, 150.0, "CNY", "2025-01-10T12:00:00Z").await;
+
+    let list = notifications(&base, &alice, "?unread=true").await;
+    assert_eq!(list.len(), 2);
+    let path = format!("/notification/notifications/{}/read", list[0]["id"].as_str().unwrap());
+    // 不能标记别人的通知
+    let (status, _) = post(&base, &bob, &path, json!({})).await;
+    assert_eq!(status, 404);
+    let (status, read) = post(&base, &alice, &path, json!({})).await;
+    assert_eq!(status, 200);
+    assert_eq!(read["read"], true);
+    assert_eq!(notifications(&base, &alice, "?unread=true").await.len(), 1);
+
+    let (status, count) = post(&base, &alice, "/notification/notifications/read_all", json!({})).await;
+    assert_eq!(status, 200);
+    assert_eq!(count, 1);
+    assert!(notifications(&base, &alice, "?unread=true").await.is_empty());
+    assert_eq!(notifications(&base, &alice, "").await.len(), 2);
+}
+
+#[tokio::test]
+async fn alerts_are_delivered_to_webhook_and_smtp() {
+    let hook = Hook::default();
+    let mails = Arc::new(Mutex::new(Vec::new()));
+    let mut config = test_config();
+    config.notifications.budget_thresholds = vec![50];
+    config.notifications.webhook = Some(WebhookConfig {
+        url: spawn_webhook(hook.clone()).await,
+        secret: Some("hook-secret".to_string()),
+    });
+    config.notifications.smtp = Some(SmtpConfig {
+        host: "127.0.0.1".to_string(),
+        port: spawn_smtp(mails.clone()).await,
+        from: "账本 <noreply@example.com>".to_string(),
+        username: None,
+        password: None,
+        starttls: false,
+    });
+    let base = spawn_app_with(Arc::new(MemoryStore::new()), config).await;
+    let alice = register(&base, "alice").await;
+    let (status, _) = put(&base, &alice, "/user/settings", json!({"base_currency": "CNY", "email": "bad"})).await;
+    assert_eq!(status, 400);
+    let (status, settings) = put(&base, &alice, "/user/settings", json!({"base_currency": "CNY", "email": "alice@example.com"})).await;
+    assert_eq!(status, 200);
+    assert_eq!(settings["email"], "alice@example.com");
+
+    let food = create_category(&base, &alice, "餐饮", "支出", None).await;
+    monthly_budget(&base, &alice, &food, 100.0).await;
+    spend(&base, &alice, &food, 60.0, "CNY", "2025-01-10T12:00:00Z").await;
+
+    wait_until(|| hook.received.lock().unwrap().len() == 1 && mails.lock().unwrap().len() == 1).await;
+    let (headers, body) = hook.received.lock().unwrap()[0].clone();
+    let payload: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(payload["user"]["username"], "alice");
+    assert_eq!(payload["notification"]["kind"], "budget_threshold");
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"hook-secret").unwrap();
+    mac.update(body.as_bytes());
+    let expected = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+    assert_eq!(headers["x-signature"], expected.as_str());
+
+    let mail = mails.lock().unwrap()[0].clone();
+    assert!(mail.contains("To: alice@example.com"), "{}", mail);
+    assert!(mail.contains("noreply@example.com"), "{}", mail);
+}
+
+#[tokio::test]
+async fn failed_deliveries_stay_in_the_outbox_until_retried() {
+    let hook = Hook::default();
+    hook.failing.store(true, Ordering::SeqCst);
+    let mut config: Config = test_config();
+    config.notifications.webhook = Some(WebhookConfig { url: spawn_webhook(hook.clone()).await, secret: None });
+    let store = Arc::new(MemoryStore::new());
+    let notifier = Notifier::new(&config.notifications);
+    let base = spawn_app_with(store.clone(), config).await;
+    let alice = register(&base, "alice").await;
+    let food = create_category(&base, &alice, "餐饮", "支出", None).await;
+    monthly_budget(&base, &alice, &food, 100.0).await;
+    spend(&base, &alice, &food, 90.0, "CNY", "2025-01-10T12:00:00Z").await;
+
+    // 第一次投递失败，记录错误并留在发件箱
+    for _ in 0..100 {
+        let pending = store.get_pending_notifications(5).await.unwrap();
+        if pending.len() == 1 && pending[0].attempts == 1 {
+            assert!(pending[0].last_error.as_deref().unwrap().contains("500"));
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(store.get_pending_notifications(5).await.unwrap()[0].attempts, 1);
+
+    hook.failing.store(false, Ordering::SeqCst);
+    assert_eq!(notifier.flush(store.as_ref()).await.unwrap(), 1);
+    assert!(store.get_pending_notifications(5).await.unwrap().is_empty());
+    assert_eq!(hook.received.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn interrupted_alert_checks_are_redone_from_the_order_marker() {
+    let store = Arc::new(MemoryStore::new());
+    let config = test_config();
+    let notifier = Notifier::new(&config.notifications);
+    let base = spawn_app_with(store.clone(), config).await;
+    let alice = register(&base, "alice").await;
+    let food = create_category(&base, &alice, "餐饮", "支出", None).await;
+    monthly_budget(&base, &alice, &food, 100.0).await;
+    let user = store.get_user_by_username("alice").await.unwrap().unwrap();
+    let (_, ledgers) = get(&base, &alice, "/ledger/ledgers").await;
+
+    // 模拟订单写入后、提醒检查前进程退出：订单带着待检查标记留在库里
+    store.create_order(Order {
+        id: ObjectId::new(),
+        ledger_id: ObjectId::parse_str(ledgers[0]["id"].as_str().unwrap()).unwrap(),
+        user_id: user.id,
+        account_id: None,
+        category_id: Some(ObjectId::parse_str(&food).unwrap()),
+        name: "消费".to_string(),
+        order_type: "消费".to_string(),
+        amount: Money::from_major(150.0, Currency::CNY).unwrap(),
+        date: DateTime::parse_rfc3339_str("2025-01-10T12:00:00Z").unwrap(),
+        remark: None,
+        transfer: None,
+        alerts_pending: true,
+    }).await.unwrap();
+    assert!(notifications(&base, &alice, "").await.is_empty());
+
+    assert_eq!(notifier.evaluate_pending(store.as_ref()).await.unwrap(), 2);
+    assert!(store.get_orders_pending_alerts().await.unwrap().is_empty());
+    assert_eq!(notifications(&base, &alice, "").await.len(), 2);
+    // 正常创建的订单检查完成后不留标记
+    spend(&base, &alice, &food, 10.0, "CNY", "2025-01-10T12:00:00Z").await;
+    assert!(store.get_orders_pending_alerts().await.unwrap().is_empty());
+}
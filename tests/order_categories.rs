@@ -0,0 +1,135 @@
+mod common;
+
+use common::{create_category, delete, get, patch, post, register, spawn_app};
+use serde_json::{json, Value};
+
+async fn order(base: &str, token: &str, order_type: &str, amount: f64, category: Option<&str>) -> (u16, Value) {
+    post(base, token, "/v1/orders", json!({
+        "name": order_type, "order_type": order_type, "amount": amount, "currency": "CNY",
+        "date": "2025-01-10", "category_id": category,
+    })).await
+}
+
+fn amounts(body: &Value) -> Vec<i64> {
+    body["orders"].as_array().unwrap().iter().map(|o| o["amount"]["minor"].as_i64().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn category_type_must_match_order_direction() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let (status, _) = post(&base, &token, "/category/categories", json!({"name": "杂项", "category_type": "其他"})).await;
+    assert_eq!(status, 400);
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let salary = create_category(&base, &token, "工资", "收入", None).await;
+
+    let (status, body) = order(&base, &token, "消费", 10.0, Some(&salary)).await;
+    assert_eq!(status, 400);
+    assert!(body["message"].as_str().unwrap().contains("工资"));
+    let (status, _) = order(&base, &token, "转账", 10.0, Some(&food)).await;
+    assert_eq!(status, 400);
+    let (status, created) = order(&base, &token, "收入", 10.0, Some(&salary)).await;
+    assert_eq!(status, 201);
+    assert_eq!(created["category_id"], salary.as_str());
+    let (status, _) = order(&base, &token, "消费", 10.0, Some(&food)).await;
+    assert_eq!(status, 201);
+}
+
+#[tokio::test]
+async fn orders_can_be_filtered_by_category_and_its_children() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let lunch = create_category(&base, &token, "午餐", "支出", Some(&food)).await;
+    let travel = create_category(&base, &token, "交通", "支出", None).await;
+    order(&base, &token, "消费", 1.0, Some(&food)).await;
+    order(&base, &token, "消费", 2.0, Some(&lunch)).await;
+    order(&base, &token, "消费", 4.0, Some(&travel)).await;
+    order(&base, &token, "消费", 8.0, None).await;
+
+    let (status, body) = get(&base, &token, &format!("/v1/orders?category_id={}", food)).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["total"], 2);
+    assert_eq!(body["stat"]["消费"]["minor"], 300);
+
+    let (_, body) = get(&base, &token, &format!("/v1/orders?category_id={}&include_children=false", food)).await;
+    assert_eq!(amounts(&body), [100]);
+    let (_, body) = get(&base, &token, &format!("/v1/orders?category_id={},{}&include_children=false", lunch, travel)).await;
+    assert_eq!(body["total"], 2);
+
+    // 旧查询接口同样支持
+    let (status, body) = get(&base, &token, &format!("/order_query/orders/query?category_id={}", travel)).await;
+    assert_eq!(status, 200);
+    assert_eq!(amounts(&body), [400]);
+
+    let (status, _) = get(&base, &token, "/v1/orders?category_id=000000000000000000000000").await;
+    assert_eq!(status, 404);
+    let (status, _) = get(&base, &token, "/v1/orders?category_id=bad").await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn query_reports_totals_per_category() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let lunch = create_category(&base, &token, "午餐", "支出", Some(&food)).await;
+    let salary = create_category(&base, &token, "工资", "收入", None).await;
+    order(&base, &token, "消费", 10.0, Some(&food)).await;
+    order(&base, &token, "消费", 20.0, Some(&food)).await;
+    order(&base, &token, "消费", 5.0, Some(&lunch)).await;
+    order(&base, &token, "消费", 7.0, None).await;
+    order(&base, &token, "收入", 100.0, Some(&salary)).await;
+
+    let (status, body) = get(&base, &token, "/v1/orders").await;
+    assert_eq!(status, 200);
+    let stat = body["category_stat"].as_array().unwrap();
+    assert_eq!(stat.len(), 4);
+    assert_eq!(stat[0], json!({
+        "category_id": salary, "name": "工资", "order_type": "收入",
+        "amount": {"minor": 10000, "currency": "CNY"},
+    }));
+    // 子分类单独统计，不汇总到父分类
+    assert_eq!(stat[1]["category_id"], food.as_str());
+    assert_eq!(stat[1]["amount"]["minor"], 3000);
+    assert_eq!(stat[2]["category_id"], Value::Null);
+    assert_eq!(stat[2]["amount"]["minor"], 700);
+    assert_eq!(stat[3]["name"], "午餐");
+
+    // 与筛选条件一致
+    let (_, body) = get(&base, &token, &format!("/v1/orders?category_id={}", lunch)).await;
+    assert_eq!(body["category_stat"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn categories_in_use_keep_their_type_and_pass_orders_on_delete() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let food = create_category(&base, &token, "餐饮", "支出", None).await;
+    let other = create_category(&base, &token, "其他", "支出", None).await;
+    let salary = create_category(&base, &token, "工资", "收入", None).await;
+    let unused = create_category(&base, &token, "未使用", "支出", None).await;
+    order(&base, &token, "消费", 10.0, Some(&food)).await;
+
+    let (status, body) = patch(&base, &token, &format!("/category/categories/{}", food), json!({"category_type": "收入"})).await;
+    assert_eq!(status, 409);
+    assert!(body["message"].as_str().unwrap().contains("1 条订单"));
+    let (status, _) = patch(&base, &token, &format!("/category/categories/{}", unused), json!({"category_type": "收入"})).await;
+    assert_eq!(status, 200);
+    let (status, _) = patch(&base, &token, &format!("/category/categories/{}", unused), json!({"category_type": "收支"})).await;
+    assert_eq!(status, 400);
+
+    // 订单转到同类型的目标分类
+    let (status, _) = delete(&base, &token, &format!("/category/categories/{}?reassign_to={}", food, salary)).await;
+    assert_eq!(status, 400);
+    let (status, _) = delete(&base, &token, &format!("/category/categories/{}?reassign_to={}", food, other)).await;
+    assert_eq!(status, 200);
+    let (_, body) = get(&base, &token, "/v1/orders").await;
+    assert_eq!(body["orders"][0]["category_id"], other.as_str());
+
+    // 未指定目标时订单变为未分类
+    let (status, _) = delete(&base, &token, &format!("/category/categories/{}", other)).await;
+    assert_eq!(status, 200);
+    let (_, body) = get(&base, &token, "/v1/orders").await;
+    assert_eq!(body["orders"][0]["category_id"], Value::Null);
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use common::{delete, get, post, register, spawn_app};
+use serde_json::json;
+
+#[tokio::test]
+async fn v1_orders_create_get_list_and_delete() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+
+    // 日期同时接受 RFC3339 与 YYYY-MM-DD
+    let (status, order) = post(&base, &token, "/v1/orders", json!({
+        "name": "午饭", "order_type": "消费", "amount": 30.0, "currency": "CNY", "date": "2025-01-15",
+    })).await;
+    assert_eq!(status, 201);
+    assert_eq!(order["date"], "2025-01-15T00:00:00Z");
+    let id = order["id"].as_str().unwrap().to_string();
+
+    let (status, fetched) = get(&base, &token, &format!("/v1/orders/{}", id)).await;
+    assert_eq!(status, 200);
+    assert_eq!(fetched["name"], "午饭");
+    assert_eq!(fetched["amount"]["minor"], 3000);
+
+    let (status, page) = get(&base, &token, "/v1/orders?order_type=消费&date_start=2025-01-01").await;
+    assert_eq!(status, 200);
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["orders"][0]["id"], id.as_str());
+
+    let (status, _) = delete(&base, &token, &format!("/v1/orders/{}", id)).await;
+    assert_eq!(status, 200);
+    let (status, _) = get(&base, &token, &format!("/v1/orders/{}", id)).await;
+    assert_eq!(status, 404);
+    let (status, _) = delete(&base, &token, &format!("/v1/orders/{}", id)).await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn v1_orders_reject_invalid_input_with_400() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+
+    for body in [
+        json!({"name": "x", "order_type": "乱写", "amount": 1.0, "currency": "CNY", "date": "2025-01-15"}),
+        json!({"name": "x", "order_type": "消费", "amount": 1.0, "currency": "CNY", "date": "昨天"}),
+        json!({"name": "x", "order_type": "消费", "amount": -1.0, "currency": "CNY", "date": "2025-01-15"}),
+        json!({"name": "x", "order_type": "消费", "amount": 1.0, "currency": "CNY", "date": "2025-01-15", "account_id": "bad"}),
+    ] {
+        let (status, res) = post(&base, &token, "/v1/orders", body.clone()).await;
+        assert_eq!(status, 400, "{} => {}", body, res);
+    }
+    let (status, _) = get(&base, &token, "/v1/orders?date_start=昨天").await;
+    assert_eq!(status, 400);
+    let (status, _) = get(&base, &token, "/v1/orders/not-an-id").await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn legacy_order_paths_share_validation_and_are_marked_deprecated() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+
+    // 旧的 /order 接口不再把非法日期替换成当前时间
+    let (status, _) = post(&base, &token, "/order", json!({
+        "name": "x", "type": "消费", "amount": 1.0, "currency": "CNY", "date": "not a date",
+    })).await;
+    assert_eq!(status, 400);
+    let (status, order) = post(&base, &token, "/order", json!({
+        "name": "午饭", "type": "消费", "amount": 1.0, "currency": "CNY", "date": "2025-01-15",
+    })).await;
+    assert_eq!(status, 200);
+    assert_eq!(order["order_type"], "消费");
+
+    let res = reqwest::Client::new()
+        .get(format!("{}/order_query/orders/query", base))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.headers()["deprecation"], "true");
+    assert!(res.headers()["link"].to_str().unwrap().contains("/api/v1/orders"));
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["total"], 1);
+
+    let (status, body) = delete(&base, &token, "/order/000000000000000000000000").await;
+    assert_eq!(status, 404);
+    assert_eq!(body["code"], "not_found");
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use common::{delete, get, id_of, patch, post, put, register, spawn_app};
+use serde_json::json;
+
+/// alice 的一整套数据：账户、分类、资产、预算、订单
+struct Fixture {
+    account: String,
+    category: String,
+    asset: String,
+    budget: String,
+    order: String,
+}
+
+async fn seed(base: &str, token: &str) -> Fixture {
+    let (_, account) = post(base, token, "/account/accounts", json!({
+        "name": "工资卡", "account_type": "银行卡", "balance": 100.0, "currency": "CNY",
+    })).await;
+    let account = id_of(&account);
+    let (_, category) = post(base, token, "/category/categories", json!({
+        "name": "餐饮", "category_type": "支出",
+    })).await;
+    let category = id_of(&category);
+    let (_, asset) = post(base, token, "/asset/assets", json!({
+        "name": "基金", "asset_type": "基金", "value": 1000.0, "currency": "CNY", "account_id": account,
+    })).await;
+    let asset = id_of(&asset);
+    let (_, budget) = post(base, token, "/budget/budgets", json!({
+        "category_id": category, "amount": 500.0, "currency": "CNY", "period": "月",
+        "start_date": "2025-01-01T00:00:00Z", "end_date": "2025-01-31T23:59:59Z",
+    })).await;
+    let budget = id_of(&budget);
+    let (_, order) = post(base, token, "/transaction/orders", json!({
+        "account_id": account, "name": "午饭", "order_type": "消费",
+        "amount": 30.0, "currency": "CNY", "date": "2025-01-15T12:00:00Z",
+    })).await;
+    let order = id_of(&order);
+    Fixture { account, category, asset, budget, order }
+}
+
+#[tokio::test]
+async fn by_id_operations_on_other_users_documents_return_404() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let f = seed(&base, &alice).await;
+
+    let resources = [
+        ("/account/accounts", &f.account, json!({
+            "name": "x", "account_type": "x", "balance": 0.0, "currency": "CNY",
+        })),
+        ("/category/categories", &f.category, json!({"name": "x", "category_type": "支出"})),
+        ("/asset/assets", &f.asset, json!({
+            "name": "x", "asset_type": "x", "value": 0.0, "currency": "CNY", "account_id": f.account,
+        })),
+        ("/budget/budgets", &f.budget, json!({
+            "category_id": f.category, "amount": 1.0, "currency": "CNY", "period": "月",
+            "start_date": "2025-01-01T00:00:00Z", "end_date": "2025-01-31T23:59:59Z",
+        })),
+    ];
+    for (path, id, body) in &resources {
+        let url = format!("{}/{}", path, id);
+        assert_eq!(get(&base, &bob, &url).await.0, 404, "GET {}", path);
+        assert_eq!(put(&base, &bob, &url, body.clone()).await.0, 404, "PUT {}", path);
+        assert_eq!(patch(&base, &bob, &url, json!({"name": "x"})).await.0, 404, "PATCH {}", path);
+        assert_eq!(delete(&base, &bob, &url).await.0, 404, "DELETE {}", path);
+        // alice 的数据不受影响
+        let (status, doc) = get(&base, &alice, &url).await;
+        assert_eq!(status, 200, "GET {} as owner", path);
+        assert_ne!(doc["name"], "x");
+    }
+    let (status, _) = post(&base, &bob, &format!("/account/accounts/{}/recompute", f.account), json!({})).await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn other_users_orders_cannot_be_deleted() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let f = seed(&base, &alice).await;
+
+    let (status, body) = delete(&base, &bob, &format!("/order/{}", f.order)).await;
+    assert_eq!(status, 404);
+    assert_eq!(body["code"], "not_found");
+    let (status, _) = post(&base, &bob, "/transaction/orders/delete", json!({"id": f.order})).await;
+    assert_eq!(status, 404);
+
+    let (_, orders) = get(&base, &alice, "/transaction/orders").await;
+    assert_eq!(orders.as_array().unwrap().len(), 1);
+    let (_, account) = get(&base, &alice, &format!("/account/accounts/{}", f.account)).await;
+    assert_eq!(account["balance"]["minor"], 7000);
+
+    let (status, _) = delete(&base, &alice, &format!("/order/{}", f.order)).await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+async fn other_users_documents_cannot_be_referenced() {
+    let base = spawn_app().await;
+    let alice = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let f = seed(&base, &alice).await;
+    let (_, bob_account) = post(&base, &bob, "/account/accounts", json!({
+        "name": "现金", "account_type": "现金", "balance": 0.0, "currency": "CNY",
+    })).await;
+    let bob_account = id_of(&bob_account);
+    let (_, bob_category) = post(&base, &bob, "/category/categories", json!({
+        "name": "其他", "category_type": "支出",
+    })).await;
+    let bob_category = id_of(&bob_category);
+
+    let (status, _) = post(&base, &bob, "/transaction/orders", json!({
+        "account_id": f.account, "name": "偷记", "order_type": "收入",
+        "amount": 1.0, "currency": "CNY", "date": "2025-01-15T12:00:00Z",
+    })).await;
+    assert_eq!(status, 404);
+    let (status, _) = post(&base, &bob, "/transaction/transfers", json!({
+        "from_account_id": f.account, "to_account_id": bob_account,
+        "amount": 10.0, "date": "2025-01-15T12:00:00Z",
+    })).await;
+    assert_eq!(status, 404);
+    let (status, _) = post(&base, &bob, "/asset/assets", json!({
+        "name": "x", "asset_type": "x", "value": 1.0, "currency": "CNY", "account_id": f.account,
+    })).await;
+    assert_eq!(status, 404);
+    let (status, _) = post(&base, &bob, "/budget/budgets", json!({
+        "category_id": f.category, "amount": 1.0, "currency": "CNY", "period": "月",
+        "start_date": "2025-01-01T00:00:00Z", "end_date": "2025-01-31T23:59:59Z",
+    })).await;
+    assert_eq!(status, 404);
+    let (status, _) = post(&base, &bob, "/category/categories", json!({
+        "name": "子分类", "parent_id": f.category, "category_type": "支出",
+    })).await;
+    assert_eq!(status, 404);
+    let (status, _) = delete(&base, &alice, &format!("/category/categories/{}?reassign_to={}", f.category, bob_category)).await;
+    assert_eq!(status, 404);
+
+    let (_, account) = get(&base, &alice, &format!("/account/accounts/{}", f.account)).await;
+    assert_eq!(account["balance"]["minor"], 7000);
+}
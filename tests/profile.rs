@@ -0,0 +1,81 @@
+mod common;
+
+use common::{delete_json, get, login, post, put, register, spawn_app};
+use serde_json::json;
+
+#[tokio::test]
+async fn me_returns_profile_and_username_can_change() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    register(&base, "bob").await;
+
+    let (status, me) = get(&base, &token, "/user/me").await;
+    assert_eq!(status, 200);
+    assert_eq!(me["username"], "alice");
+    assert_eq!(me["base_currency"], "CNY");
+    assert!(me.get("password").is_none());
+
+    let (status, body) = put(&base, &token, "/user/username", json!({"username": "bob"})).await;
+    assert_eq!(status, 409);
+    assert_eq!(body["code"], "conflict");
+    let (status, me) = put(&base, &token, "/user/username", json!({"username": "alice2"})).await;
+    assert_eq!(status, 200);
+    assert_eq!(me["username"], "alice2");
+    assert_eq!(login(&base, "alice2", "password123").await.0, 200);
+    assert_eq!(login(&base, "alice", "password123").await.0, 401);
+}
+
+#[tokio::test]
+async fn change_password_verifies_old_one_and_revokes_sessions() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let (_, other) = login(&base, "alice", "password123").await;
+    let other = other["token"].as_str().unwrap();
+
+    let (status, _) = put(&base, &token, "/user/password", json!({
+        "old_password": "wrong", "new_password": "new-password456",
+    })).await;
+    assert_eq!(status, 401);
+
+    let (status, tokens) = put(&base, &token, "/user/password", json!({
+        "old_password": "password123", "new_password": "new-password456",
+    })).await;
+    assert_eq!(status, 200);
+    assert_eq!(get(&base, tokens["token"].as_str().unwrap(), "/user/me").await.0, 200);
+    assert_eq!(get(&base, other, "/user/me").await.0, 401);
+    assert_eq!(login(&base, "alice", "password123").await.0, 401);
+    assert_eq!(login(&base, "alice", "new-password456").await.0, 200);
+}
+
+#[tokio::test]
+async fn deleting_account_cascades_to_all_data() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let bob = register(&base, "bob").await;
+    let (_, account) = post(&base, &token, "/account/accounts", json!({
+        "name": "现金", "account_type": "现金", "balance": 0.0, "currency": "CNY",
+    })).await;
+    post(&base, &token, "/transaction/orders", json!({
+        "account_id": account["id"]["$oid"], "name": "午饭", "order_type": "消费",
+        "amount": 30.0, "currency": "CNY", "date": "2025-01-15T12:00:00Z",
+    })).await;
+    post(&base, &bob, "/account/accounts", json!({
+        "name": "现金", "account_type": "现金", "balance": 0.0, "currency": "CNY",
+    })).await;
+
+    let (status, _) = delete_json(&base, &token, "/user/me", json!({"password": "wrong"})).await;
+    assert_eq!(status, 401);
+    let (status, _) = delete_json(&base, &token, "/user/me", json!({"password": "password123"})).await;
+    assert_eq!(status, 200);
+
+    assert_eq!(get(&base, &token, "/account/accounts").await.0, 401);
+    assert_eq!(login(&base, "alice", "password123").await.0, 401);
+    // 重新注册同名用户看不到任何旧数据
+    let token = register(&base, "alice").await;
+    let (_, accounts) = get(&base, &token, "/account/accounts").await;
+    assert!(accounts.as_array().unwrap().is_empty());
+    let (_, orders) = get(&base, &token, "/transaction/orders").await;
+    assert!(orders.as_array().unwrap().is_empty());
+    let (_, accounts) = get(&base, &bob, "/account/accounts").await;
+    assert_eq!(accounts.as_array().unwrap().len(), 1);
+}
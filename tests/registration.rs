@@ -0,0 +1,48 @@
+mod common;
+
+use common::{get, login, post, spawn_app};
+use serde_json::json;
+
+async fn register_raw(base: &str, username: &str, password: &str) -> (u16, serde_json::Value) {
+    post(base, "", "/user/register", json!({"username": username, "password": password})).await
+}
+
+#[tokio::test]
+async fn registration_validates_username_and_password() {
+    let base = spawn_app().await;
+    for (username, password) in [
+        ("", "password123"),
+        ("   ", "password123"),
+        ("a b", "password123"),
+        ("alice", ""),
+        ("alice", "short1"),
+        ("alice", "passwordonly"),
+        ("alice", "12345678"),
+    ] {
+        let (status, body) = register_raw(&base, username, password).await;
+        assert_eq!(status, 400, "{:?}/{:?} => {}", username, password, body);
+        assert_eq!(body["code"], "validation_error");
+    }
+}
+
+#[tokio::test]
+async fn usernames_are_normalized_and_unique() {
+    let base = spawn_app().await;
+    let (status, body) = register_raw(&base, "  Alice ", "password123").await;
+    assert_eq!(status, 200);
+    let (_, me) = get(&base, body["token"].as_str().unwrap(), "/user/me").await;
+    assert_eq!(me["username"], "alice");
+
+    let (status, body) = register_raw(&base, "ALICE", "password456").await;
+    assert_eq!(status, 409);
+    assert_eq!(body["code"], "conflict");
+    assert_eq!(login(&base, "ALICE ", "password123").await.0, 200);
+
+    // 并发注册同一用户名只有一个成功
+    let results = futures::future::join_all(
+        (0..5).map(|_| register_raw(&base, "bob", "password123")),
+    ).await;
+    let created = results.iter().filter(|(status, _)| *status == 200).count();
+    let conflicts = results.iter().filter(|(status, _)| *status == 409).count();
+    assert_eq!((created, conflicts), (1, 4));
+}
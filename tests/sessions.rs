@@ -0,0 +1,68 @@
+mod common;
+
+use common::{get, post, spawn_app};
+use serde_json::{json, Value};
+
+async fn login(base: &str, username: &str) -> Value {
+    let (status, body) = common::login(base, username, "password123").await;
+    assert_eq!(status, 200);
+    body
+}
+
+async fn refresh(base: &str, refresh_token: &Value) -> (u16, Value) {
+    post(base, "", "/user/refresh", json!({"refresh_token": refresh_token})).await
+}
+
+#[tokio::test]
+async fn refresh_rotates_and_detects_reuse() {
+    let base = spawn_app().await;
+    common::register(&base, "alice").await;
+    let tokens = login(&base, "alice").await;
+    assert!(tokens["expires_in"].as_i64().unwrap() > 0);
+
+    let (status, rotated) = refresh(&base, &tokens["refresh_token"]).await;
+    assert_eq!(status, 200);
+    assert_ne!(rotated["refresh_token"], tokens["refresh_token"]);
+    let access = rotated["token"].as_str().unwrap();
+    assert_eq!(get(&base, access, "/account/accounts").await.0, 200);
+
+    // 旧刷新令牌被重复使用：拒绝并吊销整个会话
+    let (status, body) = refresh(&base, &tokens["refresh_token"]).await;
+    assert_eq!(status, 401);
+    assert_eq!(body["code"], "unauthorized");
+    assert_eq!(get(&base, access, "/account/accounts").await.0, 401);
+    assert_eq!(refresh(&base, &rotated["refresh_token"]).await.0, 401);
+
+    assert_eq!(refresh(&base, &json!("garbage")).await.0, 401);
+}
+
+#[tokio::test]
+async fn logout_revokes_only_the_current_session() {
+    let base = spawn_app().await;
+    common::register(&base, "alice").await;
+    let phone = login(&base, "alice").await;
+    let laptop = login(&base, "alice").await;
+    let phone_token = phone["token"].as_str().unwrap();
+    let laptop_token = laptop["token"].as_str().unwrap();
+
+    let (status, _) = post(&base, phone_token, "/user/logout", json!({})).await;
+    assert_eq!(status, 200);
+    assert_eq!(get(&base, phone_token, "/account/accounts").await.0, 401);
+    assert_eq!(refresh(&base, &phone["refresh_token"]).await.0, 401);
+    assert_eq!(get(&base, laptop_token, "/account/accounts").await.0, 200);
+}
+
+#[tokio::test]
+async fn logout_all_revokes_every_session() {
+    let base = spawn_app().await;
+    let first = common::register(&base, "alice").await;
+    let second = login(&base, "alice").await;
+    let second_token = second["token"].as_str().unwrap();
+
+    let (status, revoked) = post(&base, &first, "/user/logout_all", json!({})).await;
+    assert_eq!(status, 200);
+    assert_eq!(revoked, 2);
+    assert_eq!(get(&base, &first, "/account/accounts").await.0, 401);
+    assert_eq!(get(&base, second_token, "/account/accounts").await.0, 401);
+    assert_eq!(refresh(&base, &second["refresh_token"]).await.0, 401);
+}
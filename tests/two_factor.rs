@@ -0,0 +1,108 @@
+mod common;
+
+use common::{get, login, post, register, spawn_app};
+use serde_json::{json, Value};
+use todo_list::totp;
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// 开启两步验证，返回 (密钥, 恢复码, 确认时使用的时间)
+async fn enable(base: &str, token: &str) -> (String, Vec<String>, i64) {
+    let (status, enrollment) = post(base, token, "/user/2fa/enroll", json!({})).await;
+    assert_eq!(status, 200);
+    let secret = enrollment["secret"].as_str().unwrap().to_string();
+    assert!(enrollment["otpauth_uri"].as_str().unwrap().starts_with("otpauth://totp/"));
+
+    let t0 = now();
+    let (status, _) = post(base, token, "/user/2fa/confirm", json!({"code": "000000x"})).await;
+    assert_eq!(status, 400);
+    let (status, body) = post(base, token, "/user/2fa/confirm", json!({
+        "code": totp::code_at(&secret, t0).unwrap(),
+    })).await;
+    assert_eq!(status, 200);
+    let codes = body["recovery_codes"].as_array().unwrap().iter().map(|c| c.as_str().unwrap().to_string()).collect();
+    (secret, codes, t0)
+}
+
+async fn challenge(base: &str) -> String {
+    let (status, body) = login(base, "alice", "password123").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["two_factor_required"], true);
+    assert!(body.get("token").is_none());
+    body["challenge_token"].as_str().unwrap().to_string()
+}
+
+async fn verify(base: &str, challenge: &str, code: &str) -> (u16, Value) {
+    post(base, "", "/user/2fa/verify", json!({"challenge_token": challenge, "code": code})).await
+}
+
+#[tokio::test]
+async fn login_requires_totp_code_after_enrolling() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let (secret, _, t0) = enable(&base, &token).await;
+    let (_, me) = get(&base, &token, "/user/me").await;
+    assert_eq!(me["two_factor_enabled"], true);
+
+    let challenge = challenge(&base).await;
+    // 挑战令牌不能当作访问令牌使用
+    assert_eq!(get(&base, &challenge, "/user/me").await.0, 401);
+    assert_eq!(verify(&base, &challenge, "123456").await.0, 401);
+    let next_code = totp::code_at(&secret, t0 + 30).unwrap();
+    let (status, tokens) = verify(&base, &challenge, &next_code).await;
+    assert_eq!(status, 200);
+    assert_eq!(get(&base, tokens["token"].as_str().unwrap(), "/user/me").await.0, 200);
+
+    // 同一个验证码不能重复使用
+    assert_eq!(verify(&base, &challenge, &next_code).await.0, 401);
+    assert_eq!(verify(&base, &token, &next_code).await.0, 401);
+}
+
+#[tokio::test]
+async fn recovery_codes_work_once_and_2fa_can_be_disabled() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let (_, codes, _) = enable(&base, &token).await;
+    assert_eq!(codes.len(), 10);
+    assert_eq!(post(&base, &token, "/user/2fa/enroll", json!({})).await.0, 409);
+
+    let challenge = challenge(&base).await;
+    assert_eq!(verify(&base, &challenge, &codes[0].to_uppercase()).await.0, 200);
+    assert_eq!(verify(&base, &challenge, &codes[0]).await.0, 401);
+
+    let (status, _) = post(&base, &token, "/user/2fa/disable", json!({"password": "password123", "code": "bad"})).await;
+    assert_eq!(status, 401);
+    let (status, _) = post(&base, &token, "/user/2fa/disable", json!({"password": "password123", "code": codes[1]})).await;
+    assert_eq!(status, 200);
+    let (_, body) = login(&base, "alice", "password123").await;
+    assert!(body["token"].is_string());
+}
+
+#[tokio::test]
+async fn challenge_token_can_only_be_exchanged_once() {
+    let base = spawn_app().await;
+    let token = register(&base, "alice").await;
+    let (_, codes, _) = enable(&base, &token).await;
+
+    let used = challenge(&base).await;
+    assert_eq!(verify(&base, &used, &codes[0]).await.0, 200);
+    // 即使验证码有效，已用过的挑战令牌也不能再换取令牌
+    let (status, body) = verify(&base, &used, &codes[1]).await;
+    assert_eq!(status, 401);
+    assert_eq!(body["code"], "unauthorized");
+
+    let fresh = challenge(&base).await;
+    assert_eq!(verify(&base, &fresh, &codes[2]).await.0, 200);
+}
+
+#[test]
+fn totp_matches_rfc6238_test_vector() {
+    // RFC 6238 附录 B，密钥 "12345678901234567890" 的 Base32 编码，取 8 位结果的后 6 位
+    let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    assert_eq!(totp::code_at(secret, 59).unwrap(), "287082");
+    assert_eq!(totp::code_at(secret, 1111111109).unwrap(), "081804");
+    assert_eq!(totp::verify(secret, "081804", 1111111109 + 30), Some(1111111109 / 30));
+    assert_eq!(totp::verify(secret, "081804", 1111111109 + 90), None);
+}